@@ -1,6 +1,6 @@
 use crate::cli::config_parser::ConfigParser;
 use crate::cli::tool_detector::Tool;
-use crate::config::schema::Features;
+use crate::config::schema::{Features, ToolMode};
 use crate::config::{IntermediateServerConfig, McpServerConfig, ServerConfig};
 use anyhow::{anyhow, Context, Result};
 use std::collections::HashMap;
@@ -85,6 +85,10 @@ pub async fn run_import_from_tool(
     let imported_config = ServerConfig {
         mcp_servers: imported_servers,
         schema: None,
+        namespacing: false,
+        dynamic_loading: false,
+        tool_mode: ToolMode::default(),
+        repair_arguments: false,
     };
 
     let output_json = serde_json::to_string_pretty(&imported_config)
@@ -294,6 +298,16 @@ fn apply_features_to_config(config: McpServerConfig, features: Features) -> McpS
             env,
             enabled,
             timeout,
+            max_concurrent_batch_calls,
+            tool_overrides,
+            resources,
+            resource_templates,
+            prompts,
+            tools,
+            tool_mode,
+            pinned_tools,
+            schema_compaction,
+            tool_schemas,
             ..
         } => McpServerConfig::Stdio {
             description,
@@ -303,6 +317,16 @@ fn apply_features_to_config(config: McpServerConfig, features: Features) -> McpS
             features,
             enabled,
             timeout,
+            max_concurrent_batch_calls,
+            tool_overrides,
+            resources,
+            resource_templates,
+            prompts,
+            tools,
+            tool_mode,
+            pinned_tools,
+            schema_compaction,
+            tool_schemas,
         },
         McpServerConfig::Http {
             description,
@@ -312,6 +336,16 @@ fn apply_features_to_config(config: McpServerConfig, features: Features) -> McpS
             oauth_scopes,
             enabled,
             timeout,
+            max_concurrent_batch_calls,
+            tool_overrides,
+            resources,
+            resource_templates,
+            prompts,
+            tools,
+            tool_mode,
+            pinned_tools,
+            schema_compaction,
+            tool_schemas,
             ..
         } => McpServerConfig::Http {
             description,
@@ -322,6 +356,16 @@ fn apply_features_to_config(config: McpServerConfig, features: Features) -> McpS
             features,
             enabled,
             timeout,
+            max_concurrent_batch_calls,
+            tool_overrides,
+            resources,
+            resource_templates,
+            prompts,
+            tools,
+            tool_mode,
+            pinned_tools,
+            schema_compaction,
+            tool_schemas,
         },
         McpServerConfig::Sse {
             description,
@@ -331,6 +375,16 @@ fn apply_features_to_config(config: McpServerConfig, features: Features) -> McpS
             oauth_scopes,
             enabled,
             timeout,
+            max_concurrent_batch_calls,
+            tool_overrides,
+            resources,
+            resource_templates,
+            prompts,
+            tools,
+            tool_mode,
+            pinned_tools,
+            schema_compaction,
+            tool_schemas,
             ..
         } => McpServerConfig::Sse {
             description,
@@ -341,6 +395,16 @@ fn apply_features_to_config(config: McpServerConfig, features: Features) -> McpS
             features,
             enabled,
             timeout,
+            max_concurrent_batch_calls,
+            tool_overrides,
+            resources,
+            resource_templates,
+            prompts,
+            tools,
+            tool_mode,
+            pinned_tools,
+            schema_compaction,
+            tool_schemas,
         },
     }
 }
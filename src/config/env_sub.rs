@@ -42,6 +42,16 @@ pub fn substitute_in_config(config: McpServerConfig) -> McpServerConfig {
             features,
             enabled,
             timeout,
+            max_concurrent_batch_calls,
+            tool_overrides,
+            resources,
+            resource_templates,
+            prompts,
+            tools,
+            tool_mode,
+            pinned_tools,
+            schema_compaction,
+            tool_schemas,
         } => McpServerConfig::Stdio {
             description,
             command,
@@ -50,6 +60,16 @@ pub fn substitute_in_config(config: McpServerConfig) -> McpServerConfig {
             features,
             enabled,
             timeout,
+            max_concurrent_batch_calls,
+            tool_overrides,
+            resources,
+            resource_templates,
+            prompts,
+            tools,
+            tool_mode,
+            pinned_tools,
+            schema_compaction,
+            tool_schemas,
         },
         McpServerConfig::Http {
             description,
@@ -60,6 +80,16 @@ pub fn substitute_in_config(config: McpServerConfig) -> McpServerConfig {
             features,
             enabled,
             timeout,
+            max_concurrent_batch_calls,
+            tool_overrides,
+            resources,
+            resource_templates,
+            prompts,
+            tools,
+            tool_mode,
+            pinned_tools,
+            schema_compaction,
+            tool_schemas,
         } => McpServerConfig::Http {
             description,
             url: substitute_env_vars(&url),
@@ -69,6 +99,16 @@ pub fn substitute_in_config(config: McpServerConfig) -> McpServerConfig {
             features,
             enabled,
             timeout,
+            max_concurrent_batch_calls,
+            tool_overrides,
+            resources,
+            resource_templates,
+            prompts,
+            tools,
+            tool_mode,
+            pinned_tools,
+            schema_compaction,
+            tool_schemas,
         },
         McpServerConfig::Sse {
             description,
@@ -79,6 +119,16 @@ pub fn substitute_in_config(config: McpServerConfig) -> McpServerConfig {
             features,
             enabled,
             timeout,
+            max_concurrent_batch_calls,
+            tool_overrides,
+            resources,
+            resource_templates,
+            prompts,
+            tools,
+            tool_mode,
+            pinned_tools,
+            schema_compaction,
+            tool_schemas,
         } => McpServerConfig::Sse {
             description,
             url: substitute_env_vars(&url),
@@ -88,6 +138,16 @@ pub fn substitute_in_config(config: McpServerConfig) -> McpServerConfig {
             features,
             enabled,
             timeout,
+            max_concurrent_batch_calls,
+            tool_overrides,
+            resources,
+            resource_templates,
+            prompts,
+            tools,
+            tool_mode,
+            pinned_tools,
+            schema_compaction,
+            tool_schemas,
         },
     }
 }
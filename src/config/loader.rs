@@ -441,6 +441,29 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[tokio::test]
+    async fn test_load_config_rejects_conflicting_tool_overrides() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        let config_json = r#"{
+            "mcpServers": {
+                "github": {
+                    "description": "GitHub",
+                    "command": "github-mcp",
+                    "tool_overrides": {
+                        "search_code": {"aliases": ["search"]},
+                        "search_issues": {"name": "search"}
+                    }
+                }
+            }
+        }"#;
+        temp_file.write_all(config_json.as_bytes()).unwrap();
+        temp_file.flush().unwrap();
+
+        let result = load_config(temp_file.path().to_str().unwrap()).await;
+        let error = result.unwrap_err().to_string();
+        assert!(error.contains("search is a name or alias of both search_code and search_issues"));
+    }
+
     #[tokio::test]
     async fn test_load_config_with_optional_fields_valid() {
         let mut temp_file = NamedTempFile::new().unwrap();
@@ -1,6 +1,7 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Deserializer, Serialize};
 use std::collections::HashMap;
+use std::num::NonZeroUsize;
 use std::time::Duration;
 
 /// Default tool call timeout in seconds
@@ -9,6 +10,10 @@ const DEFAULT_TOOL_TIMEOUT_SECS: u64 = 30;
 /// Default resource/prompt call timeout in seconds
 const DEFAULT_RESOURCE_PROMPT_TIMEOUT_SECS: u64 = 10;
 
+/// Default number of calls of one `call_dynamic_tools` batch running at the same time on a
+/// single server
+const DEFAULT_MAX_CONCURRENT_BATCH_CALLS: usize = 4;
+
 /// Parse a duration string like "30s", "1min", "3000ms" into a Duration
 fn parse_duration(s: &str) -> Result<Duration, String> {
     let s = s.trim().to_lowercase();
@@ -77,6 +82,14 @@ pub struct Timeout {
     pub resources: Option<Duration>,
     #[serde(default, deserialize_with = "deserialize_resource_prompt_timeout")]
     pub prompts: Option<Duration>,
+    /// Restart the tool call timeout whenever the server reports progress.
+    /// Only stdio servers can report progress; HTTP and SSE calls keep a fixed timeout.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub reset_on_progress: bool,
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
 }
 
 /// Custom deserializer for tools timeout that accepts Duration or string
@@ -140,7 +153,10 @@ impl Timeout {
 
     /// Returns true if all timeouts are using defaults (None)
     pub fn is_default(&self) -> bool {
-        self.tools.is_none() && self.resources.is_none() && self.prompts.is_none()
+        self.tools.is_none()
+            && self.resources.is_none()
+            && self.prompts.is_none()
+            && !self.reset_on_progress
     }
 }
 
@@ -177,6 +193,169 @@ impl Features {
     }
 }
 
+/// Glob patterns picking which of a server's items are exposed. `*` matches any run of
+/// characters and `?` any single one.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct NameFilter {
+    /// Only items matching one of these are exposed; all are when empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allow: Vec<String>,
+    /// Items matching one of these are hidden, even when allowed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deny: Vec<String>,
+}
+
+impl NameFilter {
+    pub fn is_empty(&self) -> bool {
+        self.allow.is_empty() && self.deny.is_empty()
+    }
+
+    /// Whether `name` passes the filter
+    pub fn allows(&self, name: &str) -> bool {
+        (self.allow.is_empty() || self.allow.iter().any(|p| glob_matches(p, name)))
+            && !self.deny.iter().any(|p| glob_matches(p, name))
+    }
+}
+
+/// Whether `name` matches the glob `pattern` as a whole
+fn glob_matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Where the last `*` was, and the name position it currently stands in for
+    let mut backtrack = None;
+
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                // Let the last `*` swallow one more character
+                Some((star, start)) => {
+                    p = star + 1;
+                    n = start + 1;
+                    backtrack = Some((star, start + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Changes to how one upstream tool is presented, keyed by its upstream name in
+/// `tool_overrides`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ToolOverride {
+    /// Name the tool is listed and called under instead of its upstream name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Further names the tool can be called by, without being listed under them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+    /// Replaces the upstream description
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Added after the (possibly replaced) description
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub append_description: Option<String>,
+    /// Replaces the descriptions of top-level `inputSchema` properties, by property name
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub parameter_descriptions: HashMap<String, String>,
+}
+
+/// Check that every name a server's tools are called by belongs to one tool: new names and
+/// aliases in `tool_overrides` may not repeat each other or name another overridden tool
+fn check_tool_overrides(overrides: &HashMap<String, ToolOverride>) -> Result<(), String> {
+    let mut upstream_names: Vec<&String> = overrides.keys().collect();
+    upstream_names.sort();
+
+    let mut owners: HashMap<&str, &str> = HashMap::new();
+    for upstream in upstream_names {
+        let tool = &overrides[upstream];
+        for name in tool.name.iter().chain(&tool.aliases) {
+            if name != upstream && overrides.contains_key(name) {
+                return Err(format!(
+                    "tool_overrides: {} is given the name of another tool, {}",
+                    upstream, name
+                ));
+            }
+            match owners.insert(name, upstream) {
+                Some(other) if other != upstream => {
+                    return Err(format!(
+                        "tool_overrides: {} is a name or alias of both {} and {}",
+                        name, other, upstream
+                    ));
+                }
+                _ => {}
+            }
+        }
+    }
+    Ok(())
+}
+
+/// How a group's tools are offered to the client
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ToolMode {
+    /// Behind the `get_dynamic_tools` and `call_dynamic_tool` meta-tools
+    #[default]
+    Dynamic,
+    /// Every tool listed directly in `tools/list` as `<group>__<tool>`
+    Flat,
+}
+
+impl ToolMode {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// How `get_dynamic_tools` discloses a group's tool schemas
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ToolSchemas {
+    /// Every tool with its full input schema
+    #[default]
+    Full,
+    /// Tool names and one-line descriptions; schemas come from `get_dynamic_tool_schema`
+    Compact,
+}
+
+impl ToolSchemas {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// How much tool schemas are minified before they are shown to the LLM
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SchemaCompaction {
+    /// Schemas as the server sent them, pretty-printed
+    #[default]
+    Off,
+    /// Compact JSON without titles, examples, comments, `additionalProperties: false` and
+    /// `$defs` indirections
+    Basic,
+    /// Like `Basic`, and also cuts long descriptions and lists long enums in the description
+    Aggressive,
+}
+
+impl SchemaCompaction {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
 fn default_true_enabled() -> bool {
     true
 }
@@ -198,6 +377,26 @@ pub enum McpServerConfig {
         enabled: bool,
         #[serde(default, skip_serializing_if = "Timeout::is_default")]
         timeout: Timeout,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        max_concurrent_batch_calls: Option<NonZeroUsize>,
+        #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+        tool_overrides: HashMap<String, ToolOverride>,
+        #[serde(default, skip_serializing_if = "NameFilter::is_empty")]
+        resources: NameFilter,
+        #[serde(default, skip_serializing_if = "NameFilter::is_empty")]
+        resource_templates: NameFilter,
+        #[serde(default, skip_serializing_if = "NameFilter::is_empty")]
+        prompts: NameFilter,
+        #[serde(default, skip_serializing_if = "NameFilter::is_empty")]
+        tools: NameFilter,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        tool_mode: Option<ToolMode>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        pinned_tools: Vec<String>,
+        #[serde(default, skip_serializing_if = "SchemaCompaction::is_default")]
+        schema_compaction: SchemaCompaction,
+        #[serde(default, skip_serializing_if = "ToolSchemas::is_default")]
+        tool_schemas: ToolSchemas,
     },
     Http {
         description: String,
@@ -214,6 +413,26 @@ pub enum McpServerConfig {
         enabled: bool,
         #[serde(default, skip_serializing_if = "Timeout::is_default")]
         timeout: Timeout,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        max_concurrent_batch_calls: Option<NonZeroUsize>,
+        #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+        tool_overrides: HashMap<String, ToolOverride>,
+        #[serde(default, skip_serializing_if = "NameFilter::is_empty")]
+        resources: NameFilter,
+        #[serde(default, skip_serializing_if = "NameFilter::is_empty")]
+        resource_templates: NameFilter,
+        #[serde(default, skip_serializing_if = "NameFilter::is_empty")]
+        prompts: NameFilter,
+        #[serde(default, skip_serializing_if = "NameFilter::is_empty")]
+        tools: NameFilter,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        tool_mode: Option<ToolMode>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        pinned_tools: Vec<String>,
+        #[serde(default, skip_serializing_if = "SchemaCompaction::is_default")]
+        schema_compaction: SchemaCompaction,
+        #[serde(default, skip_serializing_if = "ToolSchemas::is_default")]
+        tool_schemas: ToolSchemas,
     },
     Sse {
         description: String,
@@ -230,6 +449,26 @@ pub enum McpServerConfig {
         enabled: bool,
         #[serde(default, skip_serializing_if = "Timeout::is_default")]
         timeout: Timeout,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        max_concurrent_batch_calls: Option<NonZeroUsize>,
+        #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+        tool_overrides: HashMap<String, ToolOverride>,
+        #[serde(default, skip_serializing_if = "NameFilter::is_empty")]
+        resources: NameFilter,
+        #[serde(default, skip_serializing_if = "NameFilter::is_empty")]
+        resource_templates: NameFilter,
+        #[serde(default, skip_serializing_if = "NameFilter::is_empty")]
+        prompts: NameFilter,
+        #[serde(default, skip_serializing_if = "NameFilter::is_empty")]
+        tools: NameFilter,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        tool_mode: Option<ToolMode>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        pinned_tools: Vec<String>,
+        #[serde(default, skip_serializing_if = "SchemaCompaction::is_default")]
+        schema_compaction: SchemaCompaction,
+        #[serde(default, skip_serializing_if = "ToolSchemas::is_default")]
+        tool_schemas: ToolSchemas,
     },
 }
 
@@ -277,6 +516,26 @@ impl<'de> Deserialize<'de> for McpServerConfig {
                 enabled: bool,
                 #[serde(default)]
                 timeout: Timeout,
+                #[serde(default)]
+                max_concurrent_batch_calls: Option<NonZeroUsize>,
+                #[serde(default)]
+                tool_overrides: HashMap<String, ToolOverride>,
+                #[serde(default)]
+                resources: NameFilter,
+                #[serde(default)]
+                resource_templates: NameFilter,
+                #[serde(default)]
+                prompts: NameFilter,
+                #[serde(default)]
+                tools: NameFilter,
+                #[serde(default)]
+                tool_mode: Option<ToolMode>,
+                #[serde(default)]
+                pinned_tools: Vec<String>,
+                #[serde(default)]
+                schema_compaction: SchemaCompaction,
+                #[serde(default)]
+                tool_schemas: ToolSchemas,
             },
             Http {
                 description: String,
@@ -290,6 +549,26 @@ impl<'de> Deserialize<'de> for McpServerConfig {
                 enabled: bool,
                 #[serde(default)]
                 timeout: Timeout,
+                #[serde(default)]
+                max_concurrent_batch_calls: Option<NonZeroUsize>,
+                #[serde(default)]
+                tool_overrides: HashMap<String, ToolOverride>,
+                #[serde(default)]
+                resources: NameFilter,
+                #[serde(default)]
+                resource_templates: NameFilter,
+                #[serde(default)]
+                prompts: NameFilter,
+                #[serde(default)]
+                tools: NameFilter,
+                #[serde(default)]
+                tool_mode: Option<ToolMode>,
+                #[serde(default)]
+                pinned_tools: Vec<String>,
+                #[serde(default)]
+                schema_compaction: SchemaCompaction,
+                #[serde(default)]
+                tool_schemas: ToolSchemas,
             },
             Sse {
                 description: String,
@@ -303,10 +582,30 @@ impl<'de> Deserialize<'de> for McpServerConfig {
                 enabled: bool,
                 #[serde(default)]
                 timeout: Timeout,
+                #[serde(default)]
+                max_concurrent_batch_calls: Option<NonZeroUsize>,
+                #[serde(default)]
+                tool_overrides: HashMap<String, ToolOverride>,
+                #[serde(default)]
+                resources: NameFilter,
+                #[serde(default)]
+                resource_templates: NameFilter,
+                #[serde(default)]
+                prompts: NameFilter,
+                #[serde(default)]
+                tools: NameFilter,
+                #[serde(default)]
+                tool_mode: Option<ToolMode>,
+                #[serde(default)]
+                pinned_tools: Vec<String>,
+                #[serde(default)]
+                schema_compaction: SchemaCompaction,
+                #[serde(default)]
+                tool_schemas: ToolSchemas,
             },
         }
 
-        match serde_json::from_value::<McpServerConfigHelper>(value)
+        let config = match serde_json::from_value::<McpServerConfigHelper>(value)
             .map_err(serde::de::Error::custom)?
         {
             McpServerConfigHelper::Stdio {
@@ -317,7 +616,17 @@ impl<'de> Deserialize<'de> for McpServerConfig {
                 features,
                 enabled,
                 timeout,
-            } => Ok(McpServerConfig::Stdio {
+                max_concurrent_batch_calls,
+                tool_overrides,
+                resources,
+                resource_templates,
+                prompts,
+                tools,
+                tool_mode,
+                pinned_tools,
+                schema_compaction,
+                tool_schemas,
+            } => McpServerConfig::Stdio {
                 description,
                 command,
                 args,
@@ -325,7 +634,17 @@ impl<'de> Deserialize<'de> for McpServerConfig {
                 features,
                 enabled,
                 timeout,
-            }),
+                max_concurrent_batch_calls,
+                tool_overrides,
+                resources,
+                resource_templates,
+                prompts,
+                tools,
+                tool_mode,
+                pinned_tools,
+                schema_compaction,
+                tool_schemas,
+            },
             McpServerConfigHelper::Http {
                 description,
                 url,
@@ -335,7 +654,17 @@ impl<'de> Deserialize<'de> for McpServerConfig {
                 features,
                 enabled,
                 timeout,
-            } => Ok(McpServerConfig::Http {
+                max_concurrent_batch_calls,
+                tool_overrides,
+                resources,
+                resource_templates,
+                prompts,
+                tools,
+                tool_mode,
+                pinned_tools,
+                schema_compaction,
+                tool_schemas,
+            } => McpServerConfig::Http {
                 description,
                 url,
                 headers,
@@ -344,7 +673,17 @@ impl<'de> Deserialize<'de> for McpServerConfig {
                 features,
                 enabled,
                 timeout,
-            }),
+                max_concurrent_batch_calls,
+                tool_overrides,
+                resources,
+                resource_templates,
+                prompts,
+                tools,
+                tool_mode,
+                pinned_tools,
+                schema_compaction,
+                tool_schemas,
+            },
             McpServerConfigHelper::Sse {
                 description,
                 url,
@@ -354,7 +693,17 @@ impl<'de> Deserialize<'de> for McpServerConfig {
                 features,
                 enabled,
                 timeout,
-            } => Ok(McpServerConfig::Sse {
+                max_concurrent_batch_calls,
+                tool_overrides,
+                resources,
+                resource_templates,
+                prompts,
+                tools,
+                tool_mode,
+                pinned_tools,
+                schema_compaction,
+                tool_schemas,
+            } => McpServerConfig::Sse {
                 description,
                 url,
                 headers,
@@ -363,8 +712,21 @@ impl<'de> Deserialize<'de> for McpServerConfig {
                 features,
                 enabled,
                 timeout,
-            }),
-        }
+                max_concurrent_batch_calls,
+                tool_overrides,
+                resources,
+                resource_templates,
+                prompts,
+                tools,
+                tool_mode,
+                pinned_tools,
+                schema_compaction,
+                tool_schemas,
+            },
+        };
+
+        check_tool_overrides(config.tool_overrides()).map_err(serde::de::Error::custom)?;
+        Ok(config)
     }
 }
 
@@ -385,6 +747,128 @@ impl McpServerConfig {
         }
     }
 
+    pub fn tool_schemas(&self) -> ToolSchemas {
+        match self {
+            McpServerConfig::Stdio { tool_schemas, .. } => *tool_schemas,
+            McpServerConfig::Http { tool_schemas, .. } => *tool_schemas,
+            McpServerConfig::Sse { tool_schemas, .. } => *tool_schemas,
+        }
+    }
+
+    pub fn schema_compaction(&self) -> SchemaCompaction {
+        match self {
+            McpServerConfig::Stdio {
+                schema_compaction, ..
+            } => *schema_compaction,
+            McpServerConfig::Http {
+                schema_compaction, ..
+            } => *schema_compaction,
+            McpServerConfig::Sse {
+                schema_compaction, ..
+            } => *schema_compaction,
+        }
+    }
+
+    /// The server's own `tool_mode`, overriding the top-level one
+    pub fn tool_mode(&self) -> Option<ToolMode> {
+        match self {
+            McpServerConfig::Stdio { tool_mode, .. } => *tool_mode,
+            McpServerConfig::Http { tool_mode, .. } => *tool_mode,
+            McpServerConfig::Sse { tool_mode, .. } => *tool_mode,
+        }
+    }
+
+    /// Which of the server's tools are exposed
+    pub fn tool_filter(&self) -> &NameFilter {
+        match self {
+            McpServerConfig::Stdio { tools, .. } => tools,
+            McpServerConfig::Http { tools, .. } => tools,
+            McpServerConfig::Sse { tools, .. } => tools,
+        }
+    }
+
+    /// Which of the server's resource URIs are exposed
+    pub fn resource_filter(&self) -> &NameFilter {
+        match self {
+            McpServerConfig::Stdio { resources, .. } => resources,
+            McpServerConfig::Http { resources, .. } => resources,
+            McpServerConfig::Sse { resources, .. } => resources,
+        }
+    }
+
+    /// Which of the server's resource URI templates are exposed
+    pub fn resource_template_filter(&self) -> &NameFilter {
+        match self {
+            McpServerConfig::Stdio {
+                resource_templates, ..
+            } => resource_templates,
+            McpServerConfig::Http {
+                resource_templates, ..
+            } => resource_templates,
+            McpServerConfig::Sse {
+                resource_templates, ..
+            } => resource_templates,
+        }
+    }
+
+    /// Which of the server's prompts are exposed
+    pub fn prompt_filter(&self) -> &NameFilter {
+        match self {
+            McpServerConfig::Stdio { prompts, .. } => prompts,
+            McpServerConfig::Http { prompts, .. } => prompts,
+            McpServerConfig::Sse { prompts, .. } => prompts,
+        }
+    }
+
+    pub fn tool_overrides(&self) -> &HashMap<String, ToolOverride> {
+        match self {
+            McpServerConfig::Stdio { tool_overrides, .. } => tool_overrides,
+            McpServerConfig::Http { tool_overrides, .. } => tool_overrides,
+            McpServerConfig::Sse { tool_overrides, .. } => tool_overrides,
+        }
+    }
+
+    pub fn tool_overrides_mut(&mut self) -> &mut HashMap<String, ToolOverride> {
+        match self {
+            McpServerConfig::Stdio { tool_overrides, .. } => tool_overrides,
+            McpServerConfig::Http { tool_overrides, .. } => tool_overrides,
+            McpServerConfig::Sse { tool_overrides, .. } => tool_overrides,
+        }
+    }
+
+    /// The upstream name of the tool called `name`, which may be the name or an alias it
+    /// was given in `tool_overrides`. `None` for the upstream name of a renamed tool.
+    pub fn upstream_tool_name<'a>(&'a self, name: &'a str) -> Option<&'a str> {
+        let overrides = self.tool_overrides();
+        let renamed = overrides.iter().find(|(_, tool)| {
+            tool.name.as_deref() == Some(name) || tool.aliases.iter().any(|alias| alias == name)
+        });
+        match renamed {
+            Some((upstream, _)) => Some(upstream),
+            None if overrides.get(name).is_some_and(|tool| tool.name.is_some()) => None,
+            None => Some(name),
+        }
+    }
+
+    /// The name the tool called `name` is listed under
+    pub fn listed_tool_name<'a>(&'a self, name: &'a str) -> Option<&'a str> {
+        let upstream = self.upstream_tool_name(name)?;
+        Some(
+            self.tool_overrides()
+                .get(upstream)
+                .and_then(|tool| tool.name.as_deref())
+                .unwrap_or(upstream),
+        )
+    }
+
+    pub fn pinned_tools(&self) -> &[String] {
+        match self {
+            McpServerConfig::Stdio { pinned_tools, .. } => pinned_tools,
+            McpServerConfig::Http { pinned_tools, .. } => pinned_tools,
+            McpServerConfig::Sse { pinned_tools, .. } => pinned_tools,
+        }
+    }
+
     pub fn is_enabled(&self) -> bool {
         match self {
             McpServerConfig::Stdio { enabled, .. } => *enabled,
@@ -401,6 +885,15 @@ impl McpServerConfig {
         }
     }
 
+    /// Whether progress notifications restart the tool call timeout
+    pub fn resets_timeout_on_progress(&self) -> bool {
+        match self {
+            McpServerConfig::Stdio { timeout, .. } => timeout.reset_on_progress,
+            McpServerConfig::Http { timeout, .. } => timeout.reset_on_progress,
+            McpServerConfig::Sse { timeout, .. } => timeout.reset_on_progress,
+        }
+    }
+
     pub fn resource_timeout(&self) -> Duration {
         match self {
             McpServerConfig::Stdio { timeout, .. } => timeout.resource_timeout(),
@@ -416,6 +909,26 @@ impl McpServerConfig {
             McpServerConfig::Sse { timeout, .. } => timeout.prompt_timeout(),
         }
     }
+
+    /// Calls of one `call_dynamic_tools` batch allowed to run on this server at the same
+    /// time, returning the default if not configured
+    pub fn max_concurrent_batch_calls(&self) -> usize {
+        let configured = match self {
+            McpServerConfig::Stdio {
+                max_concurrent_batch_calls,
+                ..
+            } => max_concurrent_batch_calls,
+            McpServerConfig::Http {
+                max_concurrent_batch_calls,
+                ..
+            } => max_concurrent_batch_calls,
+            McpServerConfig::Sse {
+                max_concurrent_batch_calls,
+                ..
+            } => max_concurrent_batch_calls,
+        };
+        configured.map_or(DEFAULT_MAX_CONCURRENT_BATCH_CALLS, NonZeroUsize::get)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
@@ -425,6 +938,18 @@ pub struct ServerConfig {
     pub mcp_servers: HashMap<String, McpServerConfig>,
     #[serde(skip_serializing_if = "Option::is_none", rename = "$schema")]
     pub schema: Option<String>,
+    /// Prefix aggregated resource URIs and prompt names with their group
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub namespacing: bool,
+    /// Add a group's tools to `tools/list` once `get_dynamic_tools` opened it
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub dynamic_loading: bool,
+    /// How tools are offered for servers that do not set their own `tool_mode`
+    #[serde(default, skip_serializing_if = "ToolMode::is_default")]
+    pub tool_mode: ToolMode,
+    /// Fix up malformed `call_dynamic_tool` arguments instead of forwarding them as sent
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub repair_arguments: bool,
 }
 
 /// Intermediate representation for migration from various tools
@@ -466,6 +991,16 @@ impl IntermediateServerConfig {
                     features: Features::default(),
                     enabled,
                     timeout: Timeout::default(),
+                    max_concurrent_batch_calls: None,
+                    tool_overrides: HashMap::new(),
+                    resources: NameFilter::default(),
+                    resource_templates: NameFilter::default(),
+                    prompts: NameFilter::default(),
+                    tools: NameFilter::default(),
+                    tool_mode: None,
+                    pinned_tools: Vec::new(),
+                    schema_compaction: SchemaCompaction::default(),
+                    tool_schemas: ToolSchemas::default(),
                 })
             } else {
                 Ok(McpServerConfig::Http {
@@ -477,6 +1012,16 @@ impl IntermediateServerConfig {
                     features: Features::default(),
                     enabled,
                     timeout: Timeout::default(),
+                    max_concurrent_batch_calls: None,
+                    tool_overrides: HashMap::new(),
+                    resources: NameFilter::default(),
+                    resource_templates: NameFilter::default(),
+                    prompts: NameFilter::default(),
+                    tools: NameFilter::default(),
+                    tool_mode: None,
+                    pinned_tools: Vec::new(),
+                    schema_compaction: SchemaCompaction::default(),
+                    tool_schemas: ToolSchemas::default(),
                 })
             }
         } else if let Some(command) = self.command {
@@ -488,6 +1033,16 @@ impl IntermediateServerConfig {
                 features: Features::default(),
                 enabled,
                 timeout: Timeout::default(),
+                max_concurrent_batch_calls: None,
+                tool_overrides: HashMap::new(),
+                resources: NameFilter::default(),
+                resource_templates: NameFilter::default(),
+                prompts: NameFilter::default(),
+                tools: NameFilter::default(),
+                tool_mode: None,
+                pinned_tools: Vec::new(),
+                schema_compaction: SchemaCompaction::default(),
+                tool_schemas: ToolSchemas::default(),
             })
         } else {
             Err("Server config must have either 'command' or 'url'".to_string())
@@ -634,6 +1189,16 @@ mod tests {
             features: Features::default(),
             enabled: true,
             timeout: Timeout::default(),
+            max_concurrent_batch_calls: None,
+            tool_overrides: HashMap::new(),
+            resources: NameFilter::default(),
+            resource_templates: NameFilter::default(),
+            prompts: NameFilter::default(),
+            tools: NameFilter::default(),
+            tool_mode: None,
+            pinned_tools: Vec::new(),
+            schema_compaction: SchemaCompaction::default(),
+            tool_schemas: ToolSchemas::default(),
         };
 
         let serialized = serde_json::to_value(&config).unwrap();
@@ -657,6 +1222,16 @@ mod tests {
             },
             enabled: true,
             timeout: Timeout::default(),
+            max_concurrent_batch_calls: None,
+            tool_overrides: HashMap::new(),
+            resources: NameFilter::default(),
+            resource_templates: NameFilter::default(),
+            prompts: NameFilter::default(),
+            tools: NameFilter::default(),
+            tool_mode: None,
+            pinned_tools: Vec::new(),
+            schema_compaction: SchemaCompaction::default(),
+            tool_schemas: ToolSchemas::default(),
         };
 
         let serialized = serde_json::to_value(&config).unwrap();
@@ -747,6 +1322,288 @@ mod tests {
         assert!(result.unwrap_err().to_string().contains("unknown field"));
     }
 
+    #[test]
+    fn test_server_config_namespacing() {
+        let json = json!({"mcpServers": {}});
+        let config: ServerConfig = serde_json::from_value(json).unwrap();
+        assert!(!config.namespacing);
+
+        let json = json!({"mcpServers": {}, "namespacing": true});
+        let config: ServerConfig = serde_json::from_value(json).unwrap();
+        assert!(config.namespacing);
+    }
+
+    #[test]
+    fn test_server_config_dynamic_loading() {
+        let json = json!({"mcpServers": {}});
+        let config: ServerConfig = serde_json::from_value(json).unwrap();
+        assert!(!config.dynamic_loading);
+
+        let json = json!({"mcpServers": {}, "dynamic_loading": true});
+        let config: ServerConfig = serde_json::from_value(json).unwrap();
+        assert!(config.dynamic_loading);
+    }
+
+    #[test]
+    fn test_server_config_repair_arguments() {
+        let json = json!({"mcpServers": {}});
+        let config: ServerConfig = serde_json::from_value(json).unwrap();
+        assert!(!config.repair_arguments);
+
+        let json = json!({"mcpServers": {}, "repair_arguments": true});
+        let config: ServerConfig = serde_json::from_value(json).unwrap();
+        assert!(config.repair_arguments);
+    }
+
+    #[test]
+    fn test_glob_matches() {
+        assert!(glob_matches("delete_*", "delete_file"));
+        assert!(glob_matches("*_file", "read_file"));
+        assert!(glob_matches("browser_*_tab", "browser_close_tab"));
+        assert!(glob_matches("get_?", "get_x"));
+        assert!(glob_matches("*", ""));
+        assert!(!glob_matches("delete_*", "undelete_file"));
+        assert!(!glob_matches("get_?", "get_xy"));
+        assert!(!glob_matches("read_file", "read_files"));
+        assert!(glob_matches("a*b*c", "aXbYbZc"));
+        assert!(!glob_matches("a*b*c", "aXbYbZ"));
+    }
+
+    #[test]
+    fn test_tool_filter_config() {
+        let json = json!({"description": "Test", "command": "test-cmd"});
+        let config: McpServerConfig = serde_json::from_value(json).unwrap();
+        assert!(config.tool_filter().is_empty());
+        assert!(config.tool_filter().allows("anything"));
+        assert!(serde_json::to_value(&config)
+            .unwrap()
+            .get("tools")
+            .is_none());
+
+        let json = json!({
+            "description": "Test",
+            "command": "test-cmd",
+            "tools": {"allow": ["read_*", "delete_*"], "deny": ["delete_repo"]}
+        });
+        let config: McpServerConfig = serde_json::from_value(json).unwrap();
+        let filter = config.tool_filter();
+        assert!(filter.allows("read_file"));
+        assert!(filter.allows("delete_branch"));
+        assert!(!filter.allows("delete_repo"));
+        assert!(!filter.allows("write_file"));
+
+        let json = json!({"description": "Test", "command": "test-cmd", "tools": {"hide": []}});
+        assert!(serde_json::from_value::<McpServerConfig>(json).is_err());
+    }
+
+    #[test]
+    fn test_resource_and_prompt_filter_config() {
+        let json = json!({
+            "url": "http://localhost",
+            "description": "Test",
+            "resources": {"allow": ["file:///workspace/docs/**"]},
+            "resource_templates": {"deny": ["*{+path}"]},
+            "prompts": {"deny": ["internal_*"]}
+        });
+        let config: McpServerConfig = serde_json::from_value(json).unwrap();
+        assert!(config
+            .resource_filter()
+            .allows("file:///workspace/docs/guide/intro.md"));
+        assert!(!config
+            .resource_filter()
+            .allows("file:///workspace/src/main.rs"));
+        assert!(!config
+            .resource_template_filter()
+            .allows("file:///workspace/{+path}"));
+        assert!(config
+            .resource_template_filter()
+            .allows("file:///logs/{date}.log"));
+        assert!(!config.prompt_filter().allows("internal_review"));
+        assert!(config.prompt_filter().allows("review"));
+
+        let serialized = serde_json::to_value(&config).unwrap();
+        assert!(serialized.get("tools").is_none());
+        assert_eq!(serialized["prompts"], json!({"deny": ["internal_*"]}));
+    }
+
+    #[test]
+    fn test_tool_overrides_config() {
+        let json = json!({
+            "description": "Test",
+            "command": "test-cmd",
+            "tool_overrides": {
+                "search_code": {
+                    "name": "github_search",
+                    "aliases": ["code_search"],
+                    "append_description": "Prefer this over cloning the repository.",
+                    "parameter_descriptions": {"q": "GitHub code search syntax"}
+                },
+                "get_me": {"description": "The signed-in user"}
+            }
+        });
+        let config: McpServerConfig = serde_json::from_value(json).unwrap();
+        let search = &config.tool_overrides()["search_code"];
+        assert_eq!(search.name.as_deref(), Some("github_search"));
+        assert_eq!(
+            search.parameter_descriptions["q"],
+            "GitHub code search syntax"
+        );
+
+        assert_eq!(
+            config.upstream_tool_name("github_search"),
+            Some("search_code")
+        );
+        assert_eq!(
+            config.upstream_tool_name("code_search"),
+            Some("search_code")
+        );
+        assert_eq!(config.upstream_tool_name("search_code"), None);
+        assert_eq!(config.upstream_tool_name("get_me"), Some("get_me"));
+        assert_eq!(config.upstream_tool_name("other"), Some("other"));
+        assert_eq!(
+            config.listed_tool_name("code_search"),
+            Some("github_search")
+        );
+        assert_eq!(config.listed_tool_name("get_me"), Some("get_me"));
+
+        let json = json!({
+            "description": "Test",
+            "command": "test-cmd",
+            "tool_overrides": {"get_me": {"rename": "me"}}
+        });
+        assert!(serde_json::from_value::<McpServerConfig>(json).is_err());
+    }
+
+    #[test]
+    fn test_tool_overrides_reject_conflicting_names() {
+        for tool_overrides in [
+            json!({"search_code": {"name": "get_me"}, "get_me": {}}),
+            json!({"search_code": {"aliases": ["get_me"]}, "get_me": {"name": "me"}}),
+            json!({"search_code": {"name": "search"}, "search_issues": {"name": "search"}}),
+            json!({"search_code": {"aliases": ["find"]}, "search_issues": {"aliases": ["find"]}}),
+            json!({"search_code": {"name": "find"}, "search_issues": {"aliases": ["find"]}}),
+        ] {
+            let json = json!({
+                "description": "Test",
+                "command": "test-cmd",
+                "tool_overrides": tool_overrides
+            });
+            let error = serde_json::from_value::<McpServerConfig>(json).unwrap_err();
+            assert!(error.to_string().contains("tool_overrides"), "{}", error);
+        }
+
+        let json = json!({
+            "description": "Test",
+            "command": "test-cmd",
+            "tool_overrides": {"search_code": {"name": "search_code", "aliases": ["search_code"]}}
+        });
+        assert!(serde_json::from_value::<McpServerConfig>(json).is_ok());
+    }
+
+    #[test]
+    fn test_tool_mode_config() {
+        let json = json!({"mcpServers": {}});
+        let config: ServerConfig = serde_json::from_value(json).unwrap();
+        assert_eq!(config.tool_mode, ToolMode::Dynamic);
+
+        let json = json!({
+            "tool_mode": "flat",
+            "mcpServers": {
+                "inherits": {"description": "Test", "command": "test-cmd"},
+                "own": {"description": "Test", "command": "test-cmd", "tool_mode": "dynamic"}
+            }
+        });
+        let config: ServerConfig = serde_json::from_value(json).unwrap();
+        assert_eq!(config.tool_mode, ToolMode::Flat);
+        assert_eq!(config.mcp_servers["inherits"].tool_mode(), None);
+        assert_eq!(
+            config.mcp_servers["own"].tool_mode(),
+            Some(ToolMode::Dynamic)
+        );
+
+        let json = json!({"description": "Test", "command": "test-cmd", "tool_mode": "native"});
+        assert!(serde_json::from_value::<McpServerConfig>(json).is_err());
+    }
+
+    #[test]
+    fn test_max_concurrent_batch_calls_config() {
+        let json = json!({"description": "Test", "command": "test-cmd"});
+        let config: McpServerConfig = serde_json::from_value(json).unwrap();
+        assert_eq!(config.max_concurrent_batch_calls(), 4);
+        let serialized = serde_json::to_value(&config).unwrap();
+        assert!(serialized.get("max_concurrent_batch_calls").is_none());
+
+        let json = json!({
+            "description": "Test",
+            "url": "http://localhost",
+            "max_concurrent_batch_calls": 1
+        });
+        let config: McpServerConfig = serde_json::from_value(json).unwrap();
+        assert_eq!(config.max_concurrent_batch_calls(), 1);
+        let serialized = serde_json::to_value(&config).unwrap();
+        assert_eq!(serialized["max_concurrent_batch_calls"], 1);
+
+        let json =
+            json!({"description": "Test", "command": "test-cmd", "max_concurrent_batch_calls": 0});
+        assert!(serde_json::from_value::<McpServerConfig>(json).is_err());
+    }
+
+    #[test]
+    fn test_tool_schemas_config() {
+        let json = json!({"description": "Test", "command": "test-cmd"});
+        let config: McpServerConfig = serde_json::from_value(json).unwrap();
+        assert_eq!(config.tool_schemas(), ToolSchemas::Full);
+        let serialized = serde_json::to_value(&config).unwrap();
+        assert!(serialized.get("tool_schemas").is_none());
+
+        let json =
+            json!({"description": "Test", "url": "http://localhost", "tool_schemas": "compact"});
+        let config: McpServerConfig = serde_json::from_value(json).unwrap();
+        assert_eq!(config.tool_schemas(), ToolSchemas::Compact);
+        let serialized = serde_json::to_value(&config).unwrap();
+        assert_eq!(serialized["tool_schemas"], "compact");
+
+        let json = json!({"description": "Test", "command": "test-cmd", "tool_schemas": "tiny"});
+        assert!(serde_json::from_value::<McpServerConfig>(json).is_err());
+    }
+
+    #[test]
+    fn test_schema_compaction_config() {
+        let json = json!({"description": "Test", "command": "test-cmd"});
+        let config: McpServerConfig = serde_json::from_value(json).unwrap();
+        assert_eq!(config.schema_compaction(), SchemaCompaction::Off);
+
+        for (level, expected) in [
+            ("basic", SchemaCompaction::Basic),
+            ("aggressive", SchemaCompaction::Aggressive),
+        ] {
+            let json =
+                json!({"description": "Test", "command": "test-cmd", "schema_compaction": level});
+            let config: McpServerConfig = serde_json::from_value(json).unwrap();
+            assert_eq!(config.schema_compaction(), expected);
+        }
+    }
+
+    #[test]
+    fn test_pinned_tools_config() {
+        let json = json!({"description": "Test", "command": "test-cmd"});
+        let config: McpServerConfig = serde_json::from_value(json).unwrap();
+        assert!(config.pinned_tools().is_empty());
+        assert!(serde_json::to_value(&config)
+            .unwrap()
+            .get("pinned_tools")
+            .is_none());
+
+        let json = json!({
+            "type": "http",
+            "description": "Test",
+            "url": "http://localhost",
+            "pinned_tools": ["search", "fetch"]
+        });
+        let config: McpServerConfig = serde_json::from_value(json).unwrap();
+        assert_eq!(config.pinned_tools(), ["search", "fetch"]);
+    }
+
     #[test]
     fn test_stdio_server_accepts_all_valid_fields() {
         let json = json!({
@@ -875,6 +1732,16 @@ mod tests {
             features: Features::default(),
             enabled: true,
             timeout: Timeout::default(),
+            max_concurrent_batch_calls: None,
+            tool_overrides: HashMap::new(),
+            resources: NameFilter::default(),
+            resource_templates: NameFilter::default(),
+            prompts: NameFilter::default(),
+            tools: NameFilter::default(),
+            tool_mode: None,
+            pinned_tools: Vec::new(),
+            schema_compaction: SchemaCompaction::default(),
+            tool_schemas: ToolSchemas::default(),
         };
 
         let serialized = serde_json::to_value(&config).unwrap();
@@ -894,6 +1761,16 @@ mod tests {
             features: Features::default(),
             enabled: false,
             timeout: Timeout::default(),
+            max_concurrent_batch_calls: None,
+            tool_overrides: HashMap::new(),
+            resources: NameFilter::default(),
+            resource_templates: NameFilter::default(),
+            prompts: NameFilter::default(),
+            tools: NameFilter::default(),
+            tool_mode: None,
+            pinned_tools: Vec::new(),
+            schema_compaction: SchemaCompaction::default(),
+            tool_schemas: ToolSchemas::default(),
         };
 
         let serialized = serde_json::to_value(&config).unwrap();
@@ -1150,6 +2027,24 @@ mod tests {
 
         let timeout: Timeout = serde_json::from_value(json!({ "prompts": "30s" })).unwrap();
         assert!(!timeout.is_default());
+
+        let timeout: Timeout =
+            serde_json::from_value(json!({ "reset_on_progress": true })).unwrap();
+        assert!(!timeout.is_default());
+    }
+
+    #[test]
+    fn test_timeout_reset_on_progress() {
+        let timeout = Timeout::default();
+        assert!(!timeout.reset_on_progress);
+
+        let timeout: Timeout = serde_json::from_value(json!({
+            "tools": "5min",
+            "reset_on_progress": true
+        }))
+        .unwrap();
+        assert!(timeout.reset_on_progress);
+        assert_eq!(timeout.tool_timeout(), Duration::from_secs(300));
     }
 
     #[test]
@@ -1259,6 +2154,16 @@ mod tests {
             features: Features::default(),
             enabled: true,
             timeout,
+            max_concurrent_batch_calls: None,
+            tool_overrides: HashMap::new(),
+            resources: NameFilter::default(),
+            resource_templates: NameFilter::default(),
+            prompts: NameFilter::default(),
+            tools: NameFilter::default(),
+            tool_mode: None,
+            pinned_tools: Vec::new(),
+            tool_schemas: ToolSchemas::default(),
+            schema_compaction: SchemaCompaction::default(),
         };
 
         let serialized = serde_json::to_value(&config).unwrap();
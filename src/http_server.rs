@@ -0,0 +1,839 @@
+//! Streamable HTTP transport for the downstream MCP server.
+//!
+//! Exposes [`ModularMcpServer`] on a single `/mcp` endpoint so several agents can share
+//! one proxy (and one set of upstream servers):
+//!
+//! - `POST` carries JSON-RPC requests, notifications and responses from the client
+//! - `GET` opens an SSE stream for server-initiated messages
+//! - `DELETE` ends the session
+//!
+//! Each `initialize` request creates a session with its own `Mcp-Session-Id`. Sessions
+//! that see no requests and have no open stream for [`SESSION_IDLE_TIMEOUT`] are ended
+//! as if the client had sent `DELETE`.
+//!
+//! Requests whose `Origin` is neither a loopback origin nor explicitly allowed are
+//! rejected, so web pages cannot reach a local proxy through DNS rebinding.
+
+use crate::proxy::types::{JsonRpcError, JsonRpcMessage, JsonRpcResponse};
+use crate::proxy::ModularMcpClient;
+use crate::server::{parse_error_response, ModularMcpServer};
+use anyhow::{Context, Result};
+use axum::extract::{Request, State};
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::{Json, Router};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, Mutex, OwnedMutexGuard, RwLock};
+use tokio::task::JoinHandle;
+
+const SESSION_ID_HEADER: &str = "mcp-session-id";
+const PROTOCOL_VERSION_HEADER: &str = "mcp-protocol-version";
+
+/// How long a session may go without requests or an open stream before it is ended
+pub const SESSION_IDLE_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+/// Server-initiated messages kept for a session while no stream is open to receive them
+const OUTBOUND_QUEUE_CAPACITY: usize = 64;
+
+/// Notification method that is queued at most once, since one tells the client everything
+const TOOLS_LIST_CHANGED: &str = "notifications/tools/list_changed";
+
+struct Session {
+    server: ModularMcpServer,
+    /// Server-initiated messages, drained by at most one open GET stream at a time
+    outbound_rx: Arc<Mutex<mpsc::Receiver<serde_json::Value>>>,
+    /// Whether a `notifications/tools/list_changed` is queued and not yet streamed
+    list_changed_queued: Arc<AtomicBool>,
+    last_active: Arc<std::sync::Mutex<Instant>>,
+    notifier: Option<JoinHandle<()>>,
+    forwarder: JoinHandle<()>,
+}
+
+impl Session {
+    fn touch(&self) {
+        touch(&self.last_active);
+    }
+
+    /// Whether the session saw no request for `timeout` and has no stream open
+    fn is_idle(&self, timeout: Duration) -> bool {
+        let stream_open = self.outbound_rx.try_lock().is_err();
+        !stream_open
+            && self
+                .last_active
+                .lock()
+                .map(|last_active| last_active.elapsed() >= timeout)
+                .unwrap_or(false)
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        if let Some(notifier) = &self.notifier {
+            notifier.abort();
+        }
+        self.forwarder.abort();
+    }
+}
+
+#[derive(Clone)]
+struct HttpServerState {
+    client: Arc<RwLock<ModularMcpClient>>,
+    name: String,
+    version: String,
+    max_concurrent_requests: usize,
+    /// Origins accepted besides loopback ones, such as `https://app.example.com`
+    allowed_origins: Arc<Vec<String>>,
+    session_idle_timeout: Duration,
+    sessions: Arc<RwLock<HashMap<String, Arc<Session>>>>,
+}
+
+impl HttpServerState {
+    fn new(
+        client: Arc<RwLock<ModularMcpClient>>,
+        name: String,
+        version: String,
+        max_concurrent_requests: usize,
+        allowed_origins: Vec<String>,
+    ) -> Self {
+        Self {
+            client,
+            name,
+            version,
+            max_concurrent_requests,
+            allowed_origins: Arc::new(allowed_origins),
+            session_idle_timeout: SESSION_IDLE_TIMEOUT,
+            sessions: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    fn new_session(&self) -> Session {
+        let (outbound_tx, mut unbounded_rx) = mpsc::unbounded_channel();
+        let server =
+            ModularMcpServer::new(self.client.clone(), self.name.clone(), self.version.clone())
+                .with_outbound(outbound_tx)
+                .with_max_concurrent_requests(self.max_concurrent_requests);
+
+        // Move messages into a bounded queue, so a client without a stream open cannot
+        // make them pile up: repeated list changes collapse into one, the rest is dropped
+        // once the queue is full
+        let (queue_tx, queue_rx) = mpsc::channel(OUTBOUND_QUEUE_CAPACITY);
+        let list_changed_queued = Arc::new(AtomicBool::new(false));
+        let queued = list_changed_queued.clone();
+        let forwarder = tokio::spawn(async move {
+            while let Some(message) = unbounded_rx.recv().await {
+                let list_changed = is_list_changed(&message);
+                if list_changed && queued.swap(true, Ordering::SeqCst) {
+                    continue;
+                }
+                if let Err(mpsc::error::TrySendError::Full(message)) = queue_tx.try_send(message) {
+                    if list_changed {
+                        queued.store(false, Ordering::SeqCst);
+                    }
+                    tracing::debug!(
+                        "Session queue full, dropping {}",
+                        message.get("method").unwrap_or(&serde_json::Value::Null)
+                    );
+                }
+            }
+        });
+
+        Session {
+            notifier: server.spawn_notifier(),
+            server,
+            outbound_rx: Arc::new(Mutex::new(queue_rx)),
+            list_changed_queued,
+            last_active: Arc::new(std::sync::Mutex::new(Instant::now())),
+            forwarder,
+        }
+    }
+
+    async fn session(&self, headers: &HeaderMap) -> Result<Arc<Session>, Response> {
+        let session_id = match headers.get(SESSION_ID_HEADER).and_then(|v| v.to_str().ok()) {
+            Some(id) => id,
+            None => {
+                return Err(error_response(
+                    StatusCode::BAD_REQUEST,
+                    -32600,
+                    "Bad Request: missing Mcp-Session-Id header",
+                ))
+            }
+        };
+
+        match self.sessions.read().await.get(session_id) {
+            Some(session) => {
+                session.touch();
+                Ok(session.clone())
+            }
+            None => Err(error_response(
+                StatusCode::NOT_FOUND,
+                -32001,
+                "Session not found",
+            )),
+        }
+    }
+
+    /// End every session idle for longer than the idle timeout
+    async fn expire_idle_sessions(&self) {
+        let expired: Vec<(String, Arc<Session>)> = {
+            let mut sessions = self.sessions.write().await;
+            let idle: Vec<String> = sessions
+                .iter()
+                .filter(|(_, session)| session.is_idle(self.session_idle_timeout))
+                .map(|(id, _)| id.clone())
+                .collect();
+            idle.into_iter()
+                .filter_map(|id| sessions.remove(&id).map(|session| (id, session)))
+                .collect()
+        };
+
+        for (session_id, session) in expired {
+            session.server.end_session().await;
+            tracing::info!("Expired idle MCP session: {}", session_id);
+        }
+    }
+
+    /// Check for idle sessions periodically until the process stops
+    fn spawn_session_reaper(&self) -> JoinHandle<()> {
+        let state = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(state.session_idle_timeout / 4);
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                state.expire_idle_sessions().await;
+            }
+        })
+    }
+
+    /// Requests without `Origin` come from non-browser clients and are accepted
+    fn origin_allowed(&self, headers: &HeaderMap) -> bool {
+        let Some(origin) = headers.get(header::ORIGIN) else {
+            return true;
+        };
+        let Ok(origin) = origin.to_str() else {
+            return false;
+        };
+        is_loopback_origin(origin)
+            || self
+                .allowed_origins
+                .iter()
+                .any(|allowed| allowed.trim_end_matches('/') == origin)
+    }
+}
+
+fn touch(last_active: &std::sync::Mutex<Instant>) {
+    if let Ok(mut last_active) = last_active.lock() {
+        *last_active = Instant::now();
+    }
+}
+
+/// The open GET stream's hold on a session's queue; the idle timeout starts over once
+/// the stream closes
+struct StreamLease {
+    outbound_rx: OwnedMutexGuard<mpsc::Receiver<serde_json::Value>>,
+    list_changed_queued: Arc<AtomicBool>,
+    last_active: Arc<std::sync::Mutex<Instant>>,
+}
+
+impl Drop for StreamLease {
+    fn drop(&mut self) {
+        touch(&self.last_active);
+    }
+}
+
+fn is_list_changed(message: &serde_json::Value) -> bool {
+    message.get("method").and_then(|m| m.as_str()) == Some(TOOLS_LIST_CHANGED)
+}
+
+/// Whether `origin` is an `http(s)` origin on `localhost` or a loopback address
+fn is_loopback_origin(origin: &str) -> bool {
+    let Ok(url) = url::Url::parse(origin) else {
+        return false;
+    };
+    if !matches!(url.scheme(), "http" | "https") {
+        return false;
+    }
+    match url.host() {
+        Some(url::Host::Domain(domain)) => domain == "localhost",
+        Some(url::Host::Ipv4(ip)) => ip.is_loopback(),
+        Some(url::Host::Ipv6(ip)) => ip.is_loopback(),
+        None => false,
+    }
+}
+
+/// Serve MCP over Streamable HTTP on `addr` until the process is stopped
+pub async fn serve(
+    addr: &str,
+    client: Arc<RwLock<ModularMcpClient>>,
+    name: String,
+    version: String,
+    max_concurrent_requests: usize,
+    allowed_origins: Vec<String>,
+) -> Result<()> {
+    let listener = tokio::net::TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("Failed to bind HTTP listener on {}", addr))?;
+
+    tracing::info!(
+        "MCP server listening on http://{}/mcp",
+        listener.local_addr()?
+    );
+
+    let state = HttpServerState::new(
+        client,
+        name,
+        version,
+        max_concurrent_requests,
+        allowed_origins,
+    );
+    let _reaper = state.spawn_session_reaper();
+
+    axum::serve(listener, router(state))
+        .await
+        .context("HTTP server failed")
+}
+
+fn router(state: HttpServerState) -> Router {
+    Router::new()
+        .route(
+            "/mcp",
+            post(handle_post).get(handle_get).delete(handle_delete),
+        )
+        .layer(middleware::from_fn_with_state(state.clone(), check_origin))
+        .with_state(state)
+}
+
+async fn check_origin(
+    State(state): State<HttpServerState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if !state.origin_allowed(request.headers()) {
+        tracing::warn!(
+            "Rejected request from origin {:?}",
+            request.headers().get(header::ORIGIN)
+        );
+        return error_response(
+            StatusCode::FORBIDDEN,
+            -32600,
+            "Forbidden: origin not allowed",
+        );
+    }
+    next.run(request).await
+}
+
+fn error_response(status: StatusCode, code: i32, message: &str) -> Response {
+    let body = JsonRpcResponse {
+        jsonrpc: "2.0".to_string(),
+        id: serde_json::Value::Null,
+        result: None,
+        error: Some(JsonRpcError {
+            code,
+            message: message.to_string(),
+            data: None,
+        }),
+    };
+    (status, Json(body)).into_response()
+}
+
+async fn handle_post(
+    State(state): State<HttpServerState>,
+    headers: HeaderMap,
+    body: String,
+) -> Response {
+    let message = match serde_json::from_str::<JsonRpcMessage>(&body) {
+        Ok(message) => message,
+        Err(e) => {
+            tracing::error!("Failed to parse request: {}. Raw input: {}", e, body);
+            return (StatusCode::BAD_REQUEST, Json(parse_error_response(&e))).into_response();
+        }
+    };
+
+    let is_initialize = matches!(
+        &message,
+        JsonRpcMessage::Request(request) if request.method == "initialize"
+    );
+
+    if is_initialize && !headers.contains_key(SESSION_ID_HEADER) {
+        let session_id = uuid::Uuid::new_v4().to_string();
+        let session = Arc::new(state.new_session());
+        let response = session.server.handle_message(message).await;
+
+        state
+            .sessions
+            .write()
+            .await
+            .insert(session_id.clone(), session);
+        tracing::info!("Created MCP session: {}", session_id);
+
+        let mut http_response = match response {
+            Some(body) => Json(body).into_response(),
+            None => StatusCode::ACCEPTED.into_response(),
+        };
+        if let Ok(value) = HeaderValue::from_str(&session_id) {
+            http_response.headers_mut().insert(SESSION_ID_HEADER, value);
+        }
+        return http_response;
+    }
+
+    let session = match state.session(&headers).await {
+        Ok(session) => session,
+        Err(response) => return response,
+    };
+
+    // Clients omitting the header are assumed to speak the negotiated version
+    if let Some(version) = headers.get(PROTOCOL_VERSION_HEADER) {
+        let negotiated = version
+            .to_str()
+            .is_ok_and(|v| v == session.server.protocol_version());
+        if !negotiated {
+            return error_response(
+                StatusCode::BAD_REQUEST,
+                -32600,
+                "Bad Request: MCP-Protocol-Version does not match the negotiated version",
+            );
+        }
+    }
+
+    match session.server.handle_message(message).await {
+        Some(body) => Json(body).into_response(),
+        None => StatusCode::ACCEPTED.into_response(),
+    }
+}
+
+async fn handle_get(State(state): State<HttpServerState>, headers: HeaderMap) -> Response {
+    let accepts_sse = headers
+        .get(header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.contains("text/event-stream"));
+
+    if !accepts_sse {
+        return StatusCode::NOT_ACCEPTABLE.into_response();
+    }
+
+    let session = match state.session(&headers).await {
+        Ok(session) => session,
+        Err(response) => return response,
+    };
+
+    let outbound_rx = match session.outbound_rx.clone().try_lock_owned() {
+        Ok(guard) => guard,
+        Err(_) => {
+            return error_response(
+                StatusCode::CONFLICT,
+                -32600,
+                "An SSE stream is already open for this session",
+            )
+        }
+    };
+    let lease = StreamLease {
+        outbound_rx,
+        list_changed_queued: session.list_changed_queued.clone(),
+        last_active: session.last_active.clone(),
+    };
+
+    let stream = futures::stream::unfold(lease, |mut lease| async move {
+        let message = lease.outbound_rx.recv().await?;
+        if is_list_changed(&message) {
+            lease.list_changed_queued.store(false, Ordering::SeqCst);
+        }
+        let event = Event::default().event("message").data(message.to_string());
+        Some((Ok::<_, Infallible>(event), lease))
+    });
+
+    Sse::new(stream)
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
+
+async fn handle_delete(State(state): State<HttpServerState>, headers: HeaderMap) -> Response {
+    let session_id = match headers.get(SESSION_ID_HEADER).and_then(|v| v.to_str().ok()) {
+        Some(id) => id.to_string(),
+        None => {
+            return error_response(
+                StatusCode::BAD_REQUEST,
+                -32600,
+                "Bad Request: missing Mcp-Session-Id header",
+            )
+        }
+    };
+
+    let removed = state.sessions.write().await.remove(&session_id);
+    match removed {
+        Some(session) => {
+            session.server.end_session().await;
+            tracing::info!("Closed MCP session: {}", session_id);
+            StatusCode::OK.into_response()
+        }
+        None => error_response(StatusCode::NOT_FOUND, -32001, "Session not found"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::DEFAULT_MAX_CONCURRENT_REQUESTS;
+    use serde_json::json;
+
+    fn test_state() -> HttpServerState {
+        let client = Arc::new(RwLock::new(ModularMcpClient::new()));
+        HttpServerState::new(
+            client,
+            "test-server".to_string(),
+            "1.0.0".to_string(),
+            DEFAULT_MAX_CONCURRENT_REQUESTS,
+            vec!["https://app.example.com".to_string()],
+        )
+    }
+
+    async fn start_test_server() -> String {
+        start_with_state(test_state()).await
+    }
+
+    async fn start_with_state(state: HttpServerState) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            axum::serve(listener, router(state)).await.unwrap();
+        });
+
+        format!("http://{}/mcp", addr)
+    }
+
+    async fn initialize(http: &reqwest::Client, url: &str) -> String {
+        let response = http
+            .post(url)
+            .json(&json!({"jsonrpc": "2.0", "id": 1, "method": "initialize"}))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), 200);
+        response
+            .headers()
+            .get(SESSION_ID_HEADER)
+            .expect("initialize should issue a session id")
+            .to_str()
+            .unwrap()
+            .to_string()
+    }
+
+    #[tokio::test]
+    async fn test_initialize_creates_session() {
+        let url = start_test_server().await;
+        let http = reqwest::Client::new();
+
+        let session_id = initialize(&http, &url).await;
+        assert!(!session_id.is_empty());
+
+        let response = http
+            .post(&url)
+            .header(SESSION_ID_HEADER, &session_id)
+            .json(&json!({"jsonrpc": "2.0", "id": 2, "method": "tools/list"}))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), 200);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["id"], 2);
+        assert_eq!(body["result"]["tools"][0]["name"], "get_dynamic_tools");
+    }
+
+    #[tokio::test]
+    async fn test_sessions_are_independent() {
+        let url = start_test_server().await;
+        let http = reqwest::Client::new();
+
+        let first = initialize(&http, &url).await;
+        let second = initialize(&http, &url).await;
+        assert_ne!(first, second);
+    }
+
+    #[tokio::test]
+    async fn test_request_without_session_is_rejected() {
+        let url = start_test_server().await;
+        let http = reqwest::Client::new();
+
+        let response = http
+            .post(&url)
+            .json(&json!({"jsonrpc": "2.0", "id": 1, "method": "tools/list"}))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), 400);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_session_returns_not_found() {
+        let url = start_test_server().await;
+        let http = reqwest::Client::new();
+
+        let response = http
+            .post(&url)
+            .header(SESSION_ID_HEADER, "unknown-session")
+            .json(&json!({"jsonrpc": "2.0", "id": 1, "method": "tools/list"}))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), 404);
+    }
+
+    #[tokio::test]
+    async fn test_notification_is_accepted() {
+        let url = start_test_server().await;
+        let http = reqwest::Client::new();
+        let session_id = initialize(&http, &url).await;
+
+        let response = http
+            .post(&url)
+            .header(SESSION_ID_HEADER, &session_id)
+            .json(&json!({"jsonrpc": "2.0", "method": "notifications/initialized"}))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), 202);
+    }
+
+    #[tokio::test]
+    async fn test_protocol_version_header_must_match_session() {
+        let url = start_test_server().await;
+        let http = reqwest::Client::new();
+        let session_id = initialize(&http, &url).await;
+
+        let request = json!({"jsonrpc": "2.0", "id": 2, "method": "tools/list"});
+        let negotiated = http
+            .post(&url)
+            .header(SESSION_ID_HEADER, &session_id)
+            .header(PROTOCOL_VERSION_HEADER, "2024-11-05")
+            .json(&request)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(negotiated.status(), 200);
+
+        for version in ["2025-06-18", "1999-01-01"] {
+            let mismatched = http
+                .post(&url)
+                .header(SESSION_ID_HEADER, &session_id)
+                .header(PROTOCOL_VERSION_HEADER, version)
+                .json(&request)
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(mismatched.status(), 400, "{}", version);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_newest_protocol_version_is_accepted() {
+        let url = start_test_server().await;
+        let http = reqwest::Client::new();
+        let response = http
+            .post(&url)
+            .json(&json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "initialize",
+                "params": {"protocolVersion": "2025-11-25"}
+            }))
+            .send()
+            .await
+            .unwrap();
+        let session_id = response.headers()[SESSION_ID_HEADER]
+            .to_str()
+            .unwrap()
+            .to_string();
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["result"]["protocolVersion"], "2025-11-25");
+
+        let listed = http
+            .post(&url)
+            .header(SESSION_ID_HEADER, &session_id)
+            .header(PROTOCOL_VERSION_HEADER, "2025-11-25")
+            .json(&json!({"jsonrpc": "2.0", "id": 2, "method": "tools/list"}))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(listed.status(), 200);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_json_returns_parse_error() {
+        let url = start_test_server().await;
+        let http = reqwest::Client::new();
+
+        let response = http.post(&url).body("not json").send().await.unwrap();
+
+        assert_eq!(response.status(), 400);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["error"]["code"], -32700);
+    }
+
+    #[tokio::test]
+    async fn test_delete_ends_session() {
+        let url = start_test_server().await;
+        let http = reqwest::Client::new();
+        let session_id = initialize(&http, &url).await;
+
+        let response = http
+            .delete(&url)
+            .header(SESSION_ID_HEADER, &session_id)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+
+        let response = http
+            .post(&url)
+            .header(SESSION_ID_HEADER, &session_id)
+            .json(&json!({"jsonrpc": "2.0", "id": 2, "method": "tools/list"}))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 404);
+    }
+
+    #[tokio::test]
+    async fn test_get_opens_event_stream() {
+        let url = start_test_server().await;
+        let http = reqwest::Client::new();
+        let session_id = initialize(&http, &url).await;
+
+        let response = http
+            .get(&url)
+            .header(SESSION_ID_HEADER, &session_id)
+            .header("Accept", "text/event-stream")
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), 200);
+        let content_type = response
+            .headers()
+            .get("content-type")
+            .unwrap()
+            .to_str()
+            .unwrap();
+        assert!(content_type.contains("text/event-stream"));
+    }
+
+    #[tokio::test]
+    async fn test_get_without_event_stream_accept_is_rejected() {
+        let url = start_test_server().await;
+        let http = reqwest::Client::new();
+        let session_id = initialize(&http, &url).await;
+
+        let response = http
+            .get(&url)
+            .header(SESSION_ID_HEADER, &session_id)
+            .header("Accept", "application/json")
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), 406);
+    }
+
+    #[tokio::test]
+    async fn test_foreign_origin_is_rejected() {
+        let url = start_test_server().await;
+        let http = reqwest::Client::new();
+        let initialize = json!({"jsonrpc": "2.0", "id": 1, "method": "initialize"});
+
+        for origin in [
+            "http://evil.example",
+            "null",
+            "http://localhost.evil.example",
+        ] {
+            let response = http
+                .post(&url)
+                .header("Origin", origin)
+                .json(&initialize)
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(response.status(), 403, "{}", origin);
+        }
+
+        for origin in [
+            "http://localhost:3000",
+            "http://127.0.0.1:8080",
+            "http://[::1]",
+            "https://app.example.com",
+        ] {
+            let response = http
+                .post(&url)
+                .header("Origin", origin)
+                .json(&initialize)
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(response.status(), 200, "{}", origin);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_idle_sessions_expire() {
+        let mut state = test_state();
+        state.session_idle_timeout = Duration::ZERO;
+        let url = start_with_state(state.clone()).await;
+        let http = reqwest::Client::new();
+        let session_id = initialize(&http, &url).await;
+
+        state.expire_idle_sessions().await;
+
+        let response = http
+            .post(&url)
+            .header(SESSION_ID_HEADER, &session_id)
+            .json(&json!({"jsonrpc": "2.0", "id": 2, "method": "tools/list"}))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 404);
+    }
+
+    #[tokio::test]
+    async fn test_session_with_open_stream_is_not_idle() {
+        let session = test_state().new_session();
+        assert!(session.is_idle(Duration::ZERO));
+
+        let _stream = session.outbound_rx.clone().try_lock_owned().unwrap();
+        assert!(!session.is_idle(Duration::ZERO));
+    }
+
+    #[tokio::test]
+    async fn test_queued_messages_are_bounded_and_list_changes_coalesced() {
+        let session = test_state().new_session();
+        let list_changed = json!({"jsonrpc": "2.0", "method": TOOLS_LIST_CHANGED});
+
+        for _ in 0..3 {
+            session.server.send_to_client(list_changed.clone());
+        }
+        for i in 0..OUTBOUND_QUEUE_CAPACITY * 2 {
+            session.server.send_to_client(
+                json!({"jsonrpc": "2.0", "method": "notifications/message", "params": {"i": i}}),
+            );
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let mut queue = session.outbound_rx.lock().await;
+        let mut queued = Vec::new();
+        while let Ok(message) = queue.try_recv() {
+            queued.push(message);
+        }
+        assert_eq!(queued.len(), OUTBOUND_QUEUE_CAPACITY);
+        assert_eq!(queued.iter().filter(|m| is_list_changed(m)).count(), 1);
+    }
+}
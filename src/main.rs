@@ -1,6 +1,7 @@
 mod auth;
 mod cli;
 mod config;
+mod http_server;
 mod proxy;
 mod server;
 mod watcher;
@@ -8,7 +9,7 @@ mod watcher;
 use anyhow::Result;
 use clap::{Parser, Subcommand};
 use proxy::ModularMcpClient;
-use server::ModularMcpServer;
+use server::{ModularMcpServer, DEFAULT_MAX_CONCURRENT_REQUESTS};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use tracing_subscriber::EnvFilter;
@@ -18,12 +19,22 @@ use watcher::ConfigWatcher;
 #[command(name = "dynamic-mcp")]
 #[command(version = env!("CARGO_PKG_VERSION"))]
 #[command(about = "Dynamic MCP Proxy Server - Reduce context overhead with on-demand tool loading")]
+#[command(args_conflicts_with_subcommands = true)]
 struct Cli {
     #[command(subcommand)]
     command: Option<Commands>,
 
     /// Configuration file path (when running as server without subcommand)
     config_path: Option<String>,
+
+    /// Maximum number of requests handled concurrently per client session
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = DEFAULT_MAX_CONCURRENT_REQUESTS,
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..)
+    )]
+    max_concurrent_requests: usize,
 }
 
 #[derive(Subcommand)]
@@ -45,6 +56,28 @@ enum Commands {
         #[arg(short, long, default_value = "dynamic-mcp.json")]
         output: String,
     },
+    /// Run the proxy server over stdio (default) or Streamable HTTP
+    Serve {
+        /// Configuration file path
+        config_path: Option<String>,
+
+        /// Serve over Streamable HTTP on this address (e.g. 127.0.0.1:8080) instead of stdio
+        #[arg(long, value_name = "ADDR")]
+        http: Option<String>,
+
+        /// Maximum number of requests handled concurrently per client session
+        #[arg(
+            long,
+            value_name = "N",
+            default_value_t = DEFAULT_MAX_CONCURRENT_REQUESTS,
+            value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..)
+        )]
+        max_concurrent_requests: usize,
+
+        /// Browser origin allowed to reach the HTTP server besides loopback ones (repeatable)
+        #[arg(long = "allowed-origin", value_name = "ORIGIN", requires = "http")]
+        allowed_origins: Vec<String>,
+    },
 }
 
 fn get_config_path(cli_arg: Option<String>) -> Option<(String, &'static str)> {
@@ -79,28 +112,71 @@ async fn main() -> Result<()> {
                 .init();
             cli::import::run_import_from_tool(&tool_name, global, force, &output).await
         }
+        Some(Commands::Serve {
+            config_path,
+            http,
+            max_concurrent_requests,
+            allowed_origins,
+        }) => {
+            if http.is_some() {
+                // Logging is safe over HTTP since stdout/stderr carry no protocol traffic
+                tracing_subscriber::fmt()
+                    .with_env_filter(
+                        EnvFilter::try_from_default_env()
+                            .unwrap_or_else(|_| EnvFilter::new("warn")),
+                    )
+                    .with_writer(std::io::stderr)
+                    .init();
+            }
+
+            let (config_path, config_source) = require_config_path(config_path);
+            run_server(
+                config_path,
+                config_source,
+                http,
+                allowed_origins,
+                max_concurrent_requests,
+            )
+            .await
+        }
         None => {
             // Disable all logging for stdio mode to avoid corrupting JSON-RPC communication
             // Logging would write to stderr which interferes with the MCP protocol
 
-            let (config_path, config_source) =
-                get_config_path(cli.config_path).unwrap_or_else(|| {
-                    eprintln!("Error: No configuration file specified");
-                    eprintln!();
-                    eprintln!("Usage: dynamic-mcp <config-file>");
-                    eprintln!("   or: DYNAMIC_MCP_CONFIG=<config-file> dynamic-mcp");
-                    eprintln!();
-                    eprintln!("Example: dynamic-mcp config.example.json");
-                    eprintln!("     or: DYNAMIC_MCP_CONFIG=config.example.json dynamic-mcp");
-                    std::process::exit(1);
-                });
-
-            run_server(config_path, config_source).await
+            let (config_path, config_source) = require_config_path(cli.config_path);
+            run_server(
+                config_path,
+                config_source,
+                None,
+                Vec::new(),
+                cli.max_concurrent_requests,
+            )
+            .await
         }
     }
 }
 
-async fn run_server(config_path: String, config_source: &str) -> Result<()> {
+fn require_config_path(cli_arg: Option<String>) -> (String, &'static str) {
+    get_config_path(cli_arg).unwrap_or_else(|| {
+        eprintln!("Error: No configuration file specified");
+        eprintln!();
+        eprintln!("Usage: dynamic-mcp <config-file>");
+        eprintln!("   or: DYNAMIC_MCP_CONFIG=<config-file> dynamic-mcp");
+        eprintln!("   or: dynamic-mcp serve <config-file> --http <addr>");
+        eprintln!();
+        eprintln!("Example: dynamic-mcp config.example.json");
+        eprintln!("     or: DYNAMIC_MCP_CONFIG=config.example.json dynamic-mcp");
+        std::process::exit(1);
+    })
+}
+
+async fn run_server(
+    config_path: String,
+    config_source: &str,
+    http_addr: Option<String>,
+    allowed_origins: Vec<String>,
+    max_concurrent_requests: usize,
+) -> Result<()> {
     tracing::info!(
         "Starting dynamic-mcp server with config: {} (from {})",
         &config_path,
@@ -110,15 +186,29 @@ async fn run_server(config_path: String, config_source: &str) -> Result<()> {
     let config_path_buf = std::path::Path::new(&config_path).canonicalize()?;
     let (config_watcher, mut reload_rx) = ConfigWatcher::new(&config_path_buf)?;
 
-    let client = Arc::new(RwLock::new(ModularMcpClient::new()));
-
     // Validate initial config
-    config::load_config(&config_path).await?;
+    let initial_config = config::load_config(&config_path).await?;
+
+    let mut client = ModularMcpClient::new();
+    client.set_namespacing(initial_config.namespacing);
+    client.set_dynamic_loading(initial_config.dynamic_loading);
+    client.set_tool_mode(initial_config.tool_mode);
+    client.set_repair_arguments(initial_config.repair_arguments);
+    let mut upstream_notifications = client.subscribe_upstream_notifications();
+    // Upstream servers are only told about the sampling, elicitation and roots support the
+    // client declares in `initialize`, so over stdio, with its single client, they wait for it
+    let mut client_initialized = http_addr
+        .is_none()
+        .then(|| client.subscribe_relayed_capabilities());
+    let client = Arc::new(RwLock::new(client));
 
     // Initial load - spawn in background to avoid blocking stdio
     let client_init = client.clone();
     let config_path_init = config_path.clone();
     tokio::spawn(async move {
+        if let Some(initialized) = client_initialized.as_mut() {
+            let _ = initialized.changed().await;
+        }
         if let Ok(config) = config::load_config(&config_path_init).await {
             let servers: Vec<_> = config
                 .mcp_servers
@@ -172,14 +262,47 @@ async fn run_server(config_path: String, config_source: &str) -> Result<()> {
 
         loop {
             interval.tick().await;
+            // Waiting for the write lock would stall every request behind in-flight calls,
+            // so only take it when there is something to retry
+            if !client_retry.read().await.has_retryable_groups() {
+                continue;
+            }
+
             let mut client_lock = client_retry.write().await;
             let failed = client_lock.list_failed_groups();
+            tracing::debug!("Periodic retry check: {} failed groups", failed.len());
+            let retried = client_lock.retry_failed_connections().await;
+            if !retried.is_empty() {
+                tracing::info!("✅ Periodic retry reconnected: {}", retried.join(", "));
+            }
+        }
+    });
 
-            if !failed.is_empty() {
-                tracing::debug!("Periodic retry check: {} failed groups", failed.len());
-                let retried = client_lock.retry_failed_connections().await;
-                if !retried.is_empty() {
-                    tracing::info!("✅ Periodic retry reconnected: {}", retried.join(", "));
+    // Spawn tool list refresher for groups whose tools changed; listing only needs the
+    // read lock, so the write lock is held just to swap the new list in
+    let client_tools = client.clone();
+    tokio::spawn(async move {
+        loop {
+            let upstream = match upstream_notifications.recv().await {
+                Ok(upstream) => upstream,
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::warn!("Dropped {} upstream notifications", skipped);
+                    continue;
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+            };
+            if upstream.notification.method != "notifications/tools/list_changed" {
+                continue;
+            }
+
+            let fetched = client_tools.read().await.fetch_tools(&upstream.group).await;
+            match fetched {
+                Ok(tools) => {
+                    tracing::info!("Re-listed tools of {}", upstream.group);
+                    client_tools.write().await.set_tools(&upstream.group, tools);
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to re-list tools of {}: {}", upstream.group, e);
                 }
             }
         }
@@ -195,6 +318,10 @@ async fn run_server(config_path: String, config_source: &str) -> Result<()> {
             match config::load_config(&config_path_clone).await {
                 Ok(new_config) => {
                     let mut client_lock = client_clone.write().await;
+                    client_lock.set_namespacing(new_config.namespacing);
+                    client_lock.set_dynamic_loading(new_config.dynamic_loading);
+                    client_lock.set_tool_mode(new_config.tool_mode);
+                    client_lock.set_repair_arguments(new_config.repair_arguments);
 
                     // Disconnect all existing connections
                     if let Err(e) = client_lock.disconnect_all().await {
@@ -251,14 +378,6 @@ async fn run_server(config_path: String, config_source: &str) -> Result<()> {
         }
     });
 
-    let server = ModularMcpServer::new(
-        client.clone(),
-        env!("CARGO_PKG_NAME").to_string(),
-        env!("CARGO_PKG_VERSION").to_string(),
-    );
-
-    tracing::info!("MCP server initialized, starting stdio listener...");
-
     // Keep watcher alive
     std::mem::forget(config_watcher);
 
@@ -272,9 +391,34 @@ async fn run_server(config_path: String, config_source: &str) -> Result<()> {
         std::process::exit(0);
     });
 
-    let result = server.run_stdio().await;
+    let name = env!("CARGO_PKG_NAME").to_string();
+    let version = env!("CARGO_PKG_VERSION").to_string();
+
+    let result = match http_addr {
+        Some(addr) => {
+            tracing::info!(
+                "MCP server initialized, starting HTTP listener on {}...",
+                addr
+            );
+            http_server::serve(
+                &addr,
+                client.clone(),
+                name,
+                version,
+                max_concurrent_requests,
+                allowed_origins,
+            )
+            .await
+        }
+        None => {
+            let server = ModularMcpServer::new(client.clone(), name, version)
+                .with_max_concurrent_requests(max_concurrent_requests);
+            tracing::info!("MCP server initialized, starting stdio listener...");
+            server.run_stdio().await
+        }
+    };
 
-    // Cleanup on normal exit (stdin closed)
+    // Cleanup on normal exit (stdin closed or HTTP listener stopped)
     {
         let mut client_lock = client.write().await;
         let _ = client_lock.disconnect_all().await;
@@ -1,10 +1,43 @@
+use crate::config::schema::{NameFilter, ToolMode, ToolOverride};
 use crate::config::McpServerConfig;
-use crate::proxy::transport::Transport;
-use crate::proxy::types::{FailedGroupInfo, GroupInfo, JsonRpcRequest, ToolInfo};
+use crate::proxy::search::{ToolIndex, ToolMatch};
+use crate::proxy::transport::{IncomingMessage, Transport};
+use crate::proxy::types::{
+    FailedGroupInfo, GroupInfo, JsonRpcError, JsonRpcRequest, JsonRpcResponse, ToolInfo,
+};
+use crate::proxy::uri_template::UriTemplate;
 use anyhow::{Context, Result};
 use serde_json::json;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
+use tokio::sync::{broadcast, mpsc, oneshot, watch};
+
+/// Capacity of the channel relaying upstream notifications to downstream sessions
+const UPSTREAM_NOTIFICATION_CAPACITY: usize = 256;
+
+/// Upper bound on pages fetched when indexing one group's resources, against cursor loops
+const MAX_RESOURCE_INDEX_PAGES: usize = 100;
+
+/// Upper bound on pages fetched when listing one group's tools, against cursor loops
+const MAX_TOOL_LIST_PAGES: usize = 100;
+
+/// Upper bound on pages read when looking for a prompt or resource template in one group
+const MAX_LOOKUP_PAGES: usize = 100;
+
+/// Upper bound on remembered URIs no group serves, so arbitrary client URIs cannot grow it
+const MAX_UNROUTABLE_URIS: usize = 1024;
+
+/// Reconnection attempts made for a failed group before it is given up on
+const MAX_RETRIES: u32 = 3;
+
+/// Server-to-client requests relayed to downstream sessions, with the client capability
+/// each one requires
+const REVERSE_PROXIED_METHODS: [(&str, &str); 3] = [
+    ("sampling/createMessage", "sampling"),
+    ("elicitation/create", "elicitation"),
+    ("roots/list", "roots"),
+];
 
 pub enum GroupState {
     Connected {
@@ -13,6 +46,8 @@ pub enum GroupState {
         tools: Vec<ToolInfo>,
         transport: Transport,
         config: McpServerConfig,
+        /// Server capabilities from the `initialize` result
+        capabilities: serde_json::Value,
     },
     Failed {
         name: String,
@@ -23,19 +58,432 @@ pub enum GroupState {
     },
 }
 
+/// A notification an upstream group sent on its own initiative
+#[derive(Debug, Clone)]
+pub struct UpstreamNotification {
+    pub group: String,
+    pub notification: JsonRpcRequest,
+}
+
+impl UpstreamNotification {
+    /// Whether this is `group` reporting progress for `token`
+    pub fn is_progress_for(&self, group: &str, token: &serde_json::Value) -> bool {
+        self.group == group
+            && self.notification.method == "notifications/progress"
+            && self
+                .notification
+                .params
+                .as_ref()
+                .and_then(|p| p.get("progressToken"))
+                == Some(token)
+    }
+}
+
+/// A request an upstream group sent to its client, to be answered by a downstream session
+#[derive(Debug)]
+pub struct UpstreamRequest {
+    pub group: String,
+    pub request: JsonRpcRequest,
+    /// Receives the downstream answer; dropping it answers "Method not found"
+    pub responder: oneshot::Sender<JsonRpcResponse>,
+}
+
+/// A downstream session that answers upstream requests it has the capabilities for
+struct DownstreamPeer {
+    id: u64,
+    capabilities: serde_json::Value,
+    requests: mpsc::UnboundedSender<UpstreamRequest>,
+}
+
+/// Downstream sessions, shared with the transports' incoming handlers
+#[derive(Default)]
+struct DownstreamPeers {
+    next_id: u64,
+    peers: Vec<DownstreamPeer>,
+    /// Session that issued each call in flight, by group and upstream request id
+    calls: HashMap<(String, String), u64>,
+}
+
+impl DownstreamPeers {
+    /// The session an upstream request from `group` is meant for: the one whose calls
+    /// `group` is working on, or the only session there is. `None` when that is ambiguous.
+    fn origin(&self, group: &str) -> Option<u64> {
+        let mut callers: Vec<u64> = self
+            .calls
+            .iter()
+            .filter(|((call_group, _), _)| call_group == group)
+            .map(|(_, peer)| *peer)
+            .collect();
+        callers.sort_unstable();
+        callers.dedup();
+
+        match callers.as_slice() {
+            [peer] => Some(*peer),
+            [] if self.peers.len() == 1 => Some(self.peers[0].id),
+            _ => None,
+        }
+    }
+
+    /// The relayable capabilities any session declared, as the sessions declared them
+    fn relayed_capabilities(&self) -> serde_json::Value {
+        let mut capabilities = serde_json::Map::new();
+        for (_, capability) in REVERSE_PROXIED_METHODS {
+            let declared = self
+                .peers
+                .iter()
+                .find_map(|peer| peer.capabilities.get(capability));
+            if let Some(declared) = declared {
+                capabilities.insert(capability.to_string(), declared.clone());
+            }
+        }
+        serde_json::Value::Object(capabilities)
+    }
+
+    /// Hand `request` to the session it is meant for, or answer it with an error when that
+    /// session cannot be told or lacks the capability the request needs
+    fn route(&self, request: UpstreamRequest) {
+        let capability = REVERSE_PROXIED_METHODS
+            .iter()
+            .find(|(method, _)| *method == request.request.method)
+            .map(|(_, capability)| *capability);
+        let Some(capability) = capability else {
+            tracing::debug!(
+                "Rejecting unsupported upstream request: {}",
+                request.request.method
+            );
+            return;
+        };
+
+        if self.peers.is_empty() {
+            tracing::debug!(
+                "No downstream session can answer {} from {}",
+                request.request.method,
+                request.group
+            );
+            return;
+        }
+
+        let Some(origin) = self.origin(&request.group) else {
+            tracing::debug!(
+                "Cannot tell which session {} from {} is for",
+                request.request.method,
+                request.group
+            );
+            reject_upstream_request(
+                request,
+                -32603,
+                "The proxy cannot tell which client session this request is for",
+            );
+            return;
+        };
+
+        match self
+            .peers
+            .iter()
+            .find(|peer| peer.id == origin && peer.capabilities.get(capability).is_some())
+        {
+            Some(peer) => {
+                if let Err(e) = peer.requests.send(request) {
+                    tracing::debug!("Downstream session went away: {}", e.0.request.method);
+                }
+            }
+            None => tracing::debug!(
+                "Session {} cannot answer {} from {}",
+                origin,
+                request.request.method,
+                request.group
+            ),
+        }
+    }
+}
+
+/// Answer an upstream request with an error instead of relaying it
+fn reject_upstream_request(request: UpstreamRequest, code: i32, message: &str) {
+    let _ = request.responder.send(JsonRpcResponse {
+        jsonrpc: "2.0".to_string(),
+        id: request.request.id,
+        result: None,
+        error: Some(JsonRpcError {
+            code,
+            message: message.to_string(),
+            data: None,
+        }),
+    });
+}
+
+/// Marks an upstream call as issued by a downstream session until dropped, so upstream
+/// requests made while serving it go to that session
+pub struct UpstreamCallTag {
+    downstream_peers: Arc<Mutex<DownstreamPeers>>,
+    key: (String, String),
+}
+
+impl Drop for UpstreamCallTag {
+    fn drop(&mut self) {
+        if let Ok(mut downstream) = self.downstream_peers.lock() {
+            downstream.calls.remove(&self.key);
+        }
+    }
+}
+
+/// An upstream resource subscription shared by every downstream session subscribed to the URI
+struct ResourceSubscription {
+    /// Group that owns the URI, or `None` until a connected group lists it
+    group: Option<String>,
+    subscribers: usize,
+}
+
+/// Resource URIs and URI templates one group listed
+#[derive(Default)]
+struct ResourceRoutes {
+    uris: HashSet<String>,
+    templates: Vec<UriTemplate>,
+}
+
+impl ResourceRoutes {
+    fn matches_template(&self, uri: &str) -> bool {
+        self.templates.iter().any(|template| template.matches(uri))
+    }
+}
+
+/// Which group serves which resource URI, filled lazily from the groups' listings
+#[derive(Default)]
+struct ResourceRouting {
+    /// Bumped on every invalidation, so a listing that raced with one is not stored
+    generation: u64,
+    groups: HashMap<String, ResourceRoutes>,
+    /// URIs no group served after re-listing every group, until the next invalidation
+    unroutable: HashSet<String>,
+}
+
+impl ResourceRouting {
+    fn invalidate(&mut self, group: &str) {
+        self.generation += 1;
+        self.groups.remove(group);
+        self.unroutable.clear();
+    }
+
+    /// Remember that re-listing every group at `generation` found no route for `uri`
+    fn mark_unroutable(&mut self, generation: u64, uri: &str) {
+        if self.generation != generation {
+            return;
+        }
+        if self.unroutable.len() >= MAX_UNROUTABLE_URIS {
+            self.unroutable.clear();
+        }
+        self.unroutable.insert(uri.to_string());
+    }
+
+    /// First of `groups` listing `uri` itself, else the first with a template matching it
+    fn route(&self, groups: &[String], uri: &str) -> Option<String> {
+        let indexed = || {
+            groups
+                .iter()
+                .filter_map(|group| self.groups.get(group).map(|routes| (group, routes)))
+        };
+        indexed()
+            .find(|(_, routes)| routes.uris.contains(uri))
+            .or_else(|| indexed().find(|(_, routes)| routes.matches_template(uri)))
+            .map(|(group, _)| group.clone())
+    }
+}
+
 pub struct ModularMcpClient {
     groups: HashMap<String, GroupState>,
+    /// Sorted names of the connected groups, updated whenever that set changes
+    connected_groups: watch::Sender<Vec<String>>,
+    upstream_notifications: broadcast::Sender<UpstreamNotification>,
+    resource_subscriptions: Mutex<HashMap<String, ResourceSubscription>>,
+    /// Shared with the transports' incoming handlers, which drop a group's routes when it
+    /// reports `notifications/resources/list_changed`
+    resource_routes: Arc<Mutex<ResourceRouting>>,
+    downstream_peers: Arc<Mutex<DownstreamPeers>>,
+    /// Client capabilities offered to stdio upstreams when they connect: the sampling,
+    /// elicitation and roots capabilities the registered downstream sessions declared
+    relayed_capabilities: watch::Sender<serde_json::Value>,
+    /// Whether aggregated listings carry the group in resource URIs and prompt names
+    namespacing: bool,
+    /// Whether groups opened with `get_dynamic_tools` add their tools to `tools/list`
+    dynamic_loading: bool,
+    /// How tools are offered for groups that do not set their own `tool_mode`
+    tool_mode: ToolMode,
+    /// Whether malformed `call_dynamic_tool` arguments are fixed up before they are sent
+    repair_arguments: bool,
+    /// Search index over the connected groups' tools, rebuilt whenever that set changes
+    tool_index: ToolIndex,
 }
 
 impl ModularMcpClient {
     pub fn new() -> Self {
+        let (connected_groups, _) = watch::channel(Vec::new());
+        let (upstream_notifications, _) = broadcast::channel(UPSTREAM_NOTIFICATION_CAPACITY);
+        let (relayed_capabilities, _) = watch::channel(json!({}));
         Self {
             groups: HashMap::new(),
+            connected_groups,
+            upstream_notifications,
+            resource_subscriptions: Mutex::new(HashMap::new()),
+            resource_routes: Arc::new(Mutex::new(ResourceRouting::default())),
+            downstream_peers: Arc::new(Mutex::new(DownstreamPeers::default())),
+            relayed_capabilities,
+            namespacing: false,
+            dynamic_loading: false,
+            tool_mode: ToolMode::default(),
+            repair_arguments: false,
+            tool_index: ToolIndex::default(),
+        }
+    }
+
+    pub fn namespacing(&self) -> bool {
+        self.namespacing
+    }
+
+    pub fn set_namespacing(&mut self, namespacing: bool) {
+        self.namespacing = namespacing;
+    }
+
+    pub fn dynamic_loading(&self) -> bool {
+        self.dynamic_loading
+    }
+
+    pub fn set_dynamic_loading(&mut self, dynamic_loading: bool) {
+        self.dynamic_loading = dynamic_loading;
+    }
+
+    pub fn tool_mode(&self) -> ToolMode {
+        self.tool_mode
+    }
+
+    pub fn set_tool_mode(&mut self, tool_mode: ToolMode) {
+        self.tool_mode = tool_mode;
+    }
+
+    pub fn repair_arguments(&self) -> bool {
+        self.repair_arguments
+    }
+
+    pub fn set_repair_arguments(&mut self, repair_arguments: bool) {
+        self.repair_arguments = repair_arguments;
+    }
+
+    /// How `group`'s tools are offered: its own `tool_mode`, or the top-level one
+    pub fn group_tool_mode(&self, group: &str) -> ToolMode {
+        self.group_config(group)
+            .and_then(|config| config.tool_mode())
+            .unwrap_or(self.tool_mode)
+    }
+
+    /// Register a downstream session to answer the upstream sampling, elicitation and roots
+    /// requests its `capabilities` (from its `initialize`) support, returning the
+    /// registration id and the session's request channel
+    pub fn register_downstream_peer(
+        &self,
+        capabilities: &serde_json::Value,
+    ) -> (u64, mpsc::UnboundedReceiver<UpstreamRequest>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let mut downstream = self.downstream_peers.lock().unwrap();
+
+        let id = downstream.next_id;
+        downstream.next_id += 1;
+        downstream.peers.push(DownstreamPeer {
+            id,
+            capabilities: capabilities.clone(),
+            requests: tx,
+        });
+        self.relayed_capabilities
+            .send_replace(downstream.relayed_capabilities());
+        (id, rx)
+    }
+
+    /// Stop routing upstream requests to a downstream session
+    pub fn unregister_downstream_peer(&self, id: u64) {
+        let mut downstream = self.downstream_peers.lock().unwrap();
+        downstream.peers.retain(|peer| peer.id != id);
+        downstream.calls.retain(|_, peer| *peer != id);
+        self.relayed_capabilities
+            .send_replace(downstream.relayed_capabilities());
+    }
+
+    /// Watch the client capabilities offered to upstream servers that connect; receivers
+    /// are woken whenever a downstream session registers or goes away
+    pub fn subscribe_relayed_capabilities(&self) -> watch::Receiver<serde_json::Value> {
+        self.relayed_capabilities.subscribe()
+    }
+
+    /// Record that downstream session `peer` issued the upstream request `upstream_id`
+    /// to `group`, for as long as the returned tag lives
+    pub fn tag_upstream_call(&self, peer: u64, group: &str, upstream_id: &str) -> UpstreamCallTag {
+        let key = (group.to_string(), upstream_id.to_string());
+        self.downstream_peers
+            .lock()
+            .unwrap()
+            .calls
+            .insert(key.clone(), peer);
+        UpstreamCallTag {
+            downstream_peers: self.downstream_peers.clone(),
+            key,
+        }
+    }
+
+    /// Send a notification to every connected group
+    pub async fn notify_groups(&self, notification: &JsonRpcRequest) {
+        for (name, state) in &self.groups {
+            if let GroupState::Connected { transport, .. } = state {
+                if let Err(e) = transport.send_notification(notification).await {
+                    tracing::warn!("Failed to notify {}: {}", name, e);
+                }
+            }
         }
     }
 
-    pub async fn connect(&mut self, group_name: String, config: McpServerConfig) -> Result<()> {
-        if self.groups.contains_key(&group_name) {
+    /// Receive notifications from all upstream groups
+    pub fn subscribe_upstream_notifications(&self) -> broadcast::Receiver<UpstreamNotification> {
+        self.upstream_notifications.subscribe()
+    }
+
+    /// Watch the names of the connected groups; receivers are woken when groups connect or go
+    /// away, and when a group's tools change
+    pub fn subscribe_group_changes(&self) -> watch::Receiver<Vec<String>> {
+        self.connected_groups.subscribe()
+    }
+
+    fn publish_group_changes(&mut self) {
+        self.tool_index = ToolIndex::build(
+            self.groups
+                .iter()
+                .filter_map(|(name, state)| match state {
+                    GroupState::Connected { tools, .. } => {
+                        Some(tools.iter().map(move |tool| (name.as_str(), tool)))
+                    }
+                    GroupState::Failed { .. } => None,
+                })
+                .flatten(),
+        );
+
+        let mut names: Vec<String> = self
+            .groups
+            .iter()
+            .filter(|(_, state)| matches!(state, GroupState::Connected { .. }))
+            .map(|(name, _)| name.clone())
+            .collect();
+        names.sort();
+
+        self.connected_groups.send_if_modified(|current| {
+            if *current == names {
+                false
+            } else {
+                *current = names;
+                true
+            }
+        });
+    }
+
+    pub async fn connect(&mut self, group_name: String, mut config: McpServerConfig) -> Result<()> {
+        // Failed groups are reconnected in place; only live connections are kept as-is
+        if matches!(
+            self.groups.get(&group_name),
+            Some(GroupState::Connected { .. })
+        ) {
             return Ok(());
         }
 
@@ -50,9 +498,42 @@ impl ModularMcpClient {
         .with_context(|| format!("Transport creation timed out for group: {}", group_name))?
         .with_context(|| format!("Failed to create transport for group: {}", group_name))?;
 
+        let notifications = self.upstream_notifications.clone();
+        let downstream_peers = self.downstream_peers.clone();
+        let resource_routes = self.resource_routes.clone();
+        let notifying_group = group_name.clone();
+        transport.set_incoming_handler(Arc::new(move |message: IncomingMessage| match message {
+            IncomingMessage::Notification(notification) => {
+                if notification.method == "notifications/resources/list_changed" {
+                    resource_routes.lock().unwrap().invalidate(&notifying_group);
+                }
+                // Nobody listening is fine; the notification is simply dropped
+                let _ = notifications.send(UpstreamNotification {
+                    group: notifying_group.clone(),
+                    notification,
+                });
+            }
+            IncomingMessage::Request(request, responder) => {
+                if let Ok(downstream) = downstream_peers.lock() {
+                    downstream.route(UpstreamRequest {
+                        group: notifying_group.clone(),
+                        request,
+                        responder,
+                    });
+                }
+            }
+        }));
+
+        // Only stdio upstreams can send requests for the proxy to relay. They are offered what
+        // the sessions registered by now can answer; groups are not reconnected for later ones.
+        let capabilities = if transport.delivers_server_messages() {
+            self.relayed_capabilities.borrow().clone()
+        } else {
+            json!({})
+        };
         let init_request = JsonRpcRequest::new(1, "initialize").with_params(json!({
             "protocolVersion": "2025-06-18",
-            "capabilities": {},
+            "capabilities": capabilities,
             "clientInfo": {
                 "name": "dynamic-mcp-client",
                 "version": env!("CARGO_PKG_VERSION")
@@ -81,11 +562,17 @@ impl ModularMcpClient {
             .and_then(|r| r.get("protocolVersion"))
             .and_then(|v| v.as_str())
             .unwrap_or("2025-06-18");
+        let mut server_capabilities = response
+            .result
+            .as_ref()
+            .and_then(|r| r.get("capabilities"))
+            .cloned()
+            .unwrap_or(json!({}));
 
         if server_version != "2025-06-18" {
             let retry_request = JsonRpcRequest::new(2, "initialize").with_params(json!({
                 "protocolVersion": server_version,
-                "capabilities": {},
+                "capabilities": capabilities,
                 "clientInfo": {
                     "name": "dynamic-mcp-client",
                     "version": env!("CARGO_PKG_VERSION")
@@ -113,6 +600,14 @@ impl ModularMcpClient {
                     error.message
                 );
             }
+
+            if let Some(capabilities) = retry_response
+                .result
+                .as_ref()
+                .and_then(|r| r.get("capabilities"))
+            {
+                server_capabilities = capabilities.clone();
+            }
         }
 
         transport.set_protocol_version(server_version.to_string());
@@ -120,52 +615,27 @@ impl ModularMcpClient {
         let session_id = uuid::Uuid::new_v4().to_string();
         transport.set_session_id(session_id);
 
-        // Only list tools if tools feature is enabled
         let tools = if config.features().tools {
-            let list_tools_request = JsonRpcRequest::new(3, "tools/list");
-            let tools_response = tokio::time::timeout(
-                Duration::from_secs(5),
-                transport.send_request(&list_tools_request),
-            )
-            .await
-            .with_context(|| format!("List tools request timed out for: {}", group_name))?
-            .with_context(|| format!("Failed to list tools from: {}", group_name))?;
-
-            if let Some(result) = tools_response.result {
-                if let Some(tools_array) = result.get("tools").and_then(|v| v.as_array()) {
-                    tools_array
-                        .iter()
-                        .filter_map(|tool| {
-                            Some(ToolInfo {
-                                name: tool.get("name")?.as_str()?.to_string(),
-                                description: tool
-                                    .get("description")
-                                    .and_then(|v| v.as_str())
-                                    .map(String::from),
-                                input_schema: tool.get("inputSchema").cloned().unwrap_or(json!({})),
-                            })
-                        })
-                        .collect()
-                } else {
-                    Vec::new()
-                }
-            } else {
-                Vec::new()
-            }
+            let tools = list_upstream_tools(&transport, &config, &group_name).await?;
+            present_tools(&mut config, tools, &group_name)
         } else {
             Vec::new()
         };
 
+        self.resource_routes.lock().unwrap().invalidate(&group_name);
         self.groups.insert(
             group_name.clone(),
             GroupState::Connected {
-                name: group_name,
+                name: group_name.clone(),
                 description,
                 tools,
                 transport,
                 config,
+                capabilities: server_capabilities,
             },
         );
+        self.publish_group_changes();
+        self.restore_resource_subscriptions(&group_name).await;
 
         Ok(())
     }
@@ -193,6 +663,7 @@ impl ModularMcpClient {
                 config,
             },
         );
+        self.publish_group_changes();
     }
 
     pub fn list_groups(&self) -> Vec<GroupInfo> {
@@ -229,9 +700,15 @@ impl ModularMcpClient {
             .collect()
     }
 
-    pub async fn retry_failed_connections(&mut self) -> Vec<String> {
-        const MAX_RETRIES: u32 = 3;
+    /// Whether any failed group has retries left, so [`Self::retry_failed_connections`]
+    /// has work to do
+    pub fn has_retryable_groups(&self) -> bool {
+        self.groups.values().any(|state| {
+            matches!(state, GroupState::Failed { retry_count, .. } if *retry_count < MAX_RETRIES)
+        })
+    }
 
+    pub async fn retry_failed_connections(&mut self) -> Vec<String> {
         let failed_groups: Vec<_> = self
             .groups
             .iter()
@@ -302,6 +779,42 @@ impl ModularMcpClient {
         successfully_retried
     }
 
+    /// Configuration of a group, connected or not
+    pub fn group_config(&self, group_name: &str) -> Option<&McpServerConfig> {
+        self.groups.get(group_name).map(|state| match state {
+            GroupState::Connected { config, .. } => config,
+            GroupState::Failed { config, .. } => config,
+        })
+    }
+
+    /// List a connected group's tools anew from the upstream server, for a group that
+    /// reported `notifications/tools/list_changed`. Store them with [`Self::set_tools`],
+    /// which presents them by the group's `tool_overrides`.
+    pub async fn fetch_tools(&self, group_name: &str) -> Result<Vec<ToolInfo>> {
+        match self.groups.get(group_name) {
+            Some(GroupState::Connected {
+                transport, config, ..
+            }) if config.features().tools => {
+                list_upstream_tools(transport, config, group_name).await
+            }
+            Some(GroupState::Connected { .. }) => Ok(Vec::new()),
+            _ => anyhow::bail!("Group {} is not connected", group_name),
+        }
+    }
+
+    /// Replace a connected group's tools with upstream tools from [`Self::fetch_tools`],
+    /// telling downstream sessions its tools changed
+    pub fn set_tools(&mut self, group_name: &str, new_tools: Vec<ToolInfo>) {
+        let Some(GroupState::Connected { tools, config, .. }) = self.groups.get_mut(group_name)
+        else {
+            return;
+        };
+        *tools = present_tools(config, new_tools, group_name);
+        self.publish_group_changes();
+        // The group names are the same, but sessions still have to re-list tools
+        self.connected_groups.send_modify(|_| {});
+    }
+
     pub fn list_tools(&self, group_name: &str) -> Result<Vec<ToolInfo>> {
         let group = self.groups.get(group_name).context("Group not found")?;
 
@@ -317,11 +830,23 @@ impl ModularMcpClient {
         }
     }
 
+    /// The `limit` tools across all connected groups that best match a free-text `query`
+    pub fn search_tools(&self, query: &str, limit: usize) -> Vec<ToolMatch> {
+        self.tool_index.search(query, limit)
+    }
+
+    /// Call a tool on a group. `request_id` becomes the upstream JSON-RPC id, so the
+    /// caller can later cancel the call with [`Self::cancel_request`]. With a
+    /// `progress_token`, a stdio server may report progress, which restarts the tool
+    /// timeout when the group's config sets `reset_on_progress`. HTTP and SSE servers
+    /// cannot send notifications to the proxy, so their calls keep a fixed timeout.
     pub async fn call_tool(
         &self,
         group_name: &str,
         tool_name: &str,
         arguments: serde_json::Value,
+        request_id: String,
+        progress_token: Option<serde_json::Value>,
     ) -> Result<serde_json::Value> {
         let group = self.groups.get(group_name).context("Group not found")?;
 
@@ -329,17 +854,53 @@ impl ModularMcpClient {
             GroupState::Connected {
                 transport, config, ..
             } => {
-                let request = JsonRpcRequest::new(uuid::Uuid::new_v4().to_string(), "tools/call")
-                    .with_params(json!({
-                        "name": tool_name,
-                        "arguments": arguments
-                    }));
+                // Hidden tools are not listed, but could still be called by name
+                let Some(upstream_name) = config
+                    .upstream_tool_name(tool_name)
+                    .filter(|name| config.tool_filter().allows(name))
+                else {
+                    anyhow::bail!(
+                        "Tool {} is not available in group {}",
+                        tool_name,
+                        group_name
+                    );
+                };
+
+                // Upstreams that cannot send notifications would never report progress
+                let progress_token =
+                    progress_token.filter(|_| transport.delivers_server_messages());
+                let mut params = json!({
+                    "name": upstream_name,
+                    "arguments": arguments
+                });
+                if let Some(token) = &progress_token {
+                    params["_meta"] = json!({ "progressToken": token });
+                }
+                let request = JsonRpcRequest::new(request_id, "tools/call").with_params(params);
 
-                let response =
-                    tokio::time::timeout(config.tool_timeout(), transport.send_request(&request))
+                let response = match progress_token.filter(|_| config.resets_timeout_on_progress())
+                {
+                    Some(token) => {
+                        // Subscribe before sending so early progress is not missed
+                        let mut notifications = self.upstream_notifications.subscribe();
+                        timeout_with_progress(
+                            config.tool_timeout(),
+                            group_name,
+                            &token,
+                            &mut notifications,
+                            transport.send_request(&request),
+                        )
                         .await
                         .with_context(|| format!("Tool call timed out: {}", tool_name))?
-                        .with_context(|| format!("Tool call failed: {}", tool_name))?;
+                    }
+                    None => tokio::time::timeout(
+                        config.tool_timeout(),
+                        transport.send_request(&request),
+                    )
+                    .await
+                    .with_context(|| format!("Tool call timed out: {}", tool_name))?,
+                }
+                .with_context(|| format!("Tool call failed: {}", tool_name))?;
 
                 if let Some(error) = response.error {
                     return Err(anyhow::anyhow!("Tool call failed: {}", error.message));
@@ -357,6 +918,37 @@ impl ModularMcpClient {
         }
     }
 
+    /// Tell a group to stop working on a request it received from the proxy
+    pub async fn cancel_request(
+        &self,
+        group_name: &str,
+        request_id: serde_json::Value,
+        reason: Option<&str>,
+    ) -> Result<()> {
+        let group = self.groups.get(group_name).context("Group not found")?;
+
+        match group {
+            GroupState::Connected { transport, .. } => {
+                let mut params = json!({ "requestId": request_id });
+                if let Some(reason) = reason {
+                    params["reason"] = json!(reason);
+                }
+
+                let notification =
+                    JsonRpcRequest::notification("notifications/cancelled").with_params(params);
+                transport
+                    .send_notification(&notification)
+                    .await
+                    .with_context(|| format!("Failed to send cancellation to {}", group_name))
+            }
+            GroupState::Failed { error, .. } => Err(anyhow::anyhow!(
+                "Group {} is not connected: {}",
+                group_name,
+                error
+            )),
+        }
+    }
+
     pub async fn proxy_resources_list(
         &self,
         group_name: &str,
@@ -397,7 +989,12 @@ impl ModularMcpClient {
                     return Err(anyhow::anyhow!("Upstream error: {}", error.message));
                 }
 
-                Ok(response.result.unwrap_or(json!({})))
+                Ok(retain_allowed(
+                    response.result.unwrap_or(json!({})),
+                    "resources",
+                    "uri",
+                    config.resource_filter(),
+                ))
             }
             GroupState::Failed {
                 error, retry_count, ..
@@ -427,6 +1024,9 @@ impl ModularMcpClient {
                         group_name
                     ));
                 }
+                if !config.resource_filter().allows(&uri) {
+                    anyhow::bail!("Resource {} is not available in group {}", uri, group_name);
+                }
 
                 let request =
                     JsonRpcRequest::new(uuid::Uuid::new_v4().to_string(), "resources/read")
@@ -456,36 +1056,112 @@ impl ModularMcpClient {
         }
     }
 
-    pub async fn proxy_resources_templates_list(
+    /// Whether any connected group offers argument completion
+    pub fn supports_completions(&self) -> bool {
+        self.groups.values().any(|state| {
+            matches!(state, GroupState::Connected { capabilities, .. }
+                if capabilities.get("completions").is_some())
+        })
+    }
+
+    /// Connected groups to look up completion targets in, those offering `completions` first
+    fn completion_candidates(&self) -> Vec<String> {
+        let mut candidates: Vec<(bool, String)> = self
+            .groups
+            .values()
+            .filter_map(|state| match state {
+                GroupState::Connected {
+                    name, capabilities, ..
+                } => Some((capabilities.get("completions").is_none(), name.clone())),
+                _ => None,
+            })
+            .collect();
+        candidates.sort();
+        candidates.into_iter().map(|(_, name)| name).collect()
+    }
+
+    /// Find the connected group whose `prompts/list` contains `name`, preferring groups
+    /// that can complete its arguments
+    pub async fn find_prompt_group(&self, name: &str) -> Option<String> {
+        for group in self.completion_candidates() {
+            if self.lists_prompt(&group, name).await {
+                return Some(group);
+            }
+        }
+        None
+    }
+
+    /// Find the connected group whose `resources/templates/list` contains `uri_template`,
+    /// preferring groups that can complete its variables
+    pub async fn find_resource_template_group(&self, uri_template: &str) -> Option<String> {
+        for group in self.completion_candidates() {
+            if self.lists_resource_template(&group, uri_template).await {
+                return Some(group);
+            }
+        }
+        None
+    }
+
+    /// Whether any page of `group`'s `prompts/list`, as filtered, contains `name`
+    pub async fn lists_prompt(&self, group: &str, name: &str) -> bool {
+        any_page_lists(
+            |cursor| self.proxy_prompts_list(group, cursor),
+            "prompts",
+            "name",
+            name,
+        )
+        .await
+    }
+
+    /// Whether any page of `group`'s `resources/templates/list`, as filtered, contains
+    /// `uri_template`
+    pub async fn lists_resource_template(&self, group: &str, uri_template: &str) -> bool {
+        any_page_lists(
+            |cursor| self.proxy_resources_templates_list(group, cursor),
+            "resourceTemplates",
+            "uriTemplate",
+            uri_template,
+        )
+        .await
+    }
+
+    /// Forward `completion/complete` to a group.
+    ///
+    /// Groups without the `completions` capability get an empty completion instead of a
+    /// request they would reject.
+    pub async fn proxy_completion_complete(
         &self,
         group_name: &str,
+        params: serde_json::Value,
     ) -> Result<serde_json::Value> {
         let group = self.groups.get(group_name).context("Group not found")?;
 
         match group {
             GroupState::Connected {
-                transport, config, ..
+                transport,
+                config,
+                capabilities,
+                ..
             } => {
-                // Check if resources feature is enabled
-                if !config.features().resources {
-                    return Err(anyhow::anyhow!(
-                        "Resources feature is disabled for group: {}",
-                        group_name
-                    ));
+                if capabilities.get("completions").is_none() {
+                    return Ok(json!({
+                        "completion": { "values": [], "hasMore": false }
+                    }));
                 }
 
-                let request = JsonRpcRequest::new(
-                    uuid::Uuid::new_v4().to_string(),
-                    "resources/templates/list",
-                );
+                let timeout = match params.pointer("/ref/type").and_then(|t| t.as_str()) {
+                    Some("ref/resource") => config.resource_timeout(),
+                    _ => config.prompt_timeout(),
+                };
 
-                let response = tokio::time::timeout(
-                    config.resource_timeout(),
-                    transport.send_request(&request),
-                )
-                .await
-                .with_context(|| "resources/templates/list request timed out")?
-                .with_context(|| "Failed to list resource templates from upstream server")?;
+                let request =
+                    JsonRpcRequest::new(uuid::Uuid::new_v4().to_string(), "completion/complete")
+                        .with_params(params);
+
+                let response = tokio::time::timeout(timeout, transport.send_request(&request))
+                    .await
+                    .with_context(|| "completion/complete request timed out")?
+                    .with_context(|| "Failed to get completions from upstream server")?;
 
                 if let Some(error) = response.error {
                     return Err(anyhow::anyhow!("Upstream error: {}", error.message));
@@ -503,26 +1179,375 @@ impl ModularMcpClient {
         }
     }
 
-    pub async fn proxy_prompts_list(
-        &self,
-        group_name: &str,
-        cursor: Option<String>,
-    ) -> Result<serde_json::Value> {
-        let group = self.groups.get(group_name).context("Group not found")?;
+    /// Find the connected group that lists `uri`, or has a resource template matching it.
+    ///
+    /// Groups are looked up in the routing index, listing only those not indexed yet. A URI
+    /// no group is known for re-lists every group once, in case a server added it without
+    /// sending `notifications/resources/list_changed`. If that finds nothing either, the URI
+    /// is not looked up again until the index is next invalidated.
+    pub async fn find_resource_group(&self, uri: &str) -> Option<String> {
+        let groups = self.connected_groups.borrow().clone();
+
+        for group in &groups {
+            self.ensure_resource_routes(group).await;
+        }
+        let generation = {
+            let routing = self.resource_routes.lock().unwrap();
+            if let Some(group) = routing.route(&groups, uri) {
+                return Some(group);
+            }
+            if routing.unroutable.contains(uri) {
+                return None;
+            }
+            routing.generation
+        };
 
-        match group {
-            GroupState::Connected {
-                transport, config, ..
-            } => {
-                // Check if prompts feature is enabled
-                if !config.features().prompts {
-                    return Err(anyhow::anyhow!(
-                        "Prompts feature is disabled for group: {}",
-                        group_name
-                    ));
-                }
+        for group in &groups {
+            self.index_resources(group).await;
+        }
+        let mut routing = self.resource_routes.lock().unwrap();
+        let group = routing.route(&groups, uri);
+        if group.is_none() {
+            routing.mark_unroutable(generation, uri);
+        }
+        group
+    }
 
-                let mut params = json!({});
+    async fn group_lists_resource(&self, group_name: &str, uri: &str) -> bool {
+        self.ensure_resource_routes(group_name).await;
+        self.resource_routes
+            .lock()
+            .unwrap()
+            .groups
+            .get(group_name)
+            .is_some_and(|routes| routes.uris.contains(uri) || routes.matches_template(uri))
+    }
+
+    async fn ensure_resource_routes(&self, group_name: &str) {
+        let indexed = self
+            .resource_routes
+            .lock()
+            .unwrap()
+            .groups
+            .contains_key(group_name);
+        if !indexed {
+            self.index_resources(group_name).await;
+        }
+    }
+
+    /// List every page of a group's resources and resource templates into the routing index
+    async fn index_resources(&self, group_name: &str) {
+        let generation = self.resource_routes.lock().unwrap().generation;
+        let mut routes = ResourceRoutes::default();
+
+        for resource in self.list_all_pages(group_name, "resources").await {
+            if let Some(uri) = resource.get("uri").and_then(|u| u.as_str()) {
+                routes.uris.insert(uri.to_string());
+            }
+        }
+        for template in self.list_all_pages(group_name, "resourceTemplates").await {
+            let Some(uri_template) = template.get("uriTemplate").and_then(|u| u.as_str()) else {
+                continue;
+            };
+            match UriTemplate::parse(uri_template) {
+                Some(parsed) => routes.templates.push(parsed),
+                None => tracing::debug!(
+                    "Ignoring malformed URI template {} from {}",
+                    uri_template,
+                    group_name
+                ),
+            }
+        }
+
+        let mut routing = self.resource_routes.lock().unwrap();
+        // An invalidation while listing means the result may already be stale; the next
+        // lookup lists again
+        if routing.generation == generation {
+            routing.groups.insert(group_name.to_string(), routes);
+        }
+    }
+
+    /// Items of `field` across all pages of `resources/list` or `resources/templates/list`.
+    /// Listing stops at the first failed page.
+    async fn list_all_pages(&self, group_name: &str, field: &str) -> Vec<serde_json::Value> {
+        let mut items = Vec::new();
+        let mut cursor = None;
+
+        for _ in 0..MAX_RESOURCE_INDEX_PAGES {
+            let page = if field == "resources" {
+                self.proxy_resources_list(group_name, cursor).await
+            } else {
+                self.proxy_resources_templates_list(group_name, cursor)
+                    .await
+            };
+            let page = match page {
+                Ok(page) => page,
+                Err(e) => {
+                    tracing::debug!("Not indexing {} of {}: {}", field, group_name, e);
+                    break;
+                }
+            };
+
+            if let Some(page_items) = page.get(field).and_then(|v| v.as_array()) {
+                items.extend(page_items.iter().cloned());
+            }
+            cursor = page
+                .get("nextCursor")
+                .and_then(|c| c.as_str())
+                .map(String::from);
+            if cursor.is_none() {
+                break;
+            }
+        }
+        items
+    }
+
+    /// Whether `group_name` is connected over a transport that delivers its notifications
+    fn delivers_notifications(&self, group_name: &str) -> bool {
+        matches!(
+            self.groups.get(group_name),
+            Some(GroupState::Connected { transport, .. }) if transport.delivers_server_messages()
+        )
+    }
+
+    /// Subscribe one downstream session to updates of `uri`.
+    ///
+    /// The first subscriber sends `resources/subscribe` to the owning group. If no connected
+    /// group lists the URI yet, the subscription is kept and sent once such a group connects.
+    /// Groups whose transport cannot deliver `notifications/resources/updated` refuse the
+    /// subscription.
+    pub async fn subscribe_resource(&self, uri: &str) -> Result<()> {
+        let first = {
+            let mut subscriptions = self.resource_subscriptions.lock().unwrap();
+            let subscription =
+                subscriptions
+                    .entry(uri.to_string())
+                    .or_insert(ResourceSubscription {
+                        group: None,
+                        subscribers: 0,
+                    });
+            subscription.subscribers += 1;
+            subscription.subscribers == 1
+        };
+
+        if !first {
+            return Ok(());
+        }
+
+        let Some(group_name) = self.find_resource_group(uri).await else {
+            tracing::debug!(
+                "No connected group lists {}, subscribing once one does",
+                uri
+            );
+            return Ok(());
+        };
+
+        if !self.delivers_notifications(&group_name) {
+            self.release_resource_subscription(uri);
+            anyhow::bail!(
+                "Group {} cannot send resource updates over its transport",
+                group_name
+            );
+        }
+
+        if let Err(e) = self
+            .send_resource_subscription(&group_name, "resources/subscribe", uri)
+            .await
+        {
+            self.release_resource_subscription(uri);
+            return Err(e);
+        }
+
+        if let Some(subscription) = self.resource_subscriptions.lock().unwrap().get_mut(uri) {
+            subscription.group = Some(group_name);
+        }
+        Ok(())
+    }
+
+    /// Drop one downstream session's subscription to `uri`; the last one unsubscribes upstream
+    pub async fn unsubscribe_resource(&self, uri: &str) -> Result<()> {
+        let Some(group_name) = self.release_resource_subscription(uri) else {
+            return Ok(());
+        };
+
+        self.send_resource_subscription(&group_name, "resources/unsubscribe", uri)
+            .await
+    }
+
+    /// Decrement the subscriber count, returning the owning group if this was the last subscriber
+    fn release_resource_subscription(&self, uri: &str) -> Option<String> {
+        let mut subscriptions = self.resource_subscriptions.lock().unwrap();
+        let subscription = subscriptions.get_mut(uri)?;
+        subscription.subscribers = subscription.subscribers.saturating_sub(1);
+        if subscription.subscribers > 0 {
+            return None;
+        }
+        subscriptions.remove(uri).and_then(|s| s.group)
+    }
+
+    /// Re-send subscriptions owned by a (re)connected group, and claim pending ones it now lists
+    async fn restore_resource_subscriptions(&self, group_name: &str) {
+        if !self.delivers_notifications(group_name) {
+            return;
+        }
+
+        let candidates: Vec<(String, bool)> = self
+            .resource_subscriptions
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|(uri, subscription)| match &subscription.group {
+                Some(group) if group == group_name => Some((uri.clone(), true)),
+                None => Some((uri.clone(), false)),
+                Some(_) => None,
+            })
+            .collect();
+
+        for (uri, owned) in candidates {
+            if !owned && !self.group_lists_resource(group_name, &uri).await {
+                continue;
+            }
+
+            match self
+                .send_resource_subscription(group_name, "resources/subscribe", &uri)
+                .await
+            {
+                Ok(()) => {
+                    if let Some(subscription) =
+                        self.resource_subscriptions.lock().unwrap().get_mut(&uri)
+                    {
+                        subscription.group = Some(group_name.to_string());
+                    }
+                    tracing::debug!("Subscribed {} to resource updates for {}", group_name, uri);
+                }
+                Err(e) => {
+                    tracing::warn!(
+                        "Failed to restore resource subscription for {} on {}: {}",
+                        uri,
+                        group_name,
+                        e
+                    );
+                }
+            }
+        }
+    }
+
+    async fn send_resource_subscription(
+        &self,
+        group_name: &str,
+        method: &str,
+        uri: &str,
+    ) -> Result<()> {
+        let group = self.groups.get(group_name).context("Group not found")?;
+
+        match group {
+            GroupState::Connected {
+                transport, config, ..
+            } => {
+                let request = JsonRpcRequest::new(uuid::Uuid::new_v4().to_string(), method)
+                    .with_params(json!({ "uri": uri }));
+
+                let response = tokio::time::timeout(
+                    config.resource_timeout(),
+                    transport.send_request(&request),
+                )
+                .await
+                .with_context(|| format!("{} request timed out", method))?
+                .with_context(|| format!("Failed to send {} to upstream server", method))?;
+
+                if let Some(error) = response.error {
+                    return Err(anyhow::anyhow!("Upstream error: {}", error.message));
+                }
+
+                Ok(())
+            }
+            GroupState::Failed { error, .. } => Err(anyhow::anyhow!(
+                "Group {} is not connected: {}",
+                group_name,
+                error
+            )),
+        }
+    }
+
+    pub async fn proxy_resources_templates_list(
+        &self,
+        group_name: &str,
+        cursor: Option<String>,
+    ) -> Result<serde_json::Value> {
+        let group = self.groups.get(group_name).context("Group not found")?;
+
+        match group {
+            GroupState::Connected {
+                transport, config, ..
+            } => {
+                // Check if resources feature is enabled
+                if !config.features().resources {
+                    return Err(anyhow::anyhow!(
+                        "Resources feature is disabled for group: {}",
+                        group_name
+                    ));
+                }
+
+                let mut params = json!({});
+                if let Some(cursor) = cursor {
+                    params["cursor"] = json!(cursor);
+                }
+
+                let request = JsonRpcRequest::new(
+                    uuid::Uuid::new_v4().to_string(),
+                    "resources/templates/list",
+                )
+                .with_params(params);
+
+                let response = tokio::time::timeout(
+                    config.resource_timeout(),
+                    transport.send_request(&request),
+                )
+                .await
+                .with_context(|| "resources/templates/list request timed out")?
+                .with_context(|| "Failed to list resource templates from upstream server")?;
+
+                if let Some(error) = response.error {
+                    return Err(anyhow::anyhow!("Upstream error: {}", error.message));
+                }
+
+                Ok(retain_allowed(
+                    response.result.unwrap_or(json!({})),
+                    "resourceTemplates",
+                    "uriTemplate",
+                    config.resource_template_filter(),
+                ))
+            }
+            GroupState::Failed {
+                error, retry_count, ..
+            } => Err(anyhow::anyhow!(
+                "Group failed to connect after {} attempts: {}",
+                retry_count + 1,
+                error
+            )),
+        }
+    }
+
+    pub async fn proxy_prompts_list(
+        &self,
+        group_name: &str,
+        cursor: Option<String>,
+    ) -> Result<serde_json::Value> {
+        let group = self.groups.get(group_name).context("Group not found")?;
+
+        match group {
+            GroupState::Connected {
+                transport, config, ..
+            } => {
+                // Check if prompts feature is enabled
+                if !config.features().prompts {
+                    return Err(anyhow::anyhow!(
+                        "Prompts feature is disabled for group: {}",
+                        group_name
+                    ));
+                }
+
+                let mut params = json!({});
                 if let Some(cursor) = cursor {
                     params["cursor"] = json!(cursor);
                 }
@@ -540,7 +1565,12 @@ impl ModularMcpClient {
                     return Err(anyhow::anyhow!("Upstream error: {}", error.message));
                 }
 
-                Ok(response.result.unwrap_or(json!({})))
+                Ok(retain_allowed(
+                    response.result.unwrap_or(json!({})),
+                    "prompts",
+                    "name",
+                    config.prompt_filter(),
+                ))
             }
             GroupState::Failed {
                 error, retry_count, ..
@@ -571,6 +1601,13 @@ impl ModularMcpClient {
                         group_name
                     ));
                 }
+                if !config.prompt_filter().allows(&prompt_name) {
+                    anyhow::bail!(
+                        "Prompt {} is not available in group {}",
+                        prompt_name,
+                        group_name
+                    );
+                }
 
                 let mut params = json!({ "name": prompt_name });
                 if let Some(args) = arguments {
@@ -610,6 +1647,1135 @@ impl ModularMcpClient {
                 let _ = transport.close().await;
             }
         }
+        *self.resource_routes.lock().unwrap() = ResourceRouting::default();
+        self.publish_group_changes();
         Ok(())
     }
 }
+
+/// List every page of a group's tools, keeping those its filter lets through under their
+/// upstream names. Listing stops at the first page without a result.
+async fn list_upstream_tools(
+    transport: &Transport,
+    config: &McpServerConfig,
+    group_name: &str,
+) -> Result<Vec<ToolInfo>> {
+    let mut tools = Vec::new();
+    let mut cursor: Option<String> = None;
+
+    for _ in 0..MAX_TOOL_LIST_PAGES {
+        let mut request = JsonRpcRequest::new(uuid::Uuid::new_v4().to_string(), "tools/list");
+        if let Some(cursor) = &cursor {
+            request = request.with_params(json!({ "cursor": cursor }));
+        }
+        let response =
+            tokio::time::timeout(Duration::from_secs(5), transport.send_request(&request))
+                .await
+                .with_context(|| format!("List tools request timed out for: {}", group_name))?
+                .with_context(|| format!("Failed to list tools from: {}", group_name))?;
+
+        let Some(result) = response.result else {
+            break;
+        };
+        if let Some(page) = result.get("tools").and_then(|v| v.as_array()) {
+            tools.extend(page.iter().filter_map(|tool| {
+                Some(ToolInfo {
+                    name: tool.get("name")?.as_str()?.to_string(),
+                    title: tool.get("title").and_then(|v| v.as_str()).map(String::from),
+                    description: tool
+                        .get("description")
+                        .and_then(|v| v.as_str())
+                        .map(String::from),
+                    input_schema: tool.get("inputSchema").cloned().unwrap_or(json!({})),
+                    output_schema: tool.get("outputSchema").cloned(),
+                })
+            }));
+        }
+        cursor = result
+            .get("nextCursor")
+            .and_then(|c| c.as_str())
+            .map(String::from);
+        if cursor.is_none() {
+            break;
+        }
+    }
+
+    Ok(tools
+        .into_iter()
+        .filter(|tool| config.tool_filter().allows(&tool.name))
+        .collect())
+}
+
+/// Present a group's upstream tools by their `tool_overrides`. A new name or alias must not
+/// hide another tool of the server, so one that does is dropped from `config` with a warning.
+fn present_tools(
+    config: &mut McpServerConfig,
+    tools: Vec<ToolInfo>,
+    group_name: &str,
+) -> Vec<ToolInfo> {
+    let upstream_names: HashSet<&str> = tools.iter().map(|tool| tool.name.as_str()).collect();
+    for (upstream, tool_override) in config.tool_overrides_mut() {
+        let hides = |name: &String| name != upstream && upstream_names.contains(name.as_str());
+        if let Some(name) = tool_override.name.as_ref().filter(|name| hides(name)) {
+            tracing::warn!(
+                "tool_overrides of {} give {} the name of another of its tools, {}; keeping its upstream name",
+                group_name,
+                upstream,
+                name
+            );
+            tool_override.name = None;
+        }
+        tool_override.aliases.retain(|alias| {
+            if !hides(alias) {
+                return true;
+            }
+            tracing::warn!(
+                "tool_overrides of {} give {} the alias {}, which is another of its tools; dropping the alias",
+                group_name,
+                upstream,
+                alias
+            );
+            false
+        });
+    }
+
+    tools
+        .into_iter()
+        .map(|mut tool| {
+            if let Some(tool_override) = config.tool_overrides().get(&tool.name) {
+                apply_tool_override(&mut tool, tool_override);
+            }
+            tool
+        })
+        .collect()
+}
+
+/// Present an upstream tool the way its `tool_overrides` entry asks
+fn apply_tool_override(tool: &mut ToolInfo, tool_override: &ToolOverride) {
+    if let Some(name) = &tool_override.name {
+        tool.name = name.clone();
+    }
+    if let Some(description) = &tool_override.description {
+        tool.description = Some(description.clone());
+    }
+    if let Some(addition) = &tool_override.append_description {
+        tool.description = Some(match tool.description.as_deref() {
+            Some(description) if !description.trim().is_empty() => {
+                format!("{}\n\n{}", description.trim_end(), addition)
+            }
+            _ => addition.clone(),
+        });
+    }
+    if let Some(properties) = tool
+        .input_schema
+        .get_mut("properties")
+        .and_then(|properties| properties.as_object_mut())
+    {
+        for (parameter, description) in &tool_override.parameter_descriptions {
+            if let Some(property) = properties
+                .get_mut(parameter)
+                .and_then(|property| property.as_object_mut())
+            {
+                property.insert("description".to_string(), json!(description));
+            }
+        }
+    }
+}
+
+/// Page through a listing with `fetch` until an entry of its `key` list has `field` set to
+/// `value`. Listing stops at the first page that fails or has no `nextCursor`.
+async fn any_page_lists<F, Fut>(fetch: F, key: &str, field: &str, value: &str) -> bool
+where
+    F: Fn(Option<String>) -> Fut,
+    Fut: std::future::Future<Output = Result<serde_json::Value>>,
+{
+    let mut cursor = None;
+    for _ in 0..MAX_LOOKUP_PAGES {
+        let Ok(page) = fetch(cursor).await else {
+            return false;
+        };
+
+        let listed = page
+            .get(key)
+            .and_then(|items| items.as_array())
+            .is_some_and(|items| {
+                items
+                    .iter()
+                    .any(|item| item.get(field).and_then(|v| v.as_str()) == Some(value))
+            });
+        if listed {
+            return true;
+        }
+
+        match page.get("nextCursor").and_then(|c| c.as_str()) {
+            Some(next) => cursor = Some(next.to_string()),
+            None => return false,
+        }
+    }
+    false
+}
+
+/// Drop the entries of the `key` list in an upstream listing whose `field` the filter hides
+fn retain_allowed(
+    mut result: serde_json::Value,
+    key: &str,
+    field: &str,
+    filter: &NameFilter,
+) -> serde_json::Value {
+    if let Some(items) = result.get_mut(key).and_then(|items| items.as_array_mut()) {
+        items.retain(|item| {
+            item.get(field)
+                .and_then(|value| value.as_str())
+                .is_some_and(|value| filter.allows(value))
+        });
+    }
+    result
+}
+
+/// Like [`tokio::time::timeout`], but the deadline restarts whenever `group` reports
+/// progress for `token`. Returns `None` when the deadline passes.
+async fn timeout_with_progress<F: std::future::Future>(
+    duration: Duration,
+    group: &str,
+    token: &serde_json::Value,
+    notifications: &mut broadcast::Receiver<UpstreamNotification>,
+    future: F,
+) -> Option<F::Output> {
+    tokio::pin!(future);
+    let deadline = tokio::time::sleep(duration);
+    tokio::pin!(deadline);
+
+    loop {
+        tokio::select! {
+            output = &mut future => return Some(output),
+            _ = &mut deadline => return None,
+            received = notifications.recv() => match received {
+                Ok(upstream) if upstream.is_progress_for(group, token) => {
+                    deadline
+                        .as_mut()
+                        .reset(tokio::time::Instant::now() + duration);
+                }
+                Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Err(broadcast::error::RecvError::Closed) => {
+                    return tokio::time::timeout_at(deadline.deadline(), future)
+                        .await
+                        .ok();
+                }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::schema::Timeout;
+    use crate::proxy::testing::{fake_http_server, wait_for_logged, FakeServer};
+
+    #[tokio::test]
+    async fn test_connect_and_disconnect_publish_group_changes() {
+        let mut client = ModularMcpClient::new();
+        let mut groups = client.subscribe_group_changes();
+
+        client
+            .connect("fake".to_string(), FakeServer::new().config())
+            .await
+            .unwrap();
+        assert!(groups.has_changed().unwrap());
+        assert_eq!(*groups.borrow_and_update(), vec!["fake".to_string()]);
+
+        client.disconnect_all().await.unwrap();
+        assert!(groups.has_changed().unwrap());
+        assert!(groups.borrow_and_update().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_failed_connection_does_not_publish_group_change() {
+        let mut client = ModularMcpClient::new();
+        let groups = client.subscribe_group_changes();
+
+        client.record_failed_connection(
+            "broken".to_string(),
+            FakeServer::new().config(),
+            anyhow::anyhow!("boom"),
+        );
+
+        assert!(!groups.has_changed().unwrap());
+    }
+
+    #[test]
+    fn test_has_retryable_groups_until_retries_run_out() {
+        let mut client = ModularMcpClient::new();
+        assert!(!client.has_retryable_groups());
+
+        for _ in 0..MAX_RETRIES {
+            client.record_failed_connection(
+                "broken".to_string(),
+                FakeServer::new().config(),
+                anyhow::anyhow!("boom"),
+            );
+            assert!(client.has_retryable_groups());
+        }
+        client.record_failed_connection(
+            "broken".to_string(),
+            FakeServer::new().config(),
+            anyhow::anyhow!("boom"),
+        );
+        assert!(!client.has_retryable_groups());
+    }
+
+    #[tokio::test]
+    async fn test_failed_group_reconnects_in_place() {
+        let mut client = ModularMcpClient::new();
+        let config = FakeServer::new()
+            .respond(
+                "tools/list",
+                json!({"tools": [{"name": "echo", "inputSchema": {"type": "object"}}]}),
+            )
+            .config();
+        client.record_failed_connection(
+            "fake".to_string(),
+            config.clone(),
+            anyhow::anyhow!("boom"),
+        );
+
+        client.connect("fake".to_string(), config).await.unwrap();
+
+        assert!(client.list_failed_groups().is_empty());
+        assert_eq!(client.list_tools("fake").unwrap()[0].name, "echo");
+    }
+
+    fn resource_server(log: &std::path::Path) -> McpServerConfig {
+        FakeServer::new()
+            .respond(
+                "resources/list",
+                json!({"resources": [{"uri": "file:///watched.txt", "name": "watched"}]}),
+            )
+            .log_to(log)
+            .config()
+    }
+
+    fn logged_methods(log: &std::path::Path) -> Vec<String> {
+        std::fs::read_to_string(log)
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+            .filter_map(|message| message["method"].as_str().map(String::from))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_resource_subscription_shared_between_subscribers() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("requests.log");
+        let mut client = ModularMcpClient::new();
+        client
+            .connect("files".to_string(), resource_server(&log))
+            .await
+            .unwrap();
+
+        client
+            .subscribe_resource("file:///watched.txt")
+            .await
+            .unwrap();
+        client
+            .subscribe_resource("file:///watched.txt")
+            .await
+            .unwrap();
+        client
+            .unsubscribe_resource("file:///watched.txt")
+            .await
+            .unwrap();
+
+        let methods = logged_methods(&log);
+        assert_eq!(
+            methods
+                .iter()
+                .filter(|m| *m == "resources/subscribe")
+                .count(),
+            1
+        );
+        assert!(!methods.contains(&"resources/unsubscribe".to_string()));
+
+        client
+            .unsubscribe_resource("file:///watched.txt")
+            .await
+            .unwrap();
+        assert!(logged_methods(&log).contains(&"resources/unsubscribe".to_string()));
+
+        client.disconnect_all().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_resource_subscription_restored_after_reconnect() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("requests.log");
+        let mut client = ModularMcpClient::new();
+
+        // Nobody lists the URI yet, so the subscription waits for a group that does
+        client
+            .subscribe_resource("file:///watched.txt")
+            .await
+            .unwrap();
+
+        client
+            .connect("files".to_string(), resource_server(&log))
+            .await
+            .unwrap();
+        assert!(logged_methods(&log).contains(&"resources/subscribe".to_string()));
+
+        client.disconnect_all().await.unwrap();
+        std::fs::remove_file(&log).unwrap();
+
+        client
+            .connect("files".to_string(), resource_server(&log))
+            .await
+            .unwrap();
+        assert!(logged_methods(&log).contains(&"resources/subscribe".to_string()));
+
+        client.disconnect_all().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_resource_subscription_refused_without_notification_delivery() {
+        let mut client = ModularMcpClient::new();
+        let config = fake_http_server(json!({
+            "resources/list": {"resources": [{"uri": "file:///remote.txt", "name": "remote"}]}
+        }))
+        .await;
+        client.connect("remote".to_string(), config).await.unwrap();
+
+        let error = client
+            .subscribe_resource("file:///remote.txt")
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("cannot send resource updates"));
+        assert!(client.resource_subscriptions.lock().unwrap().is_empty());
+
+        client.disconnect_all().await.unwrap();
+    }
+
+    fn count_logged(log: &std::path::Path, method: &str) -> usize {
+        logged_methods(log).iter().filter(|m| *m == method).count()
+    }
+
+    #[tokio::test]
+    async fn test_tool_filter_hides_tools_from_listing_and_calls() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("requests.log");
+        let config = FakeServer::new()
+            .with_tools(&["read_file", "write_file", "delete_file", "delete_repo"])
+            .tool_filter(NameFilter {
+                allow: vec!["read_*".to_string(), "delete_*".to_string()],
+                deny: vec!["*_repo".to_string()],
+            })
+            .log_to(&log)
+            .config();
+        let mut client = ModularMcpClient::new();
+        client.connect("files".to_string(), config).await.unwrap();
+
+        let names: Vec<String> = client
+            .list_tools("files")
+            .unwrap()
+            .into_iter()
+            .map(|tool| tool.name)
+            .collect();
+        assert_eq!(names, ["read_file", "delete_file"]);
+
+        let error = client
+            .call_tool("files", "delete_repo", json!({}), "1".to_string(), None)
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("not available"));
+        assert!(client
+            .call_tool("files", "read_file", json!({}), "2".to_string(), None)
+            .await
+            .is_ok());
+        assert_eq!(count_logged(&log, "tools/call"), 1);
+
+        client.disconnect_all().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_resource_and_prompt_filters_apply_to_listings_and_access() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("requests.log");
+        let config = FakeServer::new()
+            .respond(
+                "resources/list",
+                json!({"resources": [
+                    {"uri": "file:///workspace/docs/guide.md", "name": "guide"},
+                    {"uri": "file:///workspace/.env", "name": "env"}
+                ]}),
+            )
+            .respond(
+                "resources/templates/list",
+                json!({"resourceTemplates": [
+                    {"uriTemplate": "file:///workspace/docs/{name}", "name": "docs"},
+                    {"uriTemplate": "file:///{+path}", "name": "anything"}
+                ]}),
+            )
+            .respond(
+                "prompts/list",
+                json!({"prompts": [{"name": "review"}, {"name": "internal_triage"}]}),
+            )
+            .resource_filter(NameFilter {
+                allow: vec!["file:///workspace/docs/**".to_string()],
+                deny: Vec::new(),
+            })
+            .resource_template_filter(NameFilter {
+                allow: vec!["file:///workspace/docs/*".to_string()],
+                deny: Vec::new(),
+            })
+            .prompt_filter(NameFilter {
+                allow: Vec::new(),
+                deny: vec!["internal_*".to_string()],
+            })
+            .log_to(&log)
+            .config();
+        let mut client = ModularMcpClient::new();
+        client.connect("files".to_string(), config).await.unwrap();
+
+        let resources = client.proxy_resources_list("files", None).await.unwrap();
+        assert_eq!(
+            resources["resources"],
+            json!([{"uri": "file:///workspace/docs/guide.md", "name": "guide"}])
+        );
+        let templates = client
+            .proxy_resources_templates_list("files", None)
+            .await
+            .unwrap();
+        assert_eq!(templates["resourceTemplates"].as_array().unwrap().len(), 1);
+        assert_eq!(templates["resourceTemplates"][0]["name"], "docs");
+        let prompts = client.proxy_prompts_list("files", None).await.unwrap();
+        assert_eq!(prompts["prompts"], json!([{"name": "review"}]));
+
+        assert!(client
+            .proxy_resources_read("files", "file:///workspace/docs/guide.md".to_string())
+            .await
+            .is_ok());
+        let error = client
+            .proxy_resources_read("files", "file:///workspace/.env".to_string())
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("not available"));
+        assert!(client
+            .proxy_prompts_get("files", "internal_triage".to_string(), None)
+            .await
+            .is_err());
+        assert_eq!(count_logged(&log, "resources/read"), 1);
+        assert_eq!(count_logged(&log, "prompts/get"), 0);
+
+        client.disconnect_all().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_tool_overrides_rename_describe_and_translate_calls() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("requests.log");
+        let config = FakeServer::new()
+            .respond(
+                "tools/list",
+                json!({"tools": [
+                    {"name": "search_code", "description": "Search code", "inputSchema": {
+                        "type": "object",
+                        "properties": {"q": {"type": "string", "description": "Query"}}
+                    }},
+                    {"name": "get_me", "inputSchema": {"type": "object"}}
+                ]}),
+            )
+            .tool_override(
+                "search_code",
+                ToolOverride {
+                    name: Some("github_search".to_string()),
+                    aliases: vec!["code_search".to_string()],
+                    append_description: Some("Prefer this over cloning.".to_string()),
+                    parameter_descriptions: [("q".to_string(), "Search syntax".to_string())].into(),
+                    ..Default::default()
+                },
+            )
+            .tool_override(
+                "get_me",
+                ToolOverride {
+                    description: Some("The signed-in user".to_string()),
+                    ..Default::default()
+                },
+            )
+            .log_to(&log)
+            .config();
+        let mut client = ModularMcpClient::new();
+        client.connect("github".to_string(), config).await.unwrap();
+
+        let tools = client.list_tools("github").unwrap();
+        assert_eq!(tools[0].name, "github_search");
+        assert_eq!(
+            tools[0].description.as_deref(),
+            Some("Search code\n\nPrefer this over cloning.")
+        );
+        assert_eq!(
+            tools[0].input_schema["properties"]["q"]["description"],
+            "Search syntax"
+        );
+        assert_eq!(tools[1].name, "get_me");
+        assert_eq!(tools[1].description.as_deref(), Some("The signed-in user"));
+
+        for (id, name) in ["github_search", "code_search"].iter().enumerate() {
+            client
+                .call_tool("github", name, json!({}), id.to_string(), None)
+                .await
+                .unwrap();
+        }
+        assert!(client
+            .call_tool("github", "search_code", json!({}), "2".to_string(), None)
+            .await
+            .is_err());
+
+        let called: Vec<serde_json::Value> = std::fs::read_to_string(&log)
+            .unwrap()
+            .lines()
+            .filter_map(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+            .filter(|message| message["method"] == "tools/call")
+            .map(|message| message["params"]["name"].clone())
+            .collect();
+        assert_eq!(called, [json!("search_code"), json!("search_code")]);
+
+        client.disconnect_all().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_find_resource_group_indexes_every_page_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("requests.log");
+        let mut client = ModularMcpClient::new();
+        let config = FakeServer::new()
+            .respond_to_cursor(
+                "resources/list",
+                "page-2",
+                json!({"resources": [{"uri": "file:///second.txt", "name": "second"}]}),
+            )
+            .respond(
+                "resources/list",
+                json!({
+                    "resources": [{"uri": "file:///first.txt", "name": "first"}],
+                    "nextCursor": "page-2"
+                }),
+            )
+            .log_to(&log)
+            .config();
+        client.connect("files".to_string(), config).await.unwrap();
+
+        assert_eq!(
+            client.find_resource_group("file:///second.txt").await,
+            Some("files".to_string())
+        );
+        assert_eq!(
+            client.find_resource_group("file:///first.txt").await,
+            Some("files".to_string())
+        );
+        assert_eq!(count_logged(&log, "resources/list"), 2);
+
+        client.disconnect_all().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_find_prompt_and_template_groups_read_later_pages() {
+        let mut client = ModularMcpClient::new();
+        let config = FakeServer::new()
+            .respond_to_cursor(
+                "prompts/list",
+                "page-2",
+                json!({"prompts": [{"name": "review"}]}),
+            )
+            .respond(
+                "prompts/list",
+                json!({"prompts": [{"name": "summarize"}], "nextCursor": "page-2"}),
+            )
+            .respond_to_cursor(
+                "resources/templates/list",
+                "page-2",
+                json!({"resourceTemplates": [{"uriTemplate": "file:///{path}"}]}),
+            )
+            .respond(
+                "resources/templates/list",
+                json!({"resourceTemplates": [], "nextCursor": "page-2"}),
+            )
+            .config();
+        client.connect("paged".to_string(), config).await.unwrap();
+
+        assert_eq!(
+            client.find_prompt_group("review").await,
+            Some("paged".to_string())
+        );
+        assert_eq!(
+            client.find_resource_template_group("file:///{path}").await,
+            Some("paged".to_string())
+        );
+        assert_eq!(client.find_prompt_group("missing").await, None);
+
+        client.disconnect_all().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_connect_lists_every_page_of_tools() {
+        let mut client = ModularMcpClient::new();
+        let config = FakeServer::new()
+            .respond_to_cursor(
+                "tools/list",
+                "page-2",
+                json!({"tools": [{"name": "second", "inputSchema": {"type": "object"}}]}),
+            )
+            .respond(
+                "tools/list",
+                json!({
+                    "tools": [{"name": "first", "inputSchema": {"type": "object"}}],
+                    "nextCursor": "page-2"
+                }),
+            )
+            .config();
+        client.connect("paged".to_string(), config).await.unwrap();
+
+        let names: Vec<_> = client
+            .list_tools("paged")
+            .unwrap()
+            .into_iter()
+            .map(|tool| tool.name)
+            .collect();
+        assert_eq!(names, ["first", "second"]);
+
+        client.disconnect_all().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_connect_drops_overrides_hiding_other_tools() {
+        let mut client = ModularMcpClient::new();
+        let config = FakeServer::new()
+            .with_tools(&["search_code", "search", "get_me", "list"])
+            .tool_override(
+                "search_code",
+                ToolOverride {
+                    aliases: vec!["search".to_string(), "code_search".to_string()],
+                    ..Default::default()
+                },
+            )
+            .tool_override(
+                "get_me",
+                ToolOverride {
+                    name: Some("list".to_string()),
+                    ..Default::default()
+                },
+            )
+            .config();
+        client.connect("github".to_string(), config).await.unwrap();
+
+        let mut names: Vec<String> = client
+            .list_tools("github")
+            .unwrap()
+            .into_iter()
+            .map(|tool| tool.name)
+            .collect();
+        names.sort();
+        assert_eq!(names, ["get_me", "list", "search", "search_code"]);
+
+        let config = client.group_config("github").unwrap();
+        assert_eq!(config.upstream_tool_name("search"), Some("search"));
+        assert_eq!(
+            config.upstream_tool_name("code_search"),
+            Some("search_code")
+        );
+        assert_eq!(config.upstream_tool_name("get_me"), Some("get_me"));
+        assert_eq!(config.upstream_tool_name("list"), Some("list"));
+
+        client.disconnect_all().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_refresh_drops_aliases_hiding_new_tools() {
+        let dir = tempfile::tempdir().unwrap();
+        let tools = dir.path().join("tools.json");
+        std::fs::write(&tools, r#"{"tools": [{"name": "search_code"}]}"#).unwrap();
+        let mut client = ModularMcpClient::new();
+        let config = FakeServer::new()
+            .respond_from_file("tools/list", &tools)
+            .tool_override(
+                "search_code",
+                ToolOverride {
+                    aliases: vec!["search".to_string()],
+                    ..Default::default()
+                },
+            )
+            .config();
+        client.connect("github".to_string(), config).await.unwrap();
+
+        std::fs::write(
+            &tools,
+            r#"{"tools": [{"name": "search_code"}, {"name": "search"}]}"#,
+        )
+        .unwrap();
+        let fetched = client.fetch_tools("github").await.unwrap();
+        client.set_tools("github", fetched);
+
+        assert_eq!(client.list_tools("github").unwrap().len(), 2);
+        let config = client.group_config("github").unwrap();
+        assert_eq!(config.upstream_tool_name("search"), Some("search"));
+
+        client.disconnect_all().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_fetch_and_set_tools_refresh_a_group() {
+        let dir = tempfile::tempdir().unwrap();
+        let tools = dir.path().join("tools.json");
+        std::fs::write(&tools, r#"{"tools": [{"name": "old"}]}"#).unwrap();
+        let mut client = ModularMcpClient::new();
+        let config = FakeServer::new()
+            .respond_from_file("tools/list", &tools)
+            .config();
+        client
+            .connect("changing".to_string(), config)
+            .await
+            .unwrap();
+        let changes = client.subscribe_group_changes();
+
+        std::fs::write(&tools, r#"{"tools": [{"name": "old"}, {"name": "new"}]}"#).unwrap();
+        let fetched = client.fetch_tools("changing").await.unwrap();
+        client.set_tools("changing", fetched);
+
+        assert_eq!(client.list_tools("changing").unwrap().len(), 2);
+        assert!(changes.has_changed().unwrap());
+        assert_eq!(client.search_tools("new", 10)[0].tool.name, "new");
+        assert!(client.fetch_tools("missing").await.is_err());
+
+        client.disconnect_all().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_unroutable_uri_not_relisted_until_invalidated() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("requests.log");
+        let mut client = ModularMcpClient::new();
+        let config = FakeServer::new()
+            .respond(
+                "resources/list",
+                json!({"resources": [{"uri": "file:///watched.txt", "name": "watched"}]}),
+            )
+            .log_to(&log)
+            .config();
+        client.connect("files".to_string(), config).await.unwrap();
+
+        for _ in 0..3 {
+            assert_eq!(
+                client.find_resource_group("file:///missing.txt").await,
+                None
+            );
+        }
+        assert_eq!(count_logged(&log, "resources/list"), 2);
+
+        client.resource_routes.lock().unwrap().invalidate("files");
+        assert_eq!(
+            client.find_resource_group("file:///missing.txt").await,
+            None
+        );
+        assert_eq!(count_logged(&log, "resources/list"), 4);
+
+        client.disconnect_all().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_find_resource_group_matches_uri_templates() {
+        let mut client = ModularMcpClient::new();
+        let logs = FakeServer::new()
+            .respond(
+                "resources/templates/list",
+                json!({"resourceTemplates": [{"uriTemplate": "logs://{service}/{date}", "name": "logs"}]}),
+            )
+            .config();
+        let repos = FakeServer::new()
+            .respond(
+                "resources/templates/list",
+                json!({"resourceTemplates": [{"uriTemplate": "repo://{owner}/{repo}{/path}", "name": "files"}]}),
+            )
+            .config();
+        client.connect("logs".to_string(), logs).await.unwrap();
+        client.connect("repos".to_string(), repos).await.unwrap();
+
+        assert_eq!(
+            client
+                .find_resource_group("repo://me/app/src/main.rs")
+                .await,
+            Some("repos".to_string())
+        );
+        assert_eq!(
+            client.find_resource_group("logs://api/2026-01-01").await,
+            Some("logs".to_string())
+        );
+        assert_eq!(client.find_resource_group("logs://api").await, None);
+
+        client.disconnect_all().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_resource_routes_invalidated_by_list_changed() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("requests.log");
+        let mut client = ModularMcpClient::new();
+        let config = FakeServer::new()
+            .respond(
+                "resources/list",
+                json!({"resources": [{"uri": "file:///watched.txt", "name": "watched"}]}),
+            )
+            .respond_and_notify(
+                "ping",
+                json!({}),
+                json!({"jsonrpc": "2.0", "method": "notifications/resources/list_changed"}),
+            )
+            .log_to(&log)
+            .config();
+        client.connect("files".to_string(), config).await.unwrap();
+
+        client.find_resource_group("file:///watched.txt").await;
+        client.find_resource_group("file:///watched.txt").await;
+        assert_eq!(count_logged(&log, "resources/list"), 1);
+
+        if let Some(GroupState::Connected { transport, .. }) = client.groups.get("files") {
+            transport
+                .send_request(&JsonRpcRequest::new(99, "ping"))
+                .await
+                .unwrap();
+        }
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while client
+                .resource_routes
+                .lock()
+                .unwrap()
+                .groups
+                .contains_key("files")
+            {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(
+            client.find_resource_group("file:///watched.txt").await,
+            Some("files".to_string())
+        );
+        assert_eq!(count_logged(&log, "resources/list"), 2);
+
+        client.disconnect_all().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_upstream_notifications_are_broadcast_with_group() {
+        let mut client = ModularMcpClient::new();
+        let mut notifications = client.subscribe_upstream_notifications();
+        let config = FakeServer::new()
+            .respond_and_notify(
+                "ping",
+                json!({}),
+                json!({"jsonrpc": "2.0", "method": "notifications/message", "params": {}}),
+            )
+            .config();
+        client.connect("fake".to_string(), config).await.unwrap();
+
+        if let Some(GroupState::Connected { transport, .. }) = client.groups.get("fake") {
+            transport
+                .send_request(&JsonRpcRequest::new(99, "ping"))
+                .await
+                .unwrap();
+        }
+
+        let received = tokio::time::timeout(Duration::from_secs(5), notifications.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(received.group, "fake");
+        assert_eq!(received.notification.method, "notifications/message");
+
+        client.disconnect_all().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_progress_resets_tool_timeout_when_enabled() {
+        let progress_config = |reset_on_progress| {
+            FakeServer::new()
+                .report_progress("tools/call", 3, Duration::from_millis(400))
+                .timeout(Timeout {
+                    tools: Some(Duration::from_secs(1)),
+                    reset_on_progress,
+                    ..Default::default()
+                })
+                .config()
+        };
+
+        let mut client = ModularMcpClient::new();
+        client
+            .connect("resetting".to_string(), progress_config(true))
+            .await
+            .unwrap();
+        client
+            .connect("strict".to_string(), progress_config(false))
+            .await
+            .unwrap();
+
+        let call = |group: &'static str| {
+            client.call_tool(
+                group,
+                "work",
+                json!({}),
+                uuid::Uuid::new_v4().to_string(),
+                Some(json!(group)),
+            )
+        };
+        assert!(call("resetting").await.is_ok());
+        let error = call("strict").await.unwrap_err();
+        assert!(error.to_string().contains("timed out"), "{:#}", error);
+
+        client.disconnect_all().await.unwrap();
+    }
+
+    fn sampling_request() -> serde_json::Value {
+        json!({
+            "jsonrpc": "2.0",
+            "id": "srv-1",
+            "method": "sampling/createMessage",
+            "params": {"messages": [], "maxTokens": 10}
+        })
+    }
+
+    async fn send_ping(client: &ModularMcpClient, group: &str) {
+        if let Some(GroupState::Connected { transport, .. }) = client.groups.get(group) {
+            transport
+                .send_request(&JsonRpcRequest::new(99, "ping"))
+                .await
+                .unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_upstream_request_routed_to_calling_session() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("messages.log");
+        let mut client = ModularMcpClient::new();
+        let (caller, mut caller_requests) =
+            client.register_downstream_peer(&json!({"sampling": {}}));
+        let (_, mut bystander_requests) = client.register_downstream_peer(&json!({"sampling": {}}));
+
+        let config = FakeServer::new()
+            .request_then_respond("tools/call", sampling_request(), json!({}))
+            .log_to(&log)
+            .config();
+        client.connect("fake".to_string(), config).await.unwrap();
+
+        let initialize = wait_for_logged(&log, |m| m["method"] == "initialize")
+            .await
+            .unwrap();
+        assert_eq!(
+            initialize["params"]["capabilities"],
+            json!({"sampling": {}})
+        );
+
+        {
+            let _tag = client.tag_upstream_call(caller, "fake", "call-1");
+            client
+                .call_tool("fake", "work", json!({}), "call-1".to_string(), None)
+                .await
+                .unwrap();
+        }
+        let upstream = tokio::time::timeout(Duration::from_secs(5), caller_requests.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(upstream.group, "fake");
+        assert_eq!(upstream.request.method, "sampling/createMessage");
+        assert!(bystander_requests.try_recv().is_err());
+
+        upstream
+            .responder
+            .send(JsonRpcResponse {
+                jsonrpc: "2.0".to_string(),
+                id: upstream.request.id,
+                result: Some(json!({"role": "assistant"})),
+                error: None,
+            })
+            .unwrap();
+        let answer = wait_for_logged(&log, |m| m["id"] == "srv-1").await.unwrap();
+        assert_eq!(answer["result"]["role"], "assistant");
+
+        client.disconnect_all().await.unwrap();
+    }
+
+    #[test]
+    fn test_relayed_capabilities_follow_registered_peers() {
+        let client = ModularMcpClient::new();
+        let capabilities = client.subscribe_relayed_capabilities();
+        assert_eq!(*capabilities.borrow(), json!({}));
+
+        let (sampler, _sampler_requests) = client.register_downstream_peer(&json!({
+            "sampling": {},
+            "experimental": {"custom": {}}
+        }));
+        let (rooted, _rooted_requests) =
+            client.register_downstream_peer(&json!({"roots": {"listChanged": true}}));
+        assert_eq!(
+            *capabilities.borrow(),
+            json!({"sampling": {}, "roots": {"listChanged": true}})
+        );
+
+        client.unregister_downstream_peer(sampler);
+        assert_eq!(
+            *capabilities.borrow(),
+            json!({"roots": {"listChanged": true}})
+        );
+        client.unregister_downstream_peer(rooted);
+        assert_eq!(*capabilities.borrow(), json!({}));
+    }
+
+    #[tokio::test]
+    async fn test_upstream_request_without_capable_peer_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("messages.log");
+        let mut client = ModularMcpClient::new();
+        let (peer, _requests) = client.register_downstream_peer(&json!({"sampling": {}}));
+        client.unregister_downstream_peer(peer);
+
+        let config = FakeServer::new()
+            .respond_and_notify("ping", json!({}), sampling_request())
+            .log_to(&log)
+            .config();
+        client.connect("fake".to_string(), config).await.unwrap();
+        send_ping(&client, "fake").await;
+
+        let initialize = wait_for_logged(&log, |m| m["method"] == "initialize")
+            .await
+            .unwrap();
+        assert_eq!(initialize["params"]["capabilities"], json!({}));
+        let answer = wait_for_logged(&log, |m| m["id"] == "srv-1").await.unwrap();
+        assert_eq!(answer["error"]["code"], -32601);
+
+        client.disconnect_all().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_upstream_request_of_unknown_origin_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("messages.log");
+        let mut client = ModularMcpClient::new();
+        let (_, mut first) = client.register_downstream_peer(&json!({"sampling": {}}));
+        let (_, mut second) = client.register_downstream_peer(&json!({"sampling": {}}));
+
+        let config = FakeServer::new()
+            .respond_and_notify("ping", json!({}), sampling_request())
+            .log_to(&log)
+            .config();
+        client.connect("fake".to_string(), config).await.unwrap();
+        send_ping(&client, "fake").await;
+
+        let answer = wait_for_logged(&log, |m| m["id"] == "srv-1").await.unwrap();
+        assert_eq!(answer["error"]["code"], -32603);
+        assert!(first.try_recv().is_err());
+        assert!(second.try_recv().is_err());
+
+        client.disconnect_all().await.unwrap();
+    }
+}
@@ -0,0 +1,407 @@
+//! Shrinking tool schemas before they are shown to the LLM.
+//!
+//! Upstream schemas are generated for validators, not for language models: they carry
+//! titles, examples, `$defs` indirections and `additionalProperties: false` that cost
+//! tokens without helping the model fill in arguments. The cached schemas are never
+//! changed; only what `get_dynamic_tools` returns is minified.
+
+use crate::config::schema::SchemaCompaction;
+use serde_json::{Map, Value};
+
+/// Descriptions longer than this are cut at [`SchemaCompaction::Aggressive`], in characters
+pub const MAX_DESCRIPTION_LENGTH: usize = 160;
+
+/// Enums with more values than this are cut at [`SchemaCompaction::Aggressive`]
+const MAX_ENUM_VALUES: usize = 16;
+
+/// Keywords that only document a schema
+const ANNOTATION_KEYWORDS: [&str; 5] = ["$schema", "$comment", "title", "examples", "example"];
+
+/// Keywords holding a map of names to subschemas
+const SCHEMA_MAP_KEYWORDS: [&str; 5] = [
+    "properties",
+    "patternProperties",
+    "$defs",
+    "definitions",
+    "dependentSchemas",
+];
+
+/// Keywords holding one subschema
+const SCHEMA_KEYWORDS: [&str; 9] = [
+    "items",
+    "additionalProperties",
+    "additionalItems",
+    "contains",
+    "not",
+    "if",
+    "then",
+    "else",
+    "propertyNames",
+];
+
+/// Keywords holding a list of subschemas
+const SCHEMA_LIST_KEYWORDS: [&str; 4] = ["allOf", "anyOf", "oneOf", "prefixItems"];
+
+/// A copy of `schema` minified for `level`. `$schema` is dropped at every level.
+pub fn minify_schema(schema: &Value, level: SchemaCompaction) -> Value {
+    let mut schema = schema.clone();
+    if let Some(obj) = schema.as_object_mut() {
+        obj.remove("$schema");
+    }
+    if level == SchemaCompaction::Off {
+        return schema;
+    }
+
+    let originals = root_definitions(&schema);
+    let definitions = take_definitions(&mut schema);
+    let mut unresolved = false;
+    let mut schema = inline_references(schema, &definitions, &mut Vec::new(), &mut unresolved);
+    if unresolved {
+        // Recursive, nested or dangling references cannot be inlined; keep the definitions
+        // as sent so that they still resolve
+        if let Some(obj) = schema.as_object_mut() {
+            obj.extend(originals);
+        }
+    }
+
+    strip(&mut schema, level);
+    schema
+}
+
+/// Cut `description` to [`MAX_DESCRIPTION_LENGTH`] characters at [`SchemaCompaction::Aggressive`]
+pub fn minify_description(description: &str, level: SchemaCompaction) -> String {
+    if level != SchemaCompaction::Aggressive {
+        return description.to_string();
+    }
+    truncate(description.trim())
+}
+
+fn truncate(text: &str) -> String {
+    match text.char_indices().nth(MAX_DESCRIPTION_LENGTH) {
+        Some((end, _)) => format!("{}…", text[..end].trim_end()),
+        None => text.to_string(),
+    }
+}
+
+/// The root's `$defs` and `definitions`, keyed by keyword
+fn root_definitions(schema: &Value) -> Map<String, Value> {
+    let Some(obj) = schema.as_object() else {
+        return Map::new();
+    };
+    ["$defs", "definitions"]
+        .into_iter()
+        .filter_map(|keyword| Some((keyword.to_string(), obj.get(keyword)?.clone())))
+        .collect()
+}
+
+/// Remove the root's `$defs` and `definitions`, keyed by their `$ref` paths
+fn take_definitions(schema: &mut Value) -> Map<String, Value> {
+    let mut definitions = Map::new();
+    let Some(obj) = schema.as_object_mut() else {
+        return definitions;
+    };
+    for keyword in ["$defs", "definitions"] {
+        if let Some(Value::Object(defs)) = obj.remove(keyword) {
+            for (name, definition) in defs {
+                definitions.insert(format!("#/{}/{}", keyword, name), definition);
+            }
+        }
+    }
+    definitions
+}
+
+/// Replace local `$ref`s with the definitions they point to. References that would recurse
+/// or that do not name a definition are left in place and flagged in `unresolved`.
+fn inline_references(
+    schema: Value,
+    definitions: &Map<String, Value>,
+    expanding: &mut Vec<String>,
+    unresolved: &mut bool,
+) -> Value {
+    match schema {
+        Value::Object(mut obj) => {
+            if let Some(Value::String(reference)) = obj.get("$ref") {
+                let reference = reference.clone();
+                match definitions.get(&reference) {
+                    Some(definition) if !expanding.contains(&reference) => {
+                        expanding.push(reference);
+                        let inlined = inline_references(
+                            definition.clone(),
+                            definitions,
+                            expanding,
+                            unresolved,
+                        );
+                        expanding.pop();
+
+                        obj.remove("$ref");
+                        // Keywords next to `$ref` (such as a description) override the definition
+                        return match inlined {
+                            Value::Object(mut merged) => {
+                                for (key, value) in obj {
+                                    let value = inline_references(
+                                        value,
+                                        definitions,
+                                        expanding,
+                                        unresolved,
+                                    );
+                                    merged.insert(key, value);
+                                }
+                                Value::Object(merged)
+                            }
+                            other => other,
+                        };
+                    }
+                    _ => *unresolved = true,
+                }
+            }
+
+            Value::Object(
+                obj.into_iter()
+                    .map(|(key, value)| {
+                        (
+                            key,
+                            inline_references(value, definitions, expanding, unresolved),
+                        )
+                    })
+                    .collect(),
+            )
+        }
+        Value::Array(items) => Value::Array(
+            items
+                .into_iter()
+                .map(|item| inline_references(item, definitions, expanding, unresolved))
+                .collect(),
+        ),
+        other => other,
+    }
+}
+
+/// Drop annotations and `additionalProperties: false` from a schema and its subschemas
+fn strip(schema: &mut Value, level: SchemaCompaction) {
+    let Some(obj) = schema.as_object_mut() else {
+        return;
+    };
+
+    for keyword in ANNOTATION_KEYWORDS {
+        obj.remove(keyword);
+    }
+    if obj.get("additionalProperties") == Some(&Value::Bool(false)) {
+        obj.remove("additionalProperties");
+    }
+    if obj
+        .get("description")
+        .and_then(|d| d.as_str())
+        .is_some_and(|d| d.trim().is_empty())
+    {
+        obj.remove("description");
+    }
+
+    if level == SchemaCompaction::Aggressive {
+        if let Some(Value::String(description)) = obj.get_mut("description") {
+            *description = truncate(description.trim());
+        }
+        collapse_enum(obj);
+    }
+
+    for keyword in SCHEMA_MAP_KEYWORDS {
+        if let Some(Value::Object(subschemas)) = obj.get_mut(keyword) {
+            for subschema in subschemas.values_mut() {
+                strip(subschema, level);
+            }
+        }
+    }
+    for keyword in SCHEMA_KEYWORDS {
+        if let Some(subschema) = obj.get_mut(keyword) {
+            match subschema {
+                // Draft 4-7 tuple validation puts a list of schemas under `items`
+                Value::Array(subschemas) => subschemas.iter_mut().for_each(|s| strip(s, level)),
+                subschema => strip(subschema, level),
+            }
+        }
+    }
+    for keyword in SCHEMA_LIST_KEYWORDS {
+        if let Some(Value::Array(subschemas)) = obj.get_mut(keyword) {
+            for subschema in subschemas {
+                strip(subschema, level);
+            }
+        }
+    }
+}
+
+/// Keep the first [`MAX_ENUM_VALUES`] values of a long enum in `enum` and list the rest
+/// in the description, so that every value can still be chosen
+fn collapse_enum(obj: &mut Map<String, Value>) {
+    let Some(Value::Array(values)) = obj.get_mut("enum") else {
+        return;
+    };
+    if values.len() <= MAX_ENUM_VALUES {
+        return;
+    }
+
+    let omitted: Vec<String> = values
+        .split_off(MAX_ENUM_VALUES)
+        .iter()
+        .map(|value| match value {
+            Value::String(value) => value.clone(),
+            other => other.to_string(),
+        })
+        .collect();
+    let note = format!("also accepts: {}", omitted.join(", "));
+    let description = match obj.get("description").and_then(|d| d.as_str()) {
+        Some(description) => format!("{} ({})", description, note),
+        None => note,
+    };
+    obj.insert("description".to_string(), Value::String(description));
+}
+
+/// Rough number of tokens `text` costs, at about four characters per token
+pub fn estimate_tokens(text: &str) -> usize {
+    text.len().div_ceil(4)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_off_only_drops_schema_keyword() {
+        let schema = json!({
+            "$schema": "http://json-schema.org/draft-07/schema#",
+            "type": "object",
+            "title": "Args",
+            "additionalProperties": false
+        });
+        assert_eq!(
+            minify_schema(&schema, SchemaCompaction::Off),
+            json!({"type": "object", "title": "Args", "additionalProperties": false})
+        );
+    }
+
+    #[test]
+    fn test_basic_drops_annotations_but_not_properties_named_like_them() {
+        let schema = json!({
+            "type": "object",
+            "title": "Args",
+            "additionalProperties": false,
+            "properties": {
+                "title": {"type": "string", "title": "Title", "examples": ["Hello"]},
+                "tags": {"type": "array", "items": {"type": "string", "$comment": "free text"}},
+                "extra": {"type": "object", "additionalProperties": true, "description": " "}
+            }
+        });
+        assert_eq!(
+            minify_schema(&schema, SchemaCompaction::Basic),
+            json!({
+                "type": "object",
+                "properties": {
+                    "title": {"type": "string"},
+                    "tags": {"type": "array", "items": {"type": "string"}},
+                    "extra": {"type": "object", "additionalProperties": true}
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_basic_inlines_definitions() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "point": {"$ref": "#/$defs/Point", "description": "Where to click"},
+                "size": {"$ref": "#/definitions/Size"}
+            },
+            "$defs": {"Point": {"type": "object", "title": "Point", "properties": {"x": {"type": "number"}}}},
+            "definitions": {"Size": {"type": "integer"}}
+        });
+        assert_eq!(
+            minify_schema(&schema, SchemaCompaction::Basic),
+            json!({
+                "type": "object",
+                "properties": {
+                    "point": {"type": "object", "description": "Where to click", "properties": {"x": {"type": "number"}}},
+                    "size": {"type": "integer"}
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_recursive_definitions_are_kept() {
+        let schema = json!({
+            "type": "object",
+            "properties": {"tree": {"$ref": "#/$defs/Node"}},
+            "$defs": {"Node": {"type": "object", "properties": {"children": {"type": "array", "items": {"$ref": "#/$defs/Node"}}}}}
+        });
+        let minified = minify_schema(&schema, SchemaCompaction::Basic);
+        assert_eq!(
+            minified["properties"]["tree"]["properties"]["children"]["items"],
+            json!({"$ref": "#/$defs/Node"})
+        );
+        assert!(minified["$defs"]["Node"].is_object());
+    }
+
+    #[test]
+    fn test_definitions_are_kept_for_references_that_stay() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "point": {"$ref": "#/$defs/Point"},
+                "x": {"$ref": "#/$defs/Point/properties/x"},
+                "size": {"$ref": "#/definitions/Size"}
+            },
+            "$defs": {"Point": {"type": "object", "properties": {"x": {"type": "number"}}}},
+            "definitions": {"Size": {"type": "integer"}}
+        });
+        let minified = minify_schema(&schema, SchemaCompaction::Basic);
+        assert_eq!(minified["properties"]["point"]["type"], "object");
+        assert_eq!(
+            minified["properties"]["x"],
+            json!({"$ref": "#/$defs/Point/properties/x"})
+        );
+        assert_eq!(minified["$defs"], schema["$defs"]);
+        assert_eq!(minified["definitions"], schema["definitions"]);
+
+        let dangling = json!({
+            "type": "object",
+            "properties": {"size": {"$ref": "#/definitions/Missing"}},
+            "definitions": {"Size": {"type": "integer"}}
+        });
+        let minified = minify_schema(&dangling, SchemaCompaction::Basic);
+        assert_eq!(minified["definitions"], dangling["definitions"]);
+    }
+
+    #[test]
+    fn test_aggressive_truncates_descriptions_and_collapses_enums() {
+        let values: Vec<String> = (0..20).map(|i| format!("v{}", i)).collect();
+        let schema = json!({
+            "type": "object",
+            "description": "x".repeat(400),
+            "properties": {"choice": {"type": "string", "enum": values}}
+        });
+        let minified = minify_schema(&schema, SchemaCompaction::Aggressive);
+        assert_eq!(
+            minified["description"].as_str().unwrap().chars().count(),
+            MAX_DESCRIPTION_LENGTH + 1
+        );
+        let choice = &minified["properties"]["choice"];
+        assert_eq!(choice["enum"].as_array().unwrap().len(), MAX_ENUM_VALUES);
+        assert_eq!(choice["description"], "also accepts: v16, v17, v18, v19");
+
+        assert_eq!(
+            minify_description("short", SchemaCompaction::Aggressive),
+            "short"
+        );
+        assert_eq!(
+            minify_description(&"y".repeat(400), SchemaCompaction::Basic).len(),
+            400
+        );
+    }
+
+    #[test]
+    fn test_estimate_tokens() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("abcd"), 1);
+        assert_eq!(estimate_tokens("abcde"), 2);
+    }
+}
@@ -11,7 +11,14 @@
 //! - Supports stdio, HTTP, and SSE transports
 
 pub mod client;
+pub mod minify;
+pub mod repair;
+pub mod search;
+#[cfg(test)]
+pub mod testing;
 pub mod transport;
 pub mod types;
+pub mod uri_template;
+pub mod validation;
 
 pub use client::ModularMcpClient;
@@ -0,0 +1,209 @@
+//! Repairing malformed `call_dynamic_tool` arguments.
+//!
+//! LLMs regularly send `args` as a JSON-encoded string, put tool arguments next to `group`
+//! and `name`, or quote numbers and booleans. When repair is enabled, such calls are fixed up
+//! against the tool's input schema instead of being forwarded as they are. Only inline
+//! subschemas under `properties` and `items` are followed.
+
+use serde_json::{Map, Value};
+
+/// Parameters of `call_dynamic_tool` itself; any other top-level key belongs in `args`
+const CALL_PARAMETERS: [&str; 3] = ["group", "name", "args"];
+
+/// The tool arguments of a `call_dynamic_tool` call, repaired to fit `schema` where needed,
+/// along with a description of every repair made
+pub fn repair_arguments(call: &Value, schema: Option<&Value>) -> (Value, Vec<String>) {
+    let mut repairs = Vec::new();
+    let mut args = match call.get("args") {
+        None | Some(Value::Null) => Value::Object(Map::new()),
+        Some(args) => args.clone(),
+    };
+
+    if let Value::String(text) = &args {
+        if let Ok(parsed @ Value::Object(_)) = serde_json::from_str(text) {
+            args = parsed;
+            repairs.push("args: parsed from a JSON string".to_string());
+        }
+    }
+
+    if let (Some(call), Value::Object(args)) = (call.as_object(), &mut args) {
+        for (key, value) in call {
+            if CALL_PARAMETERS.contains(&key.as_str()) || args.contains_key(key) {
+                continue;
+            }
+            args.insert(key.clone(), value.clone());
+            repairs.push(format!(
+                "args/{}: moved in from next to group and name",
+                key
+            ));
+        }
+    }
+
+    if let Some(schema) = schema {
+        coerce(&mut args, schema, "args", &mut repairs);
+    }
+    (args, repairs)
+}
+
+/// Convert `value` and its items and properties to the types `schema` asks for, where
+/// they hold the right value as the wrong type
+fn coerce(value: &mut Value, schema: &Value, path: &str, repairs: &mut Vec<String>) {
+    let types = schema_types(schema);
+    if !types.is_empty() && !types.iter().any(|ty| has_type(value, ty)) {
+        if let Some((converted, ty)) = types
+            .iter()
+            .find_map(|ty| convert(value, ty).map(|converted| (converted, ty)))
+        {
+            repairs.push(format!("{}: converted {} to {}", path, value, ty));
+            *value = converted;
+        }
+    }
+
+    match value {
+        Value::Object(obj) => {
+            if let Some(properties) = schema.get("properties").and_then(|p| p.as_object()) {
+                for (key, property) in properties {
+                    if let Some(value) = obj.get_mut(key) {
+                        coerce(value, property, &format!("{}/{}", path, key), repairs);
+                    }
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(item_schema) = schema.get("items").filter(|i| i.is_object()) {
+                for (index, item) in items.iter_mut().enumerate() {
+                    coerce(item, item_schema, &format!("{}/{}", path, index), repairs);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The JSON types a schema's `type` keyword allows, empty when it does not say
+fn schema_types(schema: &Value) -> Vec<&str> {
+    match schema.get("type") {
+        Some(Value::String(ty)) => vec![ty.as_str()],
+        Some(Value::Array(types)) => types.iter().filter_map(|ty| ty.as_str()).collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn has_type(value: &Value, ty: &str) -> bool {
+    match ty {
+        "null" => value.is_null(),
+        "boolean" => value.is_boolean(),
+        "integer" => {
+            value.is_i64() || value.is_u64() || value.as_f64().is_some_and(|n| n.fract() == 0.0)
+        }
+        "number" => value.is_number(),
+        "string" => value.is_string(),
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        _ => true,
+    }
+}
+
+/// `value` as a `ty`, if it holds one in another type
+fn convert(value: &Value, ty: &str) -> Option<Value> {
+    match (ty, value) {
+        ("integer", Value::String(text)) => text.trim().parse::<i64>().ok().map(Value::from),
+        ("number", Value::String(text)) => {
+            let text = text.trim();
+            match text.parse::<i64>() {
+                Ok(n) => Some(Value::from(n)),
+                Err(_) => text
+                    .parse::<f64>()
+                    .ok()
+                    .and_then(serde_json::Number::from_f64)
+                    .map(Value::Number),
+            }
+        }
+        ("boolean", Value::String(text)) => match text.trim().to_lowercase().as_str() {
+            "true" => Some(Value::Bool(true)),
+            "false" => Some(Value::Bool(false)),
+            _ => None,
+        },
+        ("string", Value::Number(n)) => Some(Value::String(n.to_string())),
+        ("string", Value::Bool(b)) => Some(Value::String(b.to_string())),
+        ("array" | "object", Value::String(text)) => serde_json::from_str(text)
+            .ok()
+            .filter(|parsed| has_type(parsed, ty)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "path": {"type": "string"},
+                "limit": {"type": "integer"},
+                "ratio": {"type": ["number", "null"]},
+                "recursive": {"type": "boolean"},
+                "tags": {"type": "array", "items": {"type": "string"}}
+            }
+        })
+    }
+
+    #[test]
+    fn test_well_formed_arguments_are_left_alone() {
+        let call = json!({"group": "fs", "name": "list", "args": {"path": "/tmp", "limit": 5}});
+        let (args, repairs) = repair_arguments(&call, Some(&schema()));
+        assert_eq!(args, json!({"path": "/tmp", "limit": 5}));
+        assert!(repairs.is_empty());
+
+        let (args, repairs) = repair_arguments(&json!({"group": "fs", "name": "list"}), None);
+        assert_eq!(args, json!({}));
+        assert!(repairs.is_empty());
+    }
+
+    #[test]
+    fn test_stringified_args_are_parsed() {
+        let call = json!({"group": "fs", "name": "list", "args": "{\"path\": \"/tmp\"}"});
+        let (args, repairs) = repair_arguments(&call, None);
+        assert_eq!(args, json!({"path": "/tmp"}));
+        assert_eq!(repairs, ["args: parsed from a JSON string"]);
+
+        // Strings that are not a JSON object are left for validation to report
+        let call = json!({"group": "fs", "name": "list", "args": "/tmp"});
+        assert_eq!(repair_arguments(&call, None).0, json!("/tmp"));
+    }
+
+    #[test]
+    fn test_stray_top_level_keys_are_hoisted() {
+        let call = json!({"group": "fs", "name": "list", "path": "/tmp", "args": {"limit": 5}});
+        let (args, repairs) = repair_arguments(&call, None);
+        assert_eq!(args, json!({"path": "/tmp", "limit": 5}));
+        assert_eq!(repairs, ["args/path: moved in from next to group and name"]);
+    }
+
+    #[test]
+    fn test_scalars_are_coerced_to_the_schema() {
+        let call = json!({"group": "fs", "name": "list", "args": {
+            "path": 42,
+            "limit": "10",
+            "ratio": "0.5",
+            "recursive": "True",
+            "tags": "[\"a\", 1]"
+        }});
+        let (args, repairs) = repair_arguments(&call, Some(&schema()));
+        assert_eq!(
+            args,
+            json!({"path": "42", "limit": 10, "ratio": 0.5, "recursive": true, "tags": ["a", "1"]})
+        );
+        assert_eq!(repairs.len(), 6);
+        assert!(repairs.contains(&"args/limit: converted \"10\" to integer".to_string()));
+        assert!(repairs.contains(&"args/tags/1: converted 1 to string".to_string()));
+
+        let call = json!({"group": "fs", "name": "list", "args": {"limit": "ten"}});
+        let (args, repairs) = repair_arguments(&call, Some(&schema()));
+        assert_eq!(args, json!({"limit": "ten"}));
+        assert!(repairs.is_empty());
+    }
+}
@@ -0,0 +1,319 @@
+//! Offline full-text search over the tools of all connected groups.
+//!
+//! Tools are ranked with BM25 over their name, title, description and argument names.
+//! Query words that do not occur in any tool also match indexed words they are a prefix
+//! of, or are a small typo away from, at a reduced weight.
+
+use crate::proxy::types::ToolInfo;
+use std::collections::HashMap;
+
+/// BM25 term frequency saturation
+const K1: f64 = 1.2;
+/// BM25 document length normalization
+const B: f64 = 0.75;
+
+/// How much a word counts towards a tool depending on where it appears
+const NAME_WEIGHT: f64 = 3.0;
+const TITLE_WEIGHT: f64 = 2.0;
+const GROUP_WEIGHT: f64 = 1.5;
+const TEXT_WEIGHT: f64 = 1.0;
+
+/// How much an inexact query word match counts compared to an exact one
+const PREFIX_MATCH_WEIGHT: f64 = 0.7;
+const TYPO_MATCH_WEIGHT: f64 = 0.5;
+
+/// A tool matching a search query
+#[derive(Debug, Clone)]
+pub struct ToolMatch {
+    pub group: String,
+    pub tool: ToolInfo,
+    pub score: f64,
+}
+
+struct Document {
+    group: String,
+    tool: ToolInfo,
+    term_frequencies: HashMap<String, f64>,
+    length: f64,
+}
+
+/// Search index over the tools of all connected groups
+#[derive(Default)]
+pub struct ToolIndex {
+    documents: Vec<Document>,
+    /// Number of tools each word occurs in
+    document_frequencies: HashMap<String, usize>,
+    average_length: f64,
+}
+
+impl ToolIndex {
+    pub fn build<'a>(tools: impl IntoIterator<Item = (&'a str, &'a ToolInfo)>) -> Self {
+        let mut documents = Vec::new();
+        let mut document_frequencies: HashMap<String, usize> = HashMap::new();
+
+        for (group, tool) in tools {
+            let mut term_frequencies: HashMap<String, f64> = HashMap::new();
+            let mut add = |text: &str, weight: f64| {
+                for term in tokenize(text) {
+                    *term_frequencies.entry(term).or_default() += weight;
+                }
+            };
+
+            add(&tool.name, NAME_WEIGHT);
+            add(tool.title.as_deref().unwrap_or_default(), TITLE_WEIGHT);
+            add(group, GROUP_WEIGHT);
+            add(tool.description.as_deref().unwrap_or_default(), TEXT_WEIGHT);
+            if let Some(properties) = tool
+                .input_schema
+                .get("properties")
+                .and_then(|p| p.as_object())
+            {
+                for argument in properties.keys() {
+                    add(argument, TEXT_WEIGHT);
+                }
+            }
+
+            for term in term_frequencies.keys() {
+                *document_frequencies.entry(term.clone()).or_default() += 1;
+            }
+            documents.push(Document {
+                group: group.to_string(),
+                tool: tool.clone(),
+                length: term_frequencies.values().sum(),
+                term_frequencies,
+            });
+        }
+
+        let average_length = if documents.is_empty() {
+            0.0
+        } else {
+            documents.iter().map(|d| d.length).sum::<f64>() / documents.len() as f64
+        };
+
+        Self {
+            documents,
+            document_frequencies,
+            average_length,
+        }
+    }
+
+    /// The `limit` best matches for `query`, best first
+    pub fn search(&self, query: &str, limit: usize) -> Vec<ToolMatch> {
+        let terms: Vec<(&str, f64)> = tokenize(query)
+            .iter()
+            .flat_map(|word| self.expand(word))
+            .collect();
+
+        let mut matches: Vec<ToolMatch> = self
+            .documents
+            .iter()
+            .map(|document| ToolMatch {
+                group: document.group.clone(),
+                tool: document.tool.clone(),
+                score: terms
+                    .iter()
+                    .map(|(term, weight)| weight * self.term_score(document, term))
+                    .sum(),
+            })
+            .filter(|m| m.score > 0.0)
+            .collect();
+
+        matches.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| a.group.cmp(&b.group))
+                .then_with(|| a.tool.name.cmp(&b.tool.name))
+        });
+        matches.truncate(limit);
+        matches
+    }
+
+    /// Indexed words a query word matches, with how much each match counts. A word that
+    /// occurs in some tool matches only itself.
+    fn expand(&self, word: &str) -> Vec<(&str, f64)> {
+        if let Some((term, _)) = self.document_frequencies.get_key_value(word) {
+            return vec![(term.as_str(), 1.0)];
+        }
+
+        self.document_frequencies
+            .keys()
+            .filter_map(|term| {
+                let weight = if word.len() >= 3 && term.starts_with(word) {
+                    PREFIX_MATCH_WEIGHT
+                } else if word.len() >= 4 && within_typo_distance(word, term) {
+                    TYPO_MATCH_WEIGHT
+                } else {
+                    return None;
+                };
+                Some((term.as_str(), weight))
+            })
+            .collect()
+    }
+
+    fn term_score(&self, document: &Document, term: &str) -> f64 {
+        let Some(&frequency) = document.term_frequencies.get(term) else {
+            return 0.0;
+        };
+        let documents = self.documents.len() as f64;
+        let containing = self.document_frequencies[term] as f64;
+        let idf = (1.0 + (documents - containing + 0.5) / (containing + 0.5)).ln();
+        let length_ratio = document.length / self.average_length;
+
+        idf * frequency * (K1 + 1.0) / (frequency + K1 * (1.0 - B + B * length_ratio))
+    }
+}
+
+/// Lowercase words of `text`, split at punctuation, `snake_case` and `camelCase` boundaries
+fn tokenize(text: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut previous_lowercase = false;
+
+    for c in text.chars() {
+        if !c.is_alphanumeric() {
+            previous_lowercase = false;
+            if !current.is_empty() {
+                words.push(std::mem::take(&mut current));
+            }
+            continue;
+        }
+        if c.is_uppercase() && previous_lowercase && !current.is_empty() {
+            words.push(std::mem::take(&mut current));
+        }
+        previous_lowercase = c.is_lowercase() || c.is_numeric();
+        current.extend(c.to_lowercase());
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+    words
+}
+
+/// Whether `a` and `b` are one edit apart, or two for longer words
+fn within_typo_distance(a: &str, b: &str) -> bool {
+    let allowed = if a.chars().count() >= 8 { 2 } else { 1 };
+    if a.chars().count().abs_diff(b.chars().count()) > allowed {
+        return false;
+    }
+    edit_distance(a, b) <= allowed
+}
+
+/// Levenshtein distance between `a` and `b`, in characters
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn tool(name: &str, description: &str) -> ToolInfo {
+        ToolInfo {
+            name: name.to_string(),
+            title: None,
+            description: Some(description.to_string()),
+            input_schema: json!({"type": "object"}),
+            output_schema: None,
+        }
+    }
+
+    fn index() -> ToolIndex {
+        let browser = [
+            tool("browser_navigate", "Navigate to a URL"),
+            tool(
+                "browser_take_screenshot",
+                "Take a screenshot of the current page",
+            ),
+        ];
+        let github = [
+            tool("create_issue", "Create a new issue in a GitHub repository"),
+            tool("searchCode", "Search code across repositories"),
+        ];
+        ToolIndex::build(
+            browser
+                .iter()
+                .map(|t| ("playwright", t))
+                .chain(github.iter().map(|t| ("github", t))),
+        )
+    }
+
+    fn names(matches: &[ToolMatch]) -> Vec<&str> {
+        matches.iter().map(|m| m.tool.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_tokenize_splits_identifiers() {
+        assert_eq!(
+            tokenize("browser_take-screenshot searchCode HTTPServer v2"),
+            vec![
+                "browser",
+                "take",
+                "screenshot",
+                "search",
+                "code",
+                "httpserver",
+                "v2"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_search_ranks_best_match_first() {
+        let matches = index().search("take a screenshot", 10);
+        assert_eq!(names(&matches)[0], "browser_take_screenshot");
+        assert_eq!(matches[0].group, "playwright");
+
+        let matches = index().search("open an issue on github", 10);
+        assert_eq!(names(&matches)[0], "create_issue");
+    }
+
+    #[test]
+    fn test_search_matches_prefixes_and_typos() {
+        assert_eq!(
+            names(&index().search("screen", 10)),
+            vec!["browser_take_screenshot"]
+        );
+        assert_eq!(
+            names(&index().search("navgate", 10)),
+            vec!["browser_navigate"]
+        );
+    }
+
+    #[test]
+    fn test_search_expands_only_words_no_tool_has() {
+        let tools = [
+            tool("list_files", "List files in a directory"),
+            tool("listen", "Wait for file system events"),
+        ];
+        let index = ToolIndex::build(tools.iter().map(|t| ("files", t)));
+
+        assert_eq!(names(&index.search("list", 10)), vec!["list_files"]);
+        assert!(names(&index.search("liste", 10)).contains(&"listen"));
+    }
+
+    #[test]
+    fn test_search_respects_limit_and_skips_unrelated_tools() {
+        assert_eq!(index().search("repository", 1).len(), 1);
+        assert!(index().search("weather forecast", 10).is_empty());
+        assert!(ToolIndex::default().search("anything", 10).is_empty());
+    }
+
+    #[test]
+    fn test_within_typo_distance() {
+        assert!(within_typo_distance("navgate", "navigate"));
+        assert!(within_typo_distance("screnshots", "screenshot"));
+        assert!(!within_typo_distance("issue", "tissues"));
+        assert!(!within_typo_distance("code", "node_modules"));
+    }
+}
@@ -0,0 +1,360 @@
+//! Fake upstream MCP servers for unit tests.
+
+use crate::config::schema::{
+    Features, NameFilter, SchemaCompaction, Timeout, ToolMode, ToolOverride, ToolSchemas,
+};
+use crate::config::McpServerConfig;
+use crate::proxy::ModularMcpClient;
+use crate::server::ModularMcpServer;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+
+/// Builds a stdio config for a shell script that speaks just enough MCP for tests.
+///
+/// Every request is answered with `{}` unless its method has a canned result;
+/// `initialize` reports protocol version 2025-06-18. Notifications and responses to the
+/// server's own requests are never answered. Results and messages must not contain single quotes.
+#[derive(Default)]
+pub struct FakeServer {
+    /// Shell snippets run for requests matching each `case` pattern, tried in the order
+    /// they were added; they set `result` and `notify`
+    cases: Vec<(String, String)>,
+    log: Option<String>,
+    timeout: Timeout,
+    tool_overrides: HashMap<String, ToolOverride>,
+    resources: NameFilter,
+    resource_templates: NameFilter,
+    prompts: NameFilter,
+    tools: NameFilter,
+    tool_mode: Option<ToolMode>,
+    pinned_tools: Vec<String>,
+    schema_compaction: SchemaCompaction,
+    tool_schemas: ToolSchemas,
+}
+
+impl FakeServer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Answer requests for `method` with `result`
+    pub fn respond(mut self, method: &str, result: serde_json::Value) -> Self {
+        self.cases
+            .push((method_pattern(method), format!("result='{}'", result)));
+        self
+    }
+
+    /// Advertise tools named `names` in `tools/list`, each taking any arguments
+    pub fn with_tools(self, names: &[&str]) -> Self {
+        let tools: Vec<_> = names
+            .iter()
+            .map(|name| serde_json::json!({"name": name, "inputSchema": {"type": "object"}}))
+            .collect();
+        self.respond("tools/list", serde_json::json!({ "tools": tools }))
+    }
+
+    /// Answer requests for `method` with the contents of the file at `path` at the time of
+    /// the request, so a test can change the result while the server runs
+    pub fn respond_from_file(mut self, method: &str, path: &Path) -> Self {
+        self.cases.push((
+            method_pattern(method),
+            format!("result=$(cat '{}')", path.display()),
+        ));
+        self
+    }
+
+    /// Answer requests for `method` carrying `cursor` with `result`; add it before a
+    /// cursor-less [`Self::respond`] for the same method
+    pub fn respond_to_cursor(
+        mut self,
+        method: &str,
+        cursor: &str,
+        result: serde_json::Value,
+    ) -> Self {
+        self.cases.push((
+            format!("{}'\"cursor\":\"{}\"'*", method_pattern(method), cursor),
+            format!("result='{}'", result),
+        ));
+        self
+    }
+
+    /// Answer requests for `method` with `result`, then send `notification` (or any other
+    /// message, such as a request to the client)
+    pub fn respond_and_notify(
+        mut self,
+        method: &str,
+        result: serde_json::Value,
+        notification: serde_json::Value,
+    ) -> Self {
+        self.cases.push((
+            method_pattern(method),
+            format!("result='{}'; notify='{}'", result, notification),
+        ));
+        self
+    }
+
+    /// On requests for `method`, first send `request` to the client (or any other message),
+    /// then answer with `result`
+    pub fn request_then_respond(
+        mut self,
+        method: &str,
+        request: serde_json::Value,
+        result: serde_json::Value,
+    ) -> Self {
+        self.cases.push((
+            method_pattern(method),
+            format!("printf '%s\\n' '{}'; result='{}'", request, result),
+        ));
+        self
+    }
+
+    /// Answer requests for `method` with `result` after `delay`
+    pub fn respond_after(
+        mut self,
+        method: &str,
+        delay: Duration,
+        result: serde_json::Value,
+    ) -> Self {
+        self.cases.push((
+            method_pattern(method),
+            format!("sleep {}; result='{}'", delay.as_secs_f64(), result),
+        ));
+        self
+    }
+
+    /// Never answer requests for `method`, as if they took forever
+    pub fn never_respond(mut self, method: &str) -> Self {
+        self.cases
+            .push((method_pattern(method), "continue".to_string()));
+        self
+    }
+
+    /// Before answering `method` with `{}`, report `steps` progress notifications `interval`
+    /// apart for the request's `_meta.progressToken`, if it has one
+    pub fn report_progress(mut self, method: &str, steps: u32, interval: Duration) -> Self {
+        let body = [
+            r#"token=$(printf '%s\n' "$line" | sed -n 's/.*"progressToken":\([^,}]*\).*/\1/p')"#
+                .to_string(),
+            format!(
+                r#"i=1; while [ -n "$token" ] && [ $i -le {steps} ]; do sleep {secs}; printf '{{"jsonrpc":"2.0","method":"notifications/progress","params":{{"progressToken":%s,"progress":%s,"total":{steps}}}}}\n' "$token" "$i"; i=$((i+1)); done"#,
+                steps = steps,
+                secs = interval.as_secs_f64()
+            ),
+            "result='{}'".to_string(),
+        ]
+        .join("; ");
+        self.cases.push((method_pattern(method), body));
+        self
+    }
+
+    /// Append every message the server receives to `path`, one per line
+    pub fn log_to(mut self, path: &Path) -> Self {
+        self.log = Some(path.display().to_string());
+        self
+    }
+
+    /// Use `timeout` for the server's calls
+    pub fn timeout(mut self, timeout: Timeout) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Rename, alias or redescribe the upstream tool `tool`
+    pub fn tool_override(mut self, tool: &str, tool_override: ToolOverride) -> Self {
+        self.tool_overrides.insert(tool.to_string(), tool_override);
+        self
+    }
+
+    /// Only expose the resources `filter` allows
+    pub fn resource_filter(mut self, filter: NameFilter) -> Self {
+        self.resources = filter;
+        self
+    }
+
+    /// Only expose the resource templates `filter` allows
+    pub fn resource_template_filter(mut self, filter: NameFilter) -> Self {
+        self.resource_templates = filter;
+        self
+    }
+
+    /// Only expose the prompts `filter` allows
+    pub fn prompt_filter(mut self, filter: NameFilter) -> Self {
+        self.prompts = filter;
+        self
+    }
+
+    /// Only expose the tools `filter` allows
+    pub fn tool_filter(mut self, filter: NameFilter) -> Self {
+        self.tools = filter;
+        self
+    }
+
+    /// Offer the server's tools in `tool_mode` instead of the client's
+    pub fn tool_mode(mut self, tool_mode: ToolMode) -> Self {
+        self.tool_mode = Some(tool_mode);
+        self
+    }
+
+    /// List `tools` directly in `tools/list`
+    pub fn pinned_tools(mut self, tools: &[&str]) -> Self {
+        self.pinned_tools = tools.iter().map(|tool| tool.to_string()).collect();
+        self
+    }
+
+    /// Compact the server's tool schemas as `schema_compaction` says
+    pub fn schema_compaction(mut self, schema_compaction: SchemaCompaction) -> Self {
+        self.schema_compaction = schema_compaction;
+        self
+    }
+
+    /// Show the server's tool schemas in `get_dynamic_tools` as `tool_schemas` says
+    pub fn tool_schemas(mut self, tool_schemas: ToolSchemas) -> Self {
+        self.tool_schemas = tool_schemas;
+        self
+    }
+
+    pub fn config(self) -> McpServerConfig {
+        let mut cases = String::new();
+        for (pattern, body) in &self.cases {
+            cases.push_str(&format!("    {}) {} ;;\n", pattern, body));
+        }
+
+        let log = self
+            .log
+            .map(|path| format!("  printf '%s\\n' \"$line\" >> '{}'\n", path))
+            .unwrap_or_default();
+
+        let script = format!(
+            r#"while IFS= read -r line; do
+{log}  case "$line" in *'"method"'*) ;; *) continue ;; esac
+  id=$(printf '%s\n' "$line" | sed -n 's/^{{"jsonrpc":"2.0","id":\([^,]*\),.*/\1/p')
+  [ -z "$id" ] && continue
+  notify=''
+  case "$line" in
+{cases}    *'"method":"initialize"'*) result='{{"protocolVersion":"2025-06-18","capabilities":{{}}}}' ;;
+    *) result='{{}}' ;;
+  esac
+  printf '{{"jsonrpc":"2.0","id":%s,"result":%s}}\n' "$id" "$result"
+  [ -n "$notify" ] && printf '%s\n' "$notify"
+done"#
+        );
+
+        McpServerConfig::Stdio {
+            description: "Fake test server".to_string(),
+            command: "sh".to_string(),
+            args: Some(vec!["-c".to_string(), script]),
+            env: None,
+            features: Features::default(),
+            enabled: true,
+            timeout: self.timeout,
+            max_concurrent_batch_calls: None,
+            tool_overrides: self.tool_overrides,
+            resources: self.resources,
+            resource_templates: self.resource_templates,
+            prompts: self.prompts,
+            tools: self.tools,
+            tool_mode: self.tool_mode,
+            pinned_tools: self.pinned_tools,
+            schema_compaction: self.schema_compaction,
+            tool_schemas: self.tool_schemas,
+        }
+    }
+}
+
+/// Connect a client to each of `groups` and open a downstream session on it, returning the
+/// session and the client it shares
+pub async fn server_with(
+    groups: &[(&str, McpServerConfig)],
+) -> (ModularMcpServer, Arc<RwLock<ModularMcpClient>>) {
+    let mut client = ModularMcpClient::new();
+    for (name, config) in groups {
+        client
+            .connect(name.to_string(), config.clone())
+            .await
+            .unwrap();
+    }
+    let client = Arc::new(RwLock::new(client));
+    let server = ModularMcpServer::new(client.clone(), "test".to_string(), "1.0".to_string());
+    (server, client)
+}
+
+/// Serve MCP over plain HTTP `POST`s for tests and return an `http` config pointing at it.
+///
+/// Requests are answered with their method's entry in `results`, or `{}`; `initialize`
+/// reports protocol version 2025-06-18 unless `results` has an entry for it.
+pub async fn fake_http_server(results: serde_json::Value) -> McpServerConfig {
+    let results = std::sync::Arc::new(results);
+    let app = axum::Router::new().route(
+        "/mcp",
+        axum::routing::post(
+            move |axum::Json(request): axum::Json<serde_json::Value>| async move {
+                let method = request["method"].as_str().unwrap_or_default();
+                let result = match results.get(method) {
+                    Some(result) => result.clone(),
+                    None if method == "initialize" => {
+                        serde_json::json!({"protocolVersion": "2025-06-18", "capabilities": {}})
+                    }
+                    None => serde_json::json!({}),
+                };
+                axum::Json(serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "id": request["id"],
+                    "result": result
+                }))
+            },
+        ),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let _ = axum::serve(listener, app).await;
+    });
+
+    McpServerConfig::Http {
+        description: "Fake HTTP test server".to_string(),
+        url: format!("http://{}/mcp", addr),
+        headers: None,
+        oauth_client_id: None,
+        oauth_scopes: None,
+        features: Features::default(),
+        enabled: true,
+        timeout: Timeout::default(),
+        max_concurrent_batch_calls: None,
+        tool_overrides: HashMap::new(),
+        resources: NameFilter::default(),
+        resource_templates: NameFilter::default(),
+        prompts: NameFilter::default(),
+        tools: NameFilter::default(),
+        tool_mode: None,
+        pinned_tools: Vec::new(),
+        schema_compaction: SchemaCompaction::default(),
+        tool_schemas: ToolSchemas::default(),
+    }
+}
+
+/// `case` pattern matching requests for `method`
+fn method_pattern(method: &str) -> String {
+    format!("*'\"method\":\"{}\"'*", method)
+}
+
+/// Wait up to a second for a message matching `predicate` to show up in a [`FakeServer::log_to`] log
+pub async fn wait_for_logged(
+    path: &Path,
+    predicate: impl Fn(&serde_json::Value) -> bool,
+) -> Option<serde_json::Value> {
+    for _ in 0..50 {
+        let logged = std::fs::read_to_string(path)
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+            .find(|message| predicate(message));
+        if logged.is_some() {
+            return logged;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    None
+}
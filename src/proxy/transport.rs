@@ -1,17 +1,50 @@
 use crate::auth::OAuthClient;
 use crate::config::McpServerConfig;
-use crate::proxy::types::{JsonRpcRequest, JsonRpcResponse};
+use crate::proxy::types::{JsonRpcError, JsonRpcRequest, JsonRpcResponse};
 use anyhow::{Context, Result};
+use std::collections::HashMap;
 use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
 use tokio::process::{Child, ChildStdin, ChildStdout, Command};
-use tokio::sync::Mutex;
+use tokio::sync::{oneshot, Mutex};
+use tokio::task::JoinHandle;
+
+/// A message an upstream server sends on its own initiative rather than in reply to a request
+pub enum IncomingMessage {
+    Notification(JsonRpcRequest),
+    /// A server-to-client request; dropping the sender unanswered replies "Method not found"
+    Request(JsonRpcRequest, oneshot::Sender<JsonRpcResponse>),
+}
+
+/// Handles notifications and requests an upstream server sends on its own initiative
+pub type IncomingHandler = Arc<dyn Fn(IncomingMessage) + Send + Sync>;
+
+/// Requests awaiting a response, keyed by their serialized JSON-RPC id
+type PendingRequests = Arc<std::sync::Mutex<HashMap<String, oneshot::Sender<JsonRpcResponse>>>>;
 
 pub struct StdioTransport {
     child: Arc<Mutex<Child>>,
     stdin: Arc<Mutex<ChildStdin>>,
-    stdout: Arc<Mutex<BufReader<ChildStdout>>>,
+    pending: PendingRequests,
+    closed: Arc<AtomicBool>,
+    incoming_handler: Arc<std::sync::RwLock<IncomingHandler>>,
+    reader: JoinHandle<()>,
+}
+
+/// Removes a pending request when its caller stops waiting (response, error or timeout)
+struct PendingGuard {
+    pending: PendingRequests,
+    key: String,
+}
+
+impl Drop for PendingGuard {
+    fn drop(&mut self) {
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.remove(&self.key);
+        }
+    }
 }
 
 impl StdioTransport {
@@ -56,74 +89,69 @@ impl StdioTransport {
             .spawn()
             .with_context(|| format!("Failed to spawn command: {}", command))?;
 
-        let stdin = child.stdin.take().context("Failed to capture stdin")?;
+        let stdin = Arc::new(Mutex::new(
+            child.stdin.take().context("Failed to capture stdin")?,
+        ));
         let stdout = child.stdout.take().context("Failed to capture stdout")?;
 
+        let pending: PendingRequests = Arc::new(std::sync::Mutex::new(HashMap::new()));
+        let closed = Arc::new(AtomicBool::new(false));
+        let incoming_handler = Arc::new(std::sync::RwLock::new(default_incoming_handler()));
+        let reader = tokio::spawn(read_stdout(
+            BufReader::new(stdout),
+            stdin.clone(),
+            pending.clone(),
+            closed.clone(),
+            incoming_handler.clone(),
+        ));
+
         Ok(Self {
             child: Arc::new(Mutex::new(child)),
-            stdin: Arc::new(Mutex::new(stdin)),
-            stdout: Arc::new(Mutex::new(BufReader::new(stdout))),
+            stdin,
+            pending,
+            closed,
+            incoming_handler,
+            reader,
         })
     }
 
-    pub async fn send_request(&self, request: &JsonRpcRequest) -> Result<JsonRpcResponse> {
-        let request_json = serde_json::to_string(request)?;
-
-        {
-            let mut stdin = self.stdin.lock().await;
-            stdin.write_all(request_json.as_bytes()).await?;
-            stdin.write_all(b"\n").await?;
-            stdin.flush().await?;
+    /// Route upstream notifications and server-to-client requests to `handler`.
+    /// Without a handler, notifications are logged and requests are answered with
+    /// "Method not found".
+    pub fn set_incoming_handler(&self, handler: IncomingHandler) {
+        if let Ok(mut current) = self.incoming_handler.write() {
+            *current = handler;
         }
+    }
 
-        let mut stdout = self.stdout.lock().await;
-        loop {
-            let mut line = String::new();
-            let bytes_read = stdout.read_line(&mut line).await?;
+    pub async fn send_notification(&self, notification: &JsonRpcRequest) -> Result<()> {
+        let notification_json = serde_json::to_string(notification)?;
+        write_line(&self.stdin, &notification_json).await
+    }
 
-            if bytes_read == 0 {
-                anyhow::bail!("Connection closed before receiving response");
-            }
+    pub async fn send_request(&self, request: &JsonRpcRequest) -> Result<JsonRpcResponse> {
+        let request_json = serde_json::to_string(request)?;
 
-            let trimmed = line.trim();
-            if trimmed.is_empty() {
-                continue;
+        // Register before writing so a fast response cannot arrive ahead of its waiter
+        let (tx, rx) = oneshot::channel();
+        let key = request.id.to_string();
+        let _guard = PendingGuard {
+            pending: self.pending.clone(),
+            key: key.clone(),
+        };
+        if let Ok(mut pending) = self.pending.lock() {
+            if pending.insert(key, tx).is_some() {
+                tracing::warn!("Request id {} is already in flight", request.id);
             }
+        }
+        if self.closed.load(Ordering::SeqCst) {
+            anyhow::bail!("Connection closed before receiving response");
+        }
 
-            if !trimmed.starts_with('{') {
-                tracing::debug!("Skipping non-JSON output: {}", trimmed);
-                continue;
-            }
+        write_line(&self.stdin, &request_json).await?;
 
-            match serde_json::from_str::<JsonRpcResponse>(trimmed) {
-                Ok(response) => return Ok(response),
-                Err(e) => {
-                    if let Ok(value) = serde_json::from_str::<serde_json::Value>(trimmed) {
-                        if value.get("error").is_some() {
-                            let id_value = value.get("id").cloned();
-                            if id_value.is_none()
-                                || matches!(id_value, Some(serde_json::Value::Null))
-                            {
-                                tracing::warn!(
-                                    "Received error response with null id, skipping: {}",
-                                    trimmed
-                                );
-                                continue;
-                            }
-                            return Ok(JsonRpcResponse {
-                                jsonrpc: "2.0".to_string(),
-                                id: id_value.unwrap(),
-                                result: None,
-                                error: serde_json::from_value(value.get("error").unwrap().clone())
-                                    .ok(),
-                            });
-                        }
-                    }
-                    tracing::warn!("Failed to parse JSON-RPC response: {}. Raw: {}", e, trimmed);
-                    continue;
-                }
-            }
-        }
+        rx.await
+            .map_err(|_| anyhow::anyhow!("Connection closed before receiving response"))
     }
 
     pub async fn close(&mut self) -> Result<()> {
@@ -163,6 +191,8 @@ impl StdioTransport {
 
 impl Drop for StdioTransport {
     fn drop(&mut self) {
+        self.reader.abort();
+
         if let Ok(mut child) = self.child.try_lock() {
             // Force kill on drop (cleanup)
             #[cfg(unix)]
@@ -199,6 +229,167 @@ impl Drop for StdioTransport {
     }
 }
 
+async fn write_line(stdin: &Mutex<ChildStdin>, line: &str) -> Result<()> {
+    let mut stdin = stdin.lock().await;
+    stdin.write_all(line.as_bytes()).await?;
+    stdin.write_all(b"\n").await?;
+    stdin.flush().await?;
+    Ok(())
+}
+
+fn default_incoming_handler() -> IncomingHandler {
+    Arc::new(|message: IncomingMessage| match message {
+        IncomingMessage::Notification(notification) => {
+            tracing::debug!("Ignoring upstream notification: {}", notification.method);
+        }
+        IncomingMessage::Request(request, responder) => {
+            tracing::debug!("Rejecting unsupported upstream request: {}", request.method);
+            let _ = responder.send(method_not_found(request.id, &request.method));
+        }
+    })
+}
+
+fn method_not_found(id: serde_json::Value, method: &str) -> JsonRpcResponse {
+    JsonRpcResponse {
+        jsonrpc: "2.0".to_string(),
+        id,
+        result: None,
+        error: Some(JsonRpcError {
+            code: -32601,
+            message: format!("Method not found: {}", method),
+            data: None,
+        }),
+    }
+}
+
+/// Hand a server-to-client request to the handler and write its answer back upstream
+fn dispatch_upstream_request(
+    request: JsonRpcRequest,
+    handler: &IncomingHandler,
+    stdin: Arc<Mutex<ChildStdin>>,
+) {
+    let (tx, rx) = oneshot::channel();
+    let id = request.id.clone();
+    let method = request.method.clone();
+    handler(IncomingMessage::Request(request, tx));
+
+    tokio::spawn(async move {
+        let response = rx.await.unwrap_or_else(|_| method_not_found(id, &method));
+
+        if let Ok(response_json) = serde_json::to_string(&response) {
+            if let Err(e) = write_line(&stdin, &response_json).await {
+                tracing::warn!("Failed to answer upstream request: {}", e);
+            }
+        }
+    });
+}
+
+/// Read upstream stdout until EOF, delivering responses to their waiting callers by id
+/// and everything else to the incoming handler
+async fn read_stdout(
+    mut stdout: BufReader<ChildStdout>,
+    stdin: Arc<Mutex<ChildStdin>>,
+    pending: PendingRequests,
+    closed: Arc<AtomicBool>,
+    incoming_handler: Arc<std::sync::RwLock<IncomingHandler>>,
+) {
+    let mut line = String::new();
+    loop {
+        line.clear();
+        match stdout.read_line(&mut line).await {
+            Ok(0) => break,
+            Ok(_) => {}
+            Err(e) => {
+                tracing::warn!("Failed to read from upstream stdout: {}", e);
+                break;
+            }
+        }
+
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+
+        if !trimmed.starts_with('{') {
+            tracing::debug!("Skipping non-JSON output: {}", trimmed);
+            continue;
+        }
+
+        let value = match serde_json::from_str::<serde_json::Value>(trimmed) {
+            Ok(value) => value,
+            Err(e) => {
+                tracing::warn!("Failed to parse JSON-RPC message: {}. Raw: {}", e, trimmed);
+                continue;
+            }
+        };
+
+        if value.get("method").is_some() {
+            match serde_json::from_value::<JsonRpcRequest>(value) {
+                Ok(message) => {
+                    let Some(handler) = incoming_handler.read().ok().map(|h| h.clone()) else {
+                        continue;
+                    };
+                    if message.is_notification() {
+                        handler(IncomingMessage::Notification(message));
+                    } else {
+                        dispatch_upstream_request(message, &handler, stdin.clone());
+                    }
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to parse JSON-RPC request: {}. Raw: {}", e, trimmed)
+                }
+            }
+            continue;
+        }
+
+        let Some(response) = parse_response(value, trimmed) else {
+            continue;
+        };
+
+        let waiter = pending
+            .lock()
+            .ok()
+            .and_then(|mut pending| pending.remove(&response.id.to_string()));
+        match waiter {
+            Some(tx) => {
+                let _ = tx.send(response);
+            }
+            None => tracing::warn!("Received response for unknown request id {}", response.id),
+        }
+    }
+
+    // Fail everything still waiting; dropping the senders wakes the callers.
+    // Requests registered after this point see the closed flag instead.
+    closed.store(true, Ordering::SeqCst);
+    if let Ok(mut pending) = pending.lock() {
+        pending.clear();
+    }
+}
+
+fn parse_response(value: serde_json::Value, raw: &str) -> Option<JsonRpcResponse> {
+    let id_value = value.get("id").cloned();
+    if id_value.is_none() || matches!(id_value, Some(serde_json::Value::Null)) {
+        tracing::warn!("Received response with null id, skipping: {}", raw);
+        return None;
+    }
+
+    match serde_json::from_value::<JsonRpcResponse>(value.clone()) {
+        Ok(response) => Some(response),
+        Err(e) => {
+            if let Some(error) = value.get("error") {
+                return Some(JsonRpcResponse {
+                    jsonrpc: "2.0".to_string(),
+                    id: id_value.unwrap(),
+                    result: None,
+                    error: serde_json::from_value(error.clone()).ok(),
+                });
+            }
+            tracing::warn!("Failed to parse JSON-RPC response: {}. Raw: {}", e, raw);
+            None
+        }
+    }
+}
+
 pub struct HttpTransport {
     client: reqwest::Client,
     url: String,
@@ -242,7 +433,7 @@ impl HttpTransport {
         }
     }
 
-    pub async fn send_request(&self, request: &JsonRpcRequest) -> Result<JsonRpcResponse> {
+    fn post(&self) -> reqwest::RequestBuilder {
         let protocol_ver = if let Ok(pv) = self.protocol_version.try_lock() {
             pv.clone()
         } else {
@@ -267,7 +458,27 @@ impl HttpTransport {
             req = req.header(key, value);
         }
 
-        let response = req
+        req
+    }
+
+    pub async fn send_notification(&self, notification: &JsonRpcRequest) -> Result<()> {
+        let response = self
+            .post()
+            .json(notification)
+            .send()
+            .await
+            .context("Failed to send HTTP notification")?;
+
+        let status = response.status();
+        if !status.is_success() {
+            anyhow::bail!("HTTP notification failed with status {}", status);
+        }
+        Ok(())
+    }
+
+    pub async fn send_request(&self, request: &JsonRpcRequest) -> Result<JsonRpcResponse> {
+        let response = self
+            .post()
             .json(request)
             .send()
             .await
@@ -444,7 +655,7 @@ impl SseTransport {
         Ok((json_response, event_id))
     }
 
-    pub async fn send_request(&self, request: &JsonRpcRequest) -> Result<JsonRpcResponse> {
+    fn post(&self) -> reqwest::RequestBuilder {
         let protocol_ver = if let Ok(pv) = self.protocol_version.try_lock() {
             pv.clone()
         } else {
@@ -474,7 +685,27 @@ impl SseTransport {
             req = req.header(key, value);
         }
 
-        let response = req
+        req
+    }
+
+    pub async fn send_notification(&self, notification: &JsonRpcRequest) -> Result<()> {
+        let response = self
+            .post()
+            .json(notification)
+            .send()
+            .await
+            .context("Failed to send SSE notification")?;
+
+        let status = response.status();
+        if !status.is_success() {
+            anyhow::bail!("SSE notification failed with status {}", status);
+        }
+        Ok(())
+    }
+
+    pub async fn send_request(&self, request: &JsonRpcRequest) -> Result<JsonRpcResponse> {
+        let response = self
+            .post()
             .json(request)
             .send()
             .await
@@ -591,6 +822,15 @@ impl Transport {
         }
     }
 
+    /// Send a notification; no response is expected
+    pub async fn send_notification(&self, notification: &JsonRpcRequest) -> Result<()> {
+        match self {
+            Transport::Stdio(t) => t.send_notification(notification).await,
+            Transport::Http(t) => t.send_notification(notification).await,
+            Transport::Sse(t) => t.send_notification(notification).await,
+        }
+    }
+
     pub fn set_session_id(&self, session_id: String) {
         match self {
             Transport::Stdio(_) => {}
@@ -599,6 +839,21 @@ impl Transport {
         }
     }
 
+    /// Whether the upstream can send notifications and requests on its own initiative.
+    /// Only stdio upstreams can; the HTTP and SSE transports do not keep a stream open for
+    /// server-initiated messages.
+    pub fn delivers_server_messages(&self) -> bool {
+        matches!(self, Transport::Stdio(_))
+    }
+
+    /// Route server-initiated messages to `handler`; see [`Self::delivers_server_messages`]
+    pub fn set_incoming_handler(&self, handler: IncomingHandler) {
+        match self {
+            Transport::Stdio(t) => t.set_incoming_handler(handler),
+            Transport::Http(_) | Transport::Sse(_) => {}
+        }
+    }
+
     pub fn set_protocol_version(&self, version: String) {
         match self {
             Transport::Stdio(_) => {}
@@ -619,7 +874,7 @@ impl Transport {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::config::schema::{Features, Timeout};
+    use crate::config::schema::{Features, NameFilter, SchemaCompaction, Timeout, ToolSchemas};
     use std::collections::HashMap;
 
     #[tokio::test]
@@ -633,6 +888,16 @@ mod tests {
             features: Features::default(),
             enabled: true,
             timeout: Timeout::default(),
+            max_concurrent_batch_calls: None,
+            tool_overrides: HashMap::new(),
+            resources: NameFilter::default(),
+            resource_templates: NameFilter::default(),
+            prompts: NameFilter::default(),
+            tools: NameFilter::default(),
+            tool_mode: None,
+            pinned_tools: Vec::new(),
+            schema_compaction: SchemaCompaction::default(),
+            tool_schemas: ToolSchemas::default(),
         };
 
         let result = Transport::new(&config, "test_server").await;
@@ -653,6 +918,16 @@ mod tests {
             features: Features::default(),
             enabled: true,
             timeout: Timeout::default(),
+            max_concurrent_batch_calls: None,
+            tool_overrides: HashMap::new(),
+            resources: NameFilter::default(),
+            resource_templates: NameFilter::default(),
+            prompts: NameFilter::default(),
+            tools: NameFilter::default(),
+            tool_mode: None,
+            pinned_tools: Vec::new(),
+            schema_compaction: SchemaCompaction::default(),
+            tool_schemas: ToolSchemas::default(),
         };
 
         let result = Transport::new(&config, "test_server").await;
@@ -670,6 +945,16 @@ mod tests {
             features: Features::default(),
             enabled: true,
             timeout: Timeout::default(),
+            max_concurrent_batch_calls: None,
+            tool_overrides: HashMap::new(),
+            resources: NameFilter::default(),
+            resource_templates: NameFilter::default(),
+            prompts: NameFilter::default(),
+            tools: NameFilter::default(),
+            tool_mode: None,
+            pinned_tools: Vec::new(),
+            schema_compaction: SchemaCompaction::default(),
+            tool_schemas: ToolSchemas::default(),
         };
 
         let result = Transport::new(&config, "test_server").await;
@@ -690,6 +975,16 @@ mod tests {
             features: Features::default(),
             enabled: true,
             timeout: Timeout::default(),
+            max_concurrent_batch_calls: None,
+            tool_overrides: HashMap::new(),
+            resources: NameFilter::default(),
+            resource_templates: NameFilter::default(),
+            prompts: NameFilter::default(),
+            tools: NameFilter::default(),
+            tool_mode: None,
+            pinned_tools: Vec::new(),
+            schema_compaction: SchemaCompaction::default(),
+            tool_schemas: ToolSchemas::default(),
         };
 
         let result = Transport::new(&config, "test_server").await;
@@ -706,6 +1001,16 @@ mod tests {
             features: Features::default(),
             enabled: true,
             timeout: Timeout::default(),
+            max_concurrent_batch_calls: None,
+            tool_overrides: HashMap::new(),
+            resources: NameFilter::default(),
+            resource_templates: NameFilter::default(),
+            prompts: NameFilter::default(),
+            tools: NameFilter::default(),
+            tool_mode: None,
+            pinned_tools: Vec::new(),
+            schema_compaction: SchemaCompaction::default(),
+            tool_schemas: ToolSchemas::default(),
         };
 
         let result = Transport::new(&config, "test_server").await;
@@ -725,6 +1030,16 @@ mod tests {
             features: Features::default(),
             enabled: true,
             timeout: Timeout::default(),
+            max_concurrent_batch_calls: None,
+            tool_overrides: HashMap::new(),
+            resources: NameFilter::default(),
+            resource_templates: NameFilter::default(),
+            prompts: NameFilter::default(),
+            tools: NameFilter::default(),
+            tool_mode: None,
+            pinned_tools: Vec::new(),
+            schema_compaction: SchemaCompaction::default(),
+            tool_schemas: ToolSchemas::default(),
         };
 
         let sse_config = McpServerConfig::Sse {
@@ -736,6 +1051,16 @@ mod tests {
             features: Features::default(),
             enabled: true,
             timeout: Timeout::default(),
+            max_concurrent_batch_calls: None,
+            tool_overrides: HashMap::new(),
+            resources: NameFilter::default(),
+            resource_templates: NameFilter::default(),
+            prompts: NameFilter::default(),
+            tools: NameFilter::default(),
+            tool_mode: None,
+            pinned_tools: Vec::new(),
+            schema_compaction: SchemaCompaction::default(),
+            tool_schemas: ToolSchemas::default(),
         };
 
         let stdio_config = McpServerConfig::Stdio {
@@ -746,6 +1071,16 @@ mod tests {
             features: Features::default(),
             enabled: true,
             timeout: Timeout::default(),
+            max_concurrent_batch_calls: None,
+            tool_overrides: HashMap::new(),
+            resources: NameFilter::default(),
+            resource_templates: NameFilter::default(),
+            prompts: NameFilter::default(),
+            tools: NameFilter::default(),
+            tool_mode: None,
+            pinned_tools: Vec::new(),
+            schema_compaction: SchemaCompaction::default(),
+            tool_schemas: ToolSchemas::default(),
         };
 
         assert!(discriminant(&http_config) != discriminant(&sse_config));
@@ -813,4 +1148,107 @@ mod tests {
 
         assert_eq!(event_id, Some("test-event-789".to_string()));
     }
+
+    #[cfg(unix)]
+    async fn spawn_script(script: &str) -> StdioTransport {
+        let args = vec!["-c".to_string(), script.to_string()];
+        StdioTransport::new("sh", Some(&args), None)
+            .await
+            .expect("Failed to spawn test script")
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_stdio_responses_matched_by_id() {
+        // Answers the second request first, with a notification in between
+        let transport = spawn_script(
+            r#"read a; read b
+            echo '{"jsonrpc":"2.0","method":"notifications/message","params":{}}'
+            echo '{"jsonrpc":"2.0","id":2,"result":{"n":2}}'
+            echo '{"jsonrpc":"2.0","id":1,"result":{"n":1}}'
+            cat > /dev/null"#,
+        )
+        .await;
+
+        let first = JsonRpcRequest::new(1, "tools/list");
+        let second = JsonRpcRequest::new(2, "tools/list");
+        let (first, second) = tokio::join!(
+            transport.send_request(&first),
+            transport.send_request(&second)
+        );
+
+        assert_eq!(first.unwrap().result.unwrap()["n"], 1);
+        assert_eq!(second.unwrap().result.unwrap()["n"], 2);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_stdio_notifications_go_to_incoming_handler() {
+        let transport = spawn_script(
+            r#"read a
+            echo '{"jsonrpc":"2.0","method":"notifications/tools/list_changed"}'
+            echo '{"jsonrpc":"2.0","id":1,"result":{}}'
+            cat > /dev/null"#,
+        )
+        .await;
+
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        transport.set_incoming_handler(Arc::new(move |message: IncomingMessage| {
+            if let IncomingMessage::Notification(notification) = message {
+                let _ = tx.send(notification.method);
+            }
+        }));
+
+        let response = transport
+            .send_request(&JsonRpcRequest::new(1, "ping"))
+            .await
+            .unwrap();
+
+        assert_eq!(response.id, 1);
+        assert_eq!(
+            rx.recv().await.as_deref(),
+            Some("notifications/tools/list_changed")
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_stdio_unhandled_server_request_is_rejected() {
+        let transport = spawn_script(
+            r#"read a
+            echo '{"jsonrpc":"2.0","id":"srv-1","method":"sampling/createMessage"}'
+            read reply
+            case "$reply" in
+              *-32601*) echo '{"jsonrpc":"2.0","id":1,"result":{"rejected":true}}' ;;
+              *) echo '{"jsonrpc":"2.0","id":1,"result":{"rejected":false}}' ;;
+            esac
+            cat > /dev/null"#,
+        )
+        .await;
+
+        let response = transport
+            .send_request(&JsonRpcRequest::new(1, "ping"))
+            .await
+            .unwrap();
+
+        assert_eq!(response.result.unwrap()["rejected"], true);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_stdio_pending_request_fails_when_process_exits() {
+        let transport = spawn_script("read a; exit 0").await;
+
+        let result = transport
+            .send_request(&JsonRpcRequest::new(1, "ping"))
+            .await;
+
+        assert!(result.is_err());
+        assert!(transport.pending.lock().unwrap().is_empty());
+
+        let result = transport
+            .send_request(&JsonRpcRequest::new(2, "ping"))
+            .await;
+        assert!(result.is_err());
+    }
 }
@@ -1,4 +1,4 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GroupInfo {
@@ -17,9 +17,13 @@ pub struct FailedGroupInfo {
 pub struct ToolInfo {
     pub name: String,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
     #[serde(rename = "inputSchema")]
     pub input_schema: serde_json::Value,
+    #[serde(rename = "outputSchema", skip_serializing_if = "Option::is_none")]
+    pub output_schema: Option<serde_json::Value>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -40,11 +44,16 @@ fn is_null(value: &serde_json::Value) -> bool {
     matches!(value, serde_json::Value::Null)
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(untagged)]
+/// A message from the client. Requests carry a `method`; responses carry no `method` and
+/// exactly one of `result` or `error`.
+#[derive(Debug, Clone)]
 pub enum JsonRpcMessage {
     Request(JsonRpcRequest),
     Batch(Vec<JsonRpcRequest>),
+    /// The client's answer to a request the server sent it
+    Response(JsonRpcResponse),
+    /// Valid JSON that is neither, answered with Invalid Request under its `id`, if any
+    Invalid(serde_json::Value),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -213,10 +222,53 @@ impl JsonRpcRequest {
         }
     }
 
+    /// A notification: a request without an id
+    pub fn notification(method: impl Into<String>) -> Self {
+        Self::new(serde_json::Value::Null, method)
+    }
+
     pub fn with_params(mut self, params: serde_json::Value) -> Self {
         self.params = Some(params);
         self
     }
+
+    /// Notifications carry no id and must not be answered
+    pub fn is_notification(&self) -> bool {
+        is_null(&self.id)
+    }
+}
+
+impl<'de> Deserialize<'de> for JsonRpcMessage {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = serde_json::Value::deserialize(deserializer)?;
+        let message = match &value {
+            serde_json::Value::Array(_) => serde_json::from_value(value.clone())
+                .map(JsonRpcMessage::Batch)
+                .ok(),
+            serde_json::Value::Object(obj) if obj.contains_key("method") => {
+                serde_json::from_value(value.clone())
+                    .map(JsonRpcMessage::Request)
+                    .ok()
+            }
+            serde_json::Value::Object(obj)
+                if obj.contains_key("result") != obj.contains_key("error") =>
+            {
+                serde_json::from_value(value.clone())
+                    .map(JsonRpcMessage::Response)
+                    .ok()
+            }
+            _ => None,
+        };
+        Ok(message.unwrap_or_else(|| {
+            let id = match value.get("id") {
+                Some(id @ (serde_json::Value::String(_) | serde_json::Value::Number(_))) => {
+                    id.clone()
+                }
+                _ => serde_json::Value::Null,
+            };
+            JsonRpcMessage::Invalid(id)
+        }))
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,122 @@
+//! Matching resource URIs against RFC 6570 URI templates.
+//!
+//! Upstream servers advertise parameterized resources through `resources/templates/list`.
+//! The proxy never expands templates; it only needs to tell whether a concrete URI could
+//! have been produced by one, to route `resources/read` to the right group.
+
+use regex::Regex;
+
+/// A URI template compiled into a regular expression over the URIs it can expand to
+#[derive(Debug, Clone)]
+pub struct UriTemplate {
+    pattern: Regex,
+}
+
+impl UriTemplate {
+    /// Compile `template`, or `None` if its expressions are malformed
+    pub fn parse(template: &str) -> Option<Self> {
+        let mut pattern = String::from("^");
+        let mut rest = template;
+
+        while let Some(start) = rest.find('{') {
+            pattern.push_str(&regex::escape(&rest[..start]));
+            let end = rest[start..].find('}')? + start;
+            pattern.push_str(expression_pattern(&rest[start + 1..end])?);
+            rest = &rest[end + 1..];
+        }
+        if rest.contains('}') {
+            return None;
+        }
+        pattern.push_str(&regex::escape(rest));
+        pattern.push('$');
+
+        Some(Self {
+            pattern: Regex::new(&pattern).ok()?,
+        })
+    }
+
+    /// Whether expanding the template with some values could yield `uri`
+    pub fn matches(&self, uri: &str) -> bool {
+        self.pattern.is_match(uri)
+    }
+}
+
+/// Pattern for the text one expression (without braces) expands to, by operator
+fn expression_pattern(expression: &str) -> Option<&'static str> {
+    let mut chars = expression.chars();
+    let operator = chars.next()?;
+    let variables = if "+#./;?&".contains(operator) {
+        chars.as_str()
+    } else {
+        expression
+    };
+    if variables.is_empty() {
+        return None;
+    }
+
+    Some(match operator {
+        // Reserved expansion keeps `/`, `?` and `#`, so it can span path segments
+        '+' => ".*",
+        '#' => "(?:#.*)?",
+        '.' => r"(?:\.[^/?#]*)*",
+        '/' => "(?:/[^/?#]*)*",
+        ';' => "(?:;[^/?#]*)*",
+        '?' => r"(?:\?[^#]*)?",
+        '&' => "(?:&[^#]*)?",
+        // Simple expansion percent-encodes reserved characters, so it stays in one segment
+        _ => "[^/?#]*",
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(template: &str, uri: &str) -> bool {
+        UriTemplate::parse(template).unwrap().matches(uri)
+    }
+
+    #[test]
+    fn test_simple_expansion_stays_in_segment() {
+        assert!(matches(
+            "file:///logs/{date}.log",
+            "file:///logs/2026-01-01.log"
+        ));
+        assert!(!matches("file:///logs/{date}.log", "file:///logs/a/b.log"));
+        assert!(!matches("file:///logs/{date}.log", "file:///other/x.log"));
+    }
+
+    #[test]
+    fn test_reserved_expansion_spans_segments() {
+        assert!(matches("file:///{+path}", "file:///src/main.rs"));
+        assert!(matches(
+            "repo://{owner}/{repo}{/path}",
+            "repo://a/b/src/lib.rs"
+        ));
+        assert!(matches("repo://{owner}/{repo}{/path}", "repo://a/b"));
+    }
+
+    #[test]
+    fn test_query_expansion() {
+        assert!(matches(
+            "search://items{?q,limit}",
+            "search://items?q=rust&limit=5"
+        ));
+        assert!(matches("search://items{?q,limit}", "search://items"));
+        assert!(!matches("search://items{?q,limit}", "search://other"));
+    }
+
+    #[test]
+    fn test_literals_are_escaped() {
+        assert!(matches("db://table.{name}", "db://table.users"));
+        assert!(!matches("db://table.{name}", "db://tableXusers"));
+    }
+
+    #[test]
+    fn test_malformed_templates_are_rejected() {
+        assert!(UriTemplate::parse("file:///{path").is_none());
+        assert!(UriTemplate::parse("file:///path}").is_none());
+        assert!(UriTemplate::parse("file:///{}").is_none());
+        assert!(UriTemplate::parse("file:///{+}").is_none());
+    }
+}
@@ -0,0 +1,114 @@
+//! Checking tool calls against the tools a group advertised, before they go upstream.
+//!
+//! A call to a misspelled tool or with malformed arguments would only fail upstream, often
+//! with a terse error. Rejecting it locally lets the LLM see every problem at once, along
+//! with the schema or the names it should have used.
+
+use crate::proxy::search::edit_distance;
+use serde_json::Value;
+
+/// Most tool names suggested for an unknown one
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Every way `arguments` violate `schema`, as `<location>: <problem>`. Schemas the
+/// validator cannot compile are not checked, since the upstream server has the last word.
+pub fn argument_errors(schema: &Value, arguments: &Value) -> Vec<String> {
+    let validator = match jsonschema::validator_for(schema) {
+        Ok(validator) => validator,
+        Err(e) => {
+            tracing::debug!("Not validating arguments against invalid schema: {}", e);
+            return Vec::new();
+        }
+    };
+    validator
+        .iter_errors(arguments)
+        .map(|error| format!("args{}: {}", error.instance_path, error))
+        .collect()
+}
+
+/// Names among `candidates` that `name` is likely a misspelling of, closest first
+pub fn similar_names<'a>(
+    name: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+) -> Vec<&'a str> {
+    let lowercase = name.to_lowercase();
+    let allowed = (name.chars().count() / 3).max(2);
+
+    let mut similar: Vec<(usize, &str)> = candidates
+        .into_iter()
+        .filter_map(|candidate| {
+            let candidate_lowercase = candidate.to_lowercase();
+            let distance = edit_distance(&lowercase, &candidate_lowercase);
+            // Names that contain each other are as good as a single typo
+            let contained = lowercase.len() >= 3
+                && (candidate_lowercase.contains(&lowercase)
+                    || lowercase.contains(&candidate_lowercase));
+            if distance <= allowed {
+                Some((distance, candidate))
+            } else if contained {
+                Some((allowed, candidate))
+            } else {
+                None
+            }
+        })
+        .collect();
+    similar.sort();
+    similar
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, candidate)| candidate)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_argument_errors_lists_every_violation() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "url": {"type": "string"},
+                "retries": {"type": "integer", "minimum": 0}
+            },
+            "required": ["url"],
+            "additionalProperties": false
+        });
+        assert!(argument_errors(&schema, &json!({"url": "https://example.com"})).is_empty());
+
+        let errors = argument_errors(&schema, &json!({"retries": -1, "verbose": true}));
+        assert_eq!(errors.len(), 3);
+        assert!(errors
+            .iter()
+            .any(|e| e.starts_with("args: ") && e.contains("url")));
+        assert!(errors.iter().any(|e| e.starts_with("args/retries: ")));
+        assert!(errors.iter().any(|e| e.contains("verbose")));
+    }
+
+    #[test]
+    fn test_argument_errors_skips_uncompilable_schemas() {
+        let schema = json!({"type": "object", "properties": {"a": {"type": 5}}});
+        assert!(argument_errors(&schema, &json!({"a": 1})).is_empty());
+    }
+
+    #[test]
+    fn test_similar_names() {
+        let tools = [
+            "browser_navigate",
+            "browser_navigate_back",
+            "browser_click",
+            "browser_take_screenshot",
+        ];
+        assert_eq!(
+            similar_names("browser_navigat", tools),
+            vec!["browser_navigate", "browser_navigate_back"]
+        );
+        assert_eq!(
+            similar_names("screenshot", tools),
+            vec!["browser_take_screenshot"]
+        );
+        assert!(similar_names("send_email", tools).is_empty());
+    }
+}
@@ -1,15 +1,122 @@
-use crate::proxy::types::{JsonRpcError, JsonRpcRequest, JsonRpcResponse};
+use crate::config::schema::{SchemaCompaction, ToolMode, ToolSchemas};
+use crate::proxy::client::{UpstreamCallTag, UpstreamNotification, UpstreamRequest};
+use crate::proxy::minify::{estimate_tokens, minify_description, minify_schema};
+use crate::proxy::repair::repair_arguments;
+use crate::proxy::types::{
+    JsonRpcError, JsonRpcMessage, JsonRpcRequest, JsonRpcResponse, ToolInfo,
+};
+use crate::proxy::validation::{argument_errors, similar_names};
 use crate::proxy::ModularMcpClient;
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
 use serde_json::json;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::{broadcast, mpsc, oneshot, OwnedSemaphorePermit, Semaphore};
+use tokio::task::{JoinHandle, JoinSet};
 
+/// Default number of downstream requests handled concurrently per session
+pub const DEFAULT_MAX_CONCURRENT_REQUESTS: usize = 32;
+
+/// Protocol versions offered to downstream clients, newest first
+pub const SUPPORTED_PROTOCOL_VERSIONS: [&str; 4] =
+    ["2025-11-25", "2025-06-18", "2025-03-26", "2024-11-05"];
+
+/// Version assumed for clients whose `initialize` names none
+const DEFAULT_PROTOCOL_VERSION: &str = "2024-11-05";
+
+/// First version with structured tool output, output schemas, titles and resource links
+const STRUCTURED_OUTPUT_VERSION: &str = "2025-06-18";
+
+/// Scheme of resource URIs namespaced as `dmcp://<group>/<original-uri>`
+const NAMESPACED_URI_PREFIX: &str = "dmcp://";
+
+/// Separator of prompt names namespaced as `<group>__<prompt>`
+const NAMESPACED_PROMPT_SEPARATOR: &str = "__";
+
+/// Separator of pinned and loaded tool names exposed as `<group>__<tool>`
+const NAMESPACED_TOOL_SEPARATOR: &str = "__";
+
+/// `_meta` key listing the repairs made to `call_dynamic_tool` arguments
+const ARGUMENT_REPAIRS_META_KEY: &str = "dynamic-mcp/argumentRepairs";
+
+/// Longest tool name listed in `tools/list`, the strictest limit among common clients
+const MAX_TOOL_NAME_LENGTH: usize = 64;
+
+/// Number of tools `search_dynamic_tools` returns unless asked for another amount
+const DEFAULT_SEARCH_LIMIT: usize = 10;
+
+/// Most tools one `search_dynamic_tools` call returns
+const MAX_SEARCH_LIMIT: usize = 50;
+
+/// Longest tool description in `search_dynamic_tools` results, in characters
+const SEARCH_DESCRIPTION_LENGTH: usize = 200;
+
+/// Most calls one `call_dynamic_tools` batch may contain
+const MAX_BATCH_CALLS: usize = 32;
+
+/// Pick the version to speak with a client: the requested one when supported, otherwise
+/// the newest supported version, which the client may then reject
+pub fn negotiate_protocol_version(requested: Option<&str>) -> &'static str {
+    match requested {
+        None => DEFAULT_PROTOCOL_VERSION,
+        Some(requested) => SUPPORTED_PROTOCOL_VERSIONS
+            .into_iter()
+            .find(|supported| *supported == requested)
+            .unwrap_or(SUPPORTED_PROTOCOL_VERSIONS[0]),
+    }
+}
+
+/// A downstream MCP session. Clones share the same session state.
+#[derive(Clone)]
 pub struct ModularMcpServer {
     client: Arc<tokio::sync::RwLock<ModularMcpClient>>,
     name: String,
     version: String,
     subscriptions: Arc<tokio::sync::RwLock<HashSet<String>>>,
+    outbound: Option<mpsc::UnboundedSender<serde_json::Value>>,
+    in_flight: Arc<Semaphore>,
+    /// Number of permits `in_flight` was created with
+    max_concurrent_requests: usize,
+    in_progress: Arc<std::sync::Mutex<HashMap<String, InProgressRequest>>>,
+    /// Registration with the client for answering upstream requests, set by `initialize`
+    downstream_peer: Arc<std::sync::Mutex<Option<u64>>>,
+    /// Upstream requests relayed to the client, keyed by the id they were sent with
+    relayed_requests: Arc<std::sync::Mutex<HashMap<String, RelayedRequest>>>,
+    /// Protocol version negotiated in `initialize`
+    protocol_version: Arc<std::sync::RwLock<&'static str>>,
+    /// Groups opened with `get_dynamic_tools` whose tools are listed in `tools/list`,
+    /// when dynamic loading is enabled
+    loaded_groups: Arc<std::sync::Mutex<HashSet<String>>>,
+}
+
+/// An upstream request waiting for the client's answer
+struct RelayedRequest {
+    /// The id the upstream server used, restored on the answer
+    upstream_id: serde_json::Value,
+    responder: oneshot::Sender<JsonRpcResponse>,
+}
+
+/// A downstream request that the client can still cancel
+struct InProgressRequest {
+    cancel: oneshot::Sender<()>,
+    /// Group and upstream request id of every call the request was forwarded as
+    upstream: Vec<(String, serde_json::Value)>,
+}
+
+/// Forgets an in-progress request once it completes or is cancelled
+struct InProgressGuard {
+    in_progress: Arc<std::sync::Mutex<HashMap<String, InProgressRequest>>>,
+    key: String,
+}
+
+impl Drop for InProgressGuard {
+    fn drop(&mut self) {
+        if let Ok(mut in_progress) = self.in_progress.lock() {
+            in_progress.remove(&self.key);
+        }
+    }
 }
 
 impl ModularMcpServer {
@@ -23,6 +130,424 @@ impl ModularMcpServer {
             name,
             version,
             subscriptions: Arc::new(tokio::sync::RwLock::new(HashSet::new())),
+            outbound: None,
+            in_flight: Arc::new(Semaphore::new(DEFAULT_MAX_CONCURRENT_REQUESTS)),
+            max_concurrent_requests: DEFAULT_MAX_CONCURRENT_REQUESTS,
+            in_progress: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            downstream_peer: Arc::new(std::sync::Mutex::new(None)),
+            relayed_requests: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            protocol_version: Arc::new(std::sync::RwLock::new(DEFAULT_PROTOCOL_VERSION)),
+            loaded_groups: Arc::new(std::sync::Mutex::new(HashSet::new())),
+        }
+    }
+
+    /// Protocol version negotiated with this session's client
+    pub fn protocol_version(&self) -> &'static str {
+        self.protocol_version
+            .read()
+            .map(|version| *version)
+            .unwrap_or(DEFAULT_PROTOCOL_VERSION)
+    }
+
+    /// Whether the negotiated version has structured tool output, output schemas, titles
+    /// and resource links. Versions are dates, so they compare as strings.
+    fn supports_structured_output(&self) -> bool {
+        self.protocol_version() >= STRUCTURED_OUTPUT_VERSION
+    }
+
+    /// Limit how many requests from this session are handled at the same time
+    pub fn with_max_concurrent_requests(mut self, limit: usize) -> Self {
+        self.max_concurrent_requests = limit.max(1);
+        self.in_flight = Arc::new(Semaphore::new(self.max_concurrent_requests));
+        self
+    }
+
+    /// Attach a channel for server-initiated messages (notifications and requests)
+    pub fn with_outbound(mut self, outbound: mpsc::UnboundedSender<serde_json::Value>) -> Self {
+        self.outbound = Some(outbound);
+        self
+    }
+
+    /// Send a message to the downstream client, if a channel is attached
+    pub fn send_to_client(&self, message: serde_json::Value) {
+        if let Some(outbound) = &self.outbound {
+            if outbound.send(message).is_err() {
+                tracing::debug!("Downstream client channel closed, dropping message");
+            }
+        }
+    }
+
+    /// Relay notifications to the client until the session ends.
+    ///
+    /// - `notifications/tools/list_changed` whenever groups connect or go away, since the
+    ///   group `enum` in the meta-tool schemas is only read on `tools/list`
+    /// - upstream `notifications/resources/updated` for URIs this session subscribed to
+    ///
+    /// Returns `None` without an outbound channel.
+    pub fn spawn_notifier(&self) -> Option<JoinHandle<()>> {
+        let outbound = self.outbound.clone()?;
+        let client = self.client.clone();
+        let subscriptions = self.subscriptions.clone();
+
+        Some(tokio::spawn(async move {
+            let (mut groups, mut upstream) = {
+                let client = client.read().await;
+                (
+                    client.subscribe_group_changes(),
+                    client.subscribe_upstream_notifications(),
+                )
+            };
+
+            loop {
+                let notification = tokio::select! {
+                    changed = groups.changed() => match changed {
+                        Ok(()) => json!({
+                            "jsonrpc": "2.0",
+                            "method": "notifications/tools/list_changed"
+                        }),
+                        Err(_) => break,
+                    },
+                    received = upstream.recv() => match received {
+                        Ok(upstream) => match relayed_notification(upstream, &client, &subscriptions).await {
+                            Some(notification) => notification,
+                            None => continue,
+                        },
+                        Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                            tracing::warn!("Dropped {} upstream notifications", skipped);
+                            continue;
+                        }
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    },
+                };
+
+                if outbound.send(notification).is_err() {
+                    break;
+                }
+            }
+        }))
+    }
+
+    /// Handle a single or batch JSON-RPC message, returning the response payload if one is due.
+    ///
+    /// Notifications are never answered, so a batch of only notifications yields `None`, and
+    /// neither are requests the client cancelled. Requests wait for a free slot when the
+    /// session's concurrent request limit is reached; notifications never wait, so a
+    /// cancellation gets through even when every slot is busy.
+    pub async fn handle_message(&self, message: JsonRpcMessage) -> Option<serde_json::Value> {
+        // The semaphore is never closed, so acquiring only waits for a free slot
+        let _permit = if needs_slot(&message) {
+            self.in_flight.acquire().await.ok()
+        } else {
+            None
+        };
+        self.handle_admitted_message(message).await
+    }
+
+    /// Handle a message that already holds a slot, if it needs one
+    async fn handle_admitted_message(&self, message: JsonRpcMessage) -> Option<serde_json::Value> {
+        match message {
+            JsonRpcMessage::Batch(requests) => {
+                tracing::debug!("Received batch request with {} requests", requests.len());
+
+                if requests.is_empty() {
+                    // Empty batch is invalid per JSON-RPC spec
+                    let error_response = JsonRpcResponse {
+                        jsonrpc: "2.0".to_string(),
+                        id: serde_json::Value::Null,
+                        result: None,
+                        error: Some(JsonRpcError {
+                            code: -32600,
+                            message: "Invalid Request: batch array cannot be empty".to_string(),
+                            data: None,
+                        }),
+                    };
+                    return serde_json::to_value(&error_response).ok();
+                }
+
+                let (notifications, requests): (Vec<_>, Vec<_>) = requests
+                    .into_iter()
+                    .partition(JsonRpcRequest::is_notification);
+
+                for notification in notifications {
+                    self.handle_notification(notification).await;
+                }
+
+                // Only respond if batch contained at least one non-notification
+                if requests.is_empty() {
+                    return None;
+                }
+
+                let pending: Vec<_> = requests
+                    .into_iter()
+                    .map(|request| {
+                        tracing::debug!("Processing batch request: {}", request.method);
+                        self.handle_cancellable_request(request)
+                    })
+                    .collect();
+
+                // Batch entries run concurrently; join_all keeps responses in request order
+                let responses: Vec<_> = futures::future::join_all(pending)
+                    .await
+                    .into_iter()
+                    .flatten()
+                    .collect();
+
+                if responses.is_empty() {
+                    None
+                } else {
+                    serde_json::to_value(&responses).ok()
+                }
+            }
+            JsonRpcMessage::Response(response) => {
+                self.handle_client_response(response);
+                None
+            }
+            JsonRpcMessage::Invalid(id) => {
+                tracing::debug!("Received a message that is neither a request nor a response");
+                let error_response = JsonRpcResponse {
+                    jsonrpc: "2.0".to_string(),
+                    id,
+                    result: None,
+                    error: Some(JsonRpcError {
+                        code: -32600,
+                        message: "Invalid Request: not a JSON-RPC request or response".to_string(),
+                        data: None,
+                    }),
+                };
+                serde_json::to_value(&error_response).ok()
+            }
+            JsonRpcMessage::Request(request) => {
+                if request.is_notification() {
+                    self.handle_notification(request).await;
+                    return None;
+                }
+
+                tracing::debug!("Received request: {}", request.method);
+                let response = self.handle_cancellable_request(request).await?;
+                serde_json::to_value(&response).ok()
+            }
+        }
+    }
+
+    async fn handle_notification(&self, notification: JsonRpcRequest) {
+        match notification.method.as_str() {
+            "notifications/cancelled" => self.handle_cancelled(notification).await,
+            "notifications/roots/list_changed" => {
+                self.client.read().await.notify_groups(&notification).await;
+            }
+            _ => tracing::debug!(
+                "Received notification: {} (no response needed)",
+                notification.method
+            ),
+        }
+    }
+
+    /// Handle a request unless the client cancels it first, in which case no response is sent
+    async fn handle_cancellable_request(&self, request: JsonRpcRequest) -> Option<JsonRpcResponse> {
+        let key = request.id.to_string();
+        let (cancel_tx, cancel_rx) = oneshot::channel();
+        if let Ok(mut in_progress) = self.in_progress.lock() {
+            in_progress.insert(
+                key.clone(),
+                InProgressRequest {
+                    cancel: cancel_tx,
+                    upstream: Vec::new(),
+                },
+            );
+        }
+        let _guard = InProgressGuard {
+            in_progress: self.in_progress.clone(),
+            key,
+        };
+
+        tokio::select! {
+            response = self.handle_request(request) => Some(response),
+            Ok(()) = cancel_rx => None,
+        }
+    }
+
+    /// Remember which upstream request serves a downstream one, so it can be cancelled too
+    fn track_upstream_request(
+        &self,
+        request_id: &serde_json::Value,
+        group: &str,
+        upstream_id: serde_json::Value,
+    ) {
+        if let Ok(mut in_progress) = self.in_progress.lock() {
+            if let Some(request) = in_progress.get_mut(&request_id.to_string()) {
+                request.upstream.push((group.to_string(), upstream_id));
+            }
+        }
+    }
+
+    /// Drive a proxied call while handing the progress `group` reports for it under
+    /// `upstream_token` to `on_progress`.
+    ///
+    /// Notifications still queued when the call completes are handed over first, so the
+    /// client never sees progress after the response.
+    async fn relay_progress<F: std::future::Future>(
+        &self,
+        mut notifications: broadcast::Receiver<UpstreamNotification>,
+        group: &str,
+        upstream_token: &serde_json::Value,
+        on_progress: impl Fn(JsonRpcRequest),
+        call: F,
+    ) -> F::Output {
+        let forward = |upstream: UpstreamNotification| {
+            if upstream.is_progress_for(group, upstream_token) {
+                on_progress(upstream.notification);
+            }
+        };
+
+        tokio::pin!(call);
+        let output = loop {
+            tokio::select! {
+                output = &mut call => break output,
+                received = notifications.recv() => match received {
+                    Ok(upstream) => forward(upstream),
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        tracing::warn!("Dropped {} upstream notifications", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break (&mut call).await,
+                },
+            }
+        };
+
+        while let Ok(upstream) = notifications.try_recv() {
+            forward(upstream);
+        }
+        output
+    }
+
+    async fn handle_cancelled(&self, notification: JsonRpcRequest) {
+        let params = notification.params.unwrap_or(json!({}));
+        let Some(request_id) = params.get("requestId") else {
+            tracing::debug!("Ignoring cancellation without requestId");
+            return;
+        };
+        let reason = params.get("reason").and_then(|r| r.as_str());
+
+        let request = self
+            .in_progress
+            .lock()
+            .ok()
+            .and_then(|mut in_progress| in_progress.remove(&request_id.to_string()));
+        let Some(request) = request else {
+            tracing::debug!(
+                "Ignoring cancellation of unknown or finished request {}",
+                request_id
+            );
+            return;
+        };
+
+        // Abort locally first so the handler releases its client lock before we take one
+        tracing::debug!("Client cancelled request {}", request_id);
+        let _ = request.cancel.send(());
+
+        if !request.upstream.is_empty() {
+            let client = self.client.read().await;
+            for (group, upstream_id) in request.upstream {
+                if let Err(e) = client.cancel_request(&group, upstream_id, reason).await {
+                    tracing::warn!("Failed to forward cancellation to {}: {}", group, e);
+                }
+            }
+        }
+    }
+
+    /// Offer this session to answer upstream sampling, elicitation and roots requests.
+    ///
+    /// Sessions without an outbound channel cannot send requests and are not registered.
+    async fn register_downstream_peer(&self, capabilities: &serde_json::Value) {
+        if self.outbound.is_none() {
+            return;
+        }
+
+        let (id, mut requests) = {
+            let client = self.client.read().await;
+            if let Some(previous) = self.downstream_peer.lock().ok().and_then(|mut p| p.take()) {
+                client.unregister_downstream_peer(previous);
+            }
+            client.register_downstream_peer(capabilities)
+        };
+        if let Ok(mut peer) = self.downstream_peer.lock() {
+            *peer = Some(id);
+        }
+
+        // Ends once the session unregisters and the client drops the sender
+        let server = self.clone();
+        tokio::spawn(async move {
+            while let Some(request) = requests.recv().await {
+                server.relay_upstream_request(request);
+            }
+        });
+    }
+
+    /// Mark an upstream call as this session's, so upstream requests made while serving it
+    /// are relayed here
+    fn tag_upstream_call(
+        &self,
+        client: &ModularMcpClient,
+        group: &str,
+        upstream_id: &str,
+    ) -> Option<UpstreamCallTag> {
+        let peer = (*self.downstream_peer.lock().ok()?)?;
+        Some(client.tag_upstream_call(peer, group, upstream_id))
+    }
+
+    /// Send an upstream request to the client under an id of our own, so ids from
+    /// different groups cannot collide
+    fn relay_upstream_request(&self, upstream: UpstreamRequest) {
+        let UpstreamRequest {
+            group,
+            mut request,
+            responder,
+        } = upstream;
+        let id = format!("{}-{}", group, uuid::Uuid::new_v4());
+        tracing::debug!("Relaying {} from {} to the client", request.method, group);
+
+        if let Ok(mut relayed) = self.relayed_requests.lock() {
+            relayed.insert(
+                json!(id).to_string(),
+                RelayedRequest {
+                    upstream_id: std::mem::replace(&mut request.id, json!(id)),
+                    responder,
+                },
+            );
+        }
+        self.send_to_client(json!(request));
+    }
+
+    /// Route the client's answer back to the upstream server that asked
+    fn handle_client_response(&self, mut response: JsonRpcResponse) {
+        let relayed = self
+            .relayed_requests
+            .lock()
+            .ok()
+            .and_then(|mut relayed| relayed.remove(&response.id.to_string()));
+
+        match relayed {
+            Some(RelayedRequest {
+                upstream_id,
+                responder,
+            }) => {
+                response.id = upstream_id;
+                let _ = responder.send(response);
+            }
+            None => tracing::debug!("Ignoring response to unknown request: {}", response.id),
+        }
+    }
+
+    /// Release everything the session holds on the shared client: resource subscriptions
+    /// and its registration for upstream requests. Pending upstream requests are answered
+    /// with "Method not found".
+    pub async fn end_session(&self) {
+        self.release_subscriptions().await;
+
+        if let Some(id) = self.downstream_peer.lock().ok().and_then(|mut p| p.take()) {
+            self.client.read().await.unregister_downstream_peer(id);
+        }
+        if let Ok(mut relayed) = self.relayed_requests.lock() {
+            relayed.clear();
         }
     }
 
@@ -38,6 +563,7 @@ impl ModularMcpServer {
             "resources/unsubscribe" => self.handle_resources_unsubscribe(request).await,
             "prompts/list" => self.handle_prompts_list(request).await,
             "prompts/get" => self.handle_prompts_get(request).await,
+            "completion/complete" => self.handle_completion_complete(request).await,
             _ => JsonRpcResponse {
                 jsonrpc: "2.0".to_string(),
                 id: request.id,
@@ -52,18 +578,44 @@ impl ModularMcpServer {
     }
 
     async fn handle_initialize(&self, request: JsonRpcRequest) -> JsonRpcResponse {
+        let capabilities = request
+            .params
+            .as_ref()
+            .and_then(|p| p.get("capabilities"))
+            .cloned()
+            .unwrap_or_else(|| json!({}));
+        self.register_downstream_peer(&capabilities).await;
+
+        let protocol_version = negotiate_protocol_version(
+            request
+                .params
+                .as_ref()
+                .and_then(|p| p.get("protocolVersion"))
+                .and_then(|v| v.as_str()),
+        );
+        if let Ok(mut version) = self.protocol_version.write() {
+            *version = protocol_version;
+        }
+
+        let mut server_capabilities = json!({
+            "tools": {
+                "listChanged": true
+            },
+            "resources": {
+                "subscribe": true
+            },
+            "prompts": {}
+        });
+        if self.client.read().await.supports_completions() {
+            server_capabilities["completions"] = json!({});
+        }
+
         JsonRpcResponse {
             jsonrpc: "2.0".to_string(),
             id: request.id,
             result: Some(json!({
-                "protocolVersion": "2024-11-05",
-                "capabilities": {
-                    "tools": {},
-                    "resources": {
-                        "subscribe": true
-                    },
-                    "prompts": {}
-                },
+                "protocolVersion": protocol_version,
+                "capabilities": server_capabilities,
                 "serverInfo": {
                     "name": self.name,
                     "version": self.version
@@ -75,14 +627,46 @@ impl ModularMcpServer {
 
     async fn handle_list_tools(&self, request: JsonRpcRequest) -> JsonRpcResponse {
         let client = self.client.read().await;
-        let groups = client.list_groups();
+        let all_groups = client.list_groups();
         let failed_groups = client.list_failed_groups();
 
+        // Flat groups are listed tool by tool instead of behind the meta-tools
+        let groups: Vec<_> = all_groups
+            .iter()
+            .filter(|g| client.group_tool_mode(&g.name) == ToolMode::Dynamic)
+            .collect();
+        let flat_only =
+            groups.is_empty() && (client.tool_mode() == ToolMode::Flat || !all_groups.is_empty());
+
         let group_names: Vec<String> = groups.iter().map(|g| g.name.clone()).collect();
+        let compact_groups: Vec<&str> = groups
+            .iter()
+            .filter(|g| {
+                client
+                    .group_config(&g.name)
+                    .is_some_and(|config| config.tool_schemas() == ToolSchemas::Compact)
+            })
+            .map(|g| g.name.as_str())
+            .collect();
 
         let groups_desc = groups
             .iter()
-            .map(|g| format!("- {}: {}", g.name, g.description))
+            .map(|g| {
+                let mut notes = Vec::new();
+                if let Ok(listing) = self.group_tools_listing(&client, &g.name) {
+                    notes.push(format_token_estimate(estimate_tokens(&listing)));
+                }
+                if compact_groups.contains(&g.name.as_str()) {
+                    notes.push(
+                        "lists names only, get schemas with get_dynamic_tool_schema".to_string(),
+                    );
+                }
+                if notes.is_empty() {
+                    format!("- {}: {}", g.name, g.description)
+                } else {
+                    format!("- {}: {} ({})", g.name, g.description, notes.join("; "))
+                }
+            })
             .collect::<Vec<_>>()
             .join("\n");
 
@@ -103,8 +687,15 @@ impl ModularMcpServer {
             on demand instead of overwhelming it with all tool descriptions at once.\n\n\
             Use this tool to retrieve available tools in a specific group, \
             then use call_dynamic_tool to execute them.\n\n\
-            Available groups:\n{}{}",
-            groups_desc, failed_desc
+            {}Available groups:\n{}{}",
+            if client.dynamic_loading() {
+                "The tools of a group you get are also added to your tool list as \
+                <group>__<tool>; remove them with unload_dynamic_tools when no longer needed.\n\n"
+            } else {
+                ""
+            },
+            groups_desc,
+            failed_desc
         );
 
         let call_tool_desc = r#"Execute a tool from a specific MCP group. Proxies the call to the appropriate upstream MCP server.
@@ -117,10 +708,26 @@ Example usage:
   call_dynamic_tool(group="playwright", name="browser_navigate", args={"url": "https://example.com"})
   → Executes the browser_navigate tool from the playwright group with the specified arguments"#;
 
-        JsonRpcResponse {
-            jsonrpc: "2.0".to_string(),
-            id: request.id,
-            result: Some(json!({
+        let search_tools_desc = r#"Search the tools of all MCP groups by what they do. Returns the best-matching tools with their group, name and a short description, best match first.
+
+Use this instead of get_dynamic_tools when you do not know which group has the tool you need. Set include_schemas to also get the input schemas of the top results, so they can be passed to call_dynamic_tool right away.
+
+Example usage:
+  search_dynamic_tools(query="take a screenshot of a web page", include_schemas=1)"#;
+
+        let call_tools_desc = format!(
+            r#"Execute several independent tools at once, possibly from different MCP groups. The calls run concurrently and the results are returned in the order of the calls.
+
+A failing call does not affect the others; its entry carries an error instead of a result. Use call_dynamic_tool instead when a call depends on the result of another.
+
+Example usage:
+  call_dynamic_tools(calls=[{{"group": "filesystem", "name": "read_file", "args": {{"path": "a.txt"}}}}, {{"group": "filesystem", "name": "read_file", "args": {{"path": "b.txt"}}}}])
+
+At most {} calls per batch; calls of a batch to the same group run a few at a time."#,
+            MAX_BATCH_CALLS
+        );
+
+        let mut result = json!({
                 "tools": [
                     {
                         "name": "get_dynamic_tools",
@@ -160,9 +767,144 @@ Example usage:
                             },
                             "required": ["group", "name"]
                         }
+                    },
+                    {
+                        "name": "search_dynamic_tools",
+                        "description": search_tools_desc,
+                        "inputSchema": {
+                            "type": "object",
+                            "properties": {
+                                "query": {
+                                    "type": "string",
+                                    "description": "Words describing the tool to find"
+                                },
+                                "limit": {
+                                    "type": "integer",
+                                    "description": "Maximum number of tools to return",
+                                    "minimum": 1,
+                                    "maximum": MAX_SEARCH_LIMIT,
+                                    "default": DEFAULT_SEARCH_LIMIT
+                                },
+                                "include_schemas": {
+                                    "type": "integer",
+                                    "description": "Include the input schema of this many of the top results",
+                                    "minimum": 0,
+                                    "default": 0
+                                }
+                            },
+                            "required": ["query"]
+                        }
+                    },
+                    {
+                        "name": "call_dynamic_tools",
+                        "description": call_tools_desc,
+                        "inputSchema": {
+                            "type": "object",
+                            "properties": {
+                                "calls": {
+                                    "type": "array",
+                                    "description": "The tool calls to execute",
+                                    "maxItems": MAX_BATCH_CALLS,
+                                    "items": {
+                                        "type": "object",
+                                        "properties": {
+                                            "group": {
+                                                "type": "string",
+                                                "description": "The name of the MCP group containing the tool",
+                                                "enum": group_names
+                                            },
+                                            "name": {
+                                                "type": "string",
+                                                "description": "The name of the tool to execute"
+                                            },
+                                            "args": {
+                                                "type": "object",
+                                                "description": "Arguments to pass to the tool",
+                                                "additionalProperties": true
+                                            }
+                                        },
+                                        "required": ["group", "name"]
+                                    }
+                                }
+                            },
+                            "required": ["calls"]
+                        }
                     }
                 ]
-            })),
+        });
+
+        // Only offered when a group lists its tools without schemas
+        if !compact_groups.is_empty() {
+            let get_schema_desc = format!(
+                "Get the full input schemas of specific tools. Groups {} list only tool names \
+                and descriptions in get_dynamic_tools; fetch the schemas of the tools you want \
+                to call with this tool, then execute them with call_dynamic_tool.",
+                compact_groups.join(", ")
+            );
+            if let Some(tools) = result["tools"].as_array_mut() {
+                tools.insert(
+                    1,
+                    json!({
+                        "name": "get_dynamic_tool_schema",
+                        "description": get_schema_desc,
+                        "inputSchema": {
+                            "type": "object",
+                            "properties": {
+                                "group": {
+                                    "type": "string",
+                                    "description": "The name of the MCP group containing the tools",
+                                    "enum": group_names
+                                },
+                                "names": {
+                                    "type": "array",
+                                    "description": "Names of the tools to get schemas for",
+                                    "items": {"type": "string"}
+                                }
+                            },
+                            "required": ["group", "names"]
+                        }
+                    }),
+                );
+            }
+        }
+
+        if client.dynamic_loading() {
+            if let Some(tools) = result["tools"].as_array_mut() {
+                tools.push(json!({
+                    "name": "unload_dynamic_tools",
+                    "description": "Remove the tools of groups opened with get_dynamic_tools from your tool list. \
+                        Unloads every group unless one is given.",
+                    "inputSchema": {
+                        "type": "object",
+                        "properties": {
+                            "group": {
+                                "type": "string",
+                                "description": "The name of the MCP group to unload",
+                                "enum": group_names
+                            }
+                        }
+                    }
+                }));
+            }
+        }
+
+        // Pinned, loaded and flat tools follow the meta-tools as if they were served by the
+        // proxy itself. With only flat groups the proxy is a plain aggregator.
+        if let Some(tools) = result["tools"].as_array_mut() {
+            if flat_only {
+                tools.clear();
+            }
+            for (name, _, tool) in self.direct_tools(&client) {
+                let mut entry = self.tool_entry(&tool, SchemaCompaction::Off);
+                entry["name"] = json!(name);
+                tools.push(entry);
+            }
+        }
+
+        JsonRpcResponse {
+            jsonrpc: "2.0".to_string(),
+            id: request.id,
+            result: Some(result),
             error: None,
         }
     }
@@ -190,23 +932,11 @@ Example usage:
                 }
 
                 let client = self.client.read().await;
-                match client.list_tools(group.unwrap()) {
-                    Ok(tools) => {
-                        let tools_json: Vec<_> = tools
-                            .iter()
-                            .map(|tool| {
-                                let mut schema = tool.input_schema.clone();
-                                if let Some(obj) = schema.as_object_mut() {
-                                    obj.remove("$schema");
-                                }
-                                json!({
-                                    "name": tool.name,
-                                    "description": tool.description,
-                                    "inputSchema": schema
-                                })
-                            })
-                            .collect();
-
+                match self.group_tools_listing(&client, group.unwrap()) {
+                    Ok(listing) => {
+                        if client.dynamic_loading() {
+                            self.load_group(group.unwrap());
+                        }
                         JsonRpcResponse {
                             jsonrpc: "2.0".to_string(),
                             id: request.id,
@@ -214,7 +944,7 @@ Example usage:
                                 "content": [
                                     {
                                         "type": "text",
-                                        "text": serde_json::to_string_pretty(&tools_json).unwrap_or_else(|_| "[]".to_string())
+                                        "text": listing
                                     }
                                 ]
                             })),
@@ -233,59 +963,612 @@ Example usage:
                     },
                 }
             }
-            "call_dynamic_tool" => {
+            "get_dynamic_tool_schema" => {
                 let group = arguments.get("group").and_then(|v| v.as_str());
-                let name = arguments.get("name").and_then(|v| v.as_str());
-                let args = arguments.get("args").cloned().unwrap_or(json!({}));
-
-                if group.is_none() || name.is_none() {
+                let names = arguments.get("names").and_then(|v| v.as_array());
+                let (Some(group), Some(names)) = (group, names) else {
                     return JsonRpcResponse {
                         jsonrpc: "2.0".to_string(),
                         id: request.id,
                         result: None,
                         error: Some(JsonRpcError {
                             code: -32602,
-                            message: "Missing required parameters: group and name".to_string(),
+                            message: "Missing required parameters: group and names".to_string(),
                             data: None,
                         }),
                     };
-                }
+                };
 
                 let client = self.client.read().await;
-                match client.call_tool(group.unwrap(), name.unwrap(), args).await {
-                    Ok(result) => JsonRpcResponse {
-                        jsonrpc: "2.0".to_string(),
-                        id: request.id,
-                        result: Some(result),
-                        error: None,
-                    },
-                    Err(e) => JsonRpcResponse {
-                        jsonrpc: "2.0".to_string(),
-                        id: request.id,
-                        result: Some(json!({
-                            "content": [{
-                                "type": "text",
-                                "text": format!("Tool execution failed: {}", e),
-                                "isError": true
-                            }]
-                        })),
-                        error: None,
-                    },
+                let compaction = client
+                    .group_config(group)
+                    .map(|config| config.schema_compaction())
+                    .unwrap_or_default();
+                match client.list_tools(group) {
+                    Ok(tools) => {
+                        let tools_json: Vec<_> = names
+                            .iter()
+                            .filter_map(|name| name.as_str())
+                            .map(|name| match tools.iter().find(|tool| tool.name == name) {
+                                Some(tool) => self.tool_entry(tool, compaction),
+                                None => json!({
+                                    "name": name,
+                                    "error": format!("Tool not found in group {}", group)
+                                }),
+                            })
+                            .collect();
+
+                        JsonRpcResponse {
+                            jsonrpc: "2.0".to_string(),
+                            id: request.id,
+                            result: Some(json!({
+                                "content": [
+                                    {
+                                        "type": "text",
+                                        "text": serialize_listing(&json!(tools_json), compaction)
+                                    }
+                                ]
+                            })),
+                            error: None,
+                        }
+                    }
+                    Err(e) => JsonRpcResponse {
+                        jsonrpc: "2.0".to_string(),
+                        id: request.id,
+                        result: None,
+                        error: Some(JsonRpcError {
+                            code: -32603,
+                            message: format!("Failed to list tools: {}", e),
+                            data: None,
+                        }),
+                    },
                 }
             }
-            _ => JsonRpcResponse {
+            "call_dynamic_tool" => {
+                let group = arguments.get("group").and_then(|v| v.as_str());
+                let name = arguments.get("name").and_then(|v| v.as_str());
+
+                if group.is_none() || name.is_none() {
+                    return JsonRpcResponse {
+                        jsonrpc: "2.0".to_string(),
+                        id: request.id,
+                        result: None,
+                        error: Some(JsonRpcError {
+                            code: -32602,
+                            message: "Missing required parameters: group and name".to_string(),
+                            data: None,
+                        }),
+                    };
+                }
+
+                let (group, name) = (group.unwrap(), name.unwrap());
+
+                let (args, repairs) = {
+                    let client = self.client.read().await;
+                    if client.repair_arguments() {
+                        let tool = listed_tool(&client, group, name);
+                        repair_arguments(&arguments, tool.as_ref().map(|tool| &tool.input_schema))
+                    } else {
+                        (
+                            arguments.get("args").cloned().unwrap_or(json!({})),
+                            Vec::new(),
+                        )
+                    }
+                };
+
+                let mut response = self
+                    .call_upstream_tool(request.id, &params, group, name, args)
+                    .await;
+                if !repairs.is_empty() {
+                    if let Some(result) = response.result.as_mut().and_then(|r| r.as_object_mut()) {
+                        if let Some(meta) = result
+                            .entry("_meta")
+                            .or_insert_with(|| json!({}))
+                            .as_object_mut()
+                        {
+                            meta.insert(ARGUMENT_REPAIRS_META_KEY.to_string(), json!(repairs));
+                        }
+                    }
+                }
+                response
+            }
+            "unload_dynamic_tools" => {
+                let group = arguments.get("group").and_then(|v| v.as_str());
+                let unloaded = self.unload_groups(group);
+
+                let text = if unloaded.is_empty() {
+                    "No groups were loaded".to_string()
+                } else {
+                    format!("Unloaded the tools of {}", unloaded.join(", "))
+                };
+                JsonRpcResponse {
+                    jsonrpc: "2.0".to_string(),
+                    id: request.id,
+                    result: Some(json!({
+                        "content": [
+                            {
+                                "type": "text",
+                                "text": text
+                            }
+                        ]
+                    })),
+                    error: None,
+                }
+            }
+            "call_dynamic_tools" => {
+                let Some(calls) = arguments.get("calls").and_then(|v| v.as_array()) else {
+                    return JsonRpcResponse {
+                        jsonrpc: "2.0".to_string(),
+                        id: request.id,
+                        result: None,
+                        error: Some(JsonRpcError {
+                            code: -32602,
+                            message: "Missing required parameter: calls".to_string(),
+                            data: None,
+                        }),
+                    };
+                };
+                if calls.len() > MAX_BATCH_CALLS {
+                    return JsonRpcResponse {
+                        jsonrpc: "2.0".to_string(),
+                        id: request.id,
+                        result: None,
+                        error: Some(JsonRpcError {
+                            code: -32602,
+                            message: format!(
+                                "Too many calls: {} (at most {} per batch)",
+                                calls.len(),
+                                MAX_BATCH_CALLS
+                            ),
+                            data: None,
+                        }),
+                    };
+                }
+
+                let progress_token = params
+                    .get("_meta")
+                    .and_then(|meta| meta.get("progressToken"));
+                let results = self
+                    .call_tools_batch(&request.id, calls, progress_token)
+                    .await;
+
+                JsonRpcResponse {
+                    jsonrpc: "2.0".to_string(),
+                    id: request.id,
+                    result: Some(json!({
+                        "content": [
+                            {
+                                "type": "text",
+                                "text": serde_json::to_string_pretty(&results).unwrap_or_else(|_| "[]".to_string())
+                            }
+                        ]
+                    })),
+                    error: None,
+                }
+            }
+            "search_dynamic_tools" => {
+                let Some(query) = arguments.get("query").and_then(|v| v.as_str()) else {
+                    return JsonRpcResponse {
+                        jsonrpc: "2.0".to_string(),
+                        id: request.id,
+                        result: None,
+                        error: Some(JsonRpcError {
+                            code: -32602,
+                            message: "Missing required parameter: query".to_string(),
+                            data: None,
+                        }),
+                    };
+                };
+                let limit = arguments
+                    .get("limit")
+                    .and_then(|v| v.as_u64())
+                    .map_or(DEFAULT_SEARCH_LIMIT, |limit| {
+                        (limit as usize).clamp(1, MAX_SEARCH_LIMIT)
+                    });
+                let include_schemas = arguments
+                    .get("include_schemas")
+                    .and_then(|v| v.as_u64())
+                    .unwrap_or(0) as usize;
+
+                let client = self.client.read().await;
+                let matches: Vec<_> = client
+                    .search_tools(query, limit)
+                    .iter()
+                    .enumerate()
+                    .map(|(rank, found)| {
+                        let mut match_json = json!({
+                            "group": found.group,
+                            "name": found.tool.name,
+                            "description": found
+                                .tool
+                                .description
+                                .as_deref()
+                                .map(short_description)
+                        });
+                        if rank < include_schemas {
+                            let compaction = client
+                                .group_config(&found.group)
+                                .map(|config| config.schema_compaction())
+                                .unwrap_or_default();
+                            match_json["inputSchema"] =
+                                minify_schema(&found.tool.input_schema, compaction);
+                        }
+                        match_json
+                    })
+                    .collect();
+
+                JsonRpcResponse {
+                    jsonrpc: "2.0".to_string(),
+                    id: request.id,
+                    result: Some(json!({
+                        "content": [
+                            {
+                                "type": "text",
+                                "text": serde_json::to_string_pretty(&matches).unwrap_or_else(|_| "[]".to_string())
+                            }
+                        ]
+                    })),
+                    error: None,
+                }
+            }
+            _ => {
+                let pinned = {
+                    let client = self.client.read().await;
+                    self.direct_tools(&client)
+                        .into_iter()
+                        .find(|(name, _, _)| name == tool_name)
+                };
+                match pinned {
+                    Some((_, group, tool)) => {
+                        self.call_upstream_tool(request.id, &params, &group, &tool.name, arguments)
+                            .await
+                    }
+                    None => JsonRpcResponse {
+                        jsonrpc: "2.0".to_string(),
+                        id: request.id,
+                        result: None,
+                        error: Some(JsonRpcError {
+                            code: -32601,
+                            message: format!("Unknown tool: {}", tool_name),
+                            data: None,
+                        }),
+                    },
+                }
+            }
+        }
+    }
+
+    /// Forward one tool call upstream, relaying progress when the client asked for it
+    async fn call_upstream_tool(
+        &self,
+        request_id: serde_json::Value,
+        params: &serde_json::Value,
+        group: &str,
+        name: &str,
+        args: serde_json::Value,
+    ) -> JsonRpcResponse {
+        let client = self.client.read().await;
+        if let Some(problem) = invalid_tool_call(&client, group, name, &args) {
+            return JsonRpcResponse {
                 jsonrpc: "2.0".to_string(),
-                id: request.id,
-                result: None,
-                error: Some(JsonRpcError {
-                    code: -32601,
-                    message: format!("Unknown tool: {}", tool_name),
-                    data: None,
+                id: request_id,
+                result: Some(json!({
+                    "content": [{"type": "text", "text": problem}],
+                    "isError": true
+                })),
+                error: None,
+            };
+        }
+
+        let upstream_id = uuid::Uuid::new_v4().to_string();
+        let progress_token = params
+            .get("_meta")
+            .and_then(|meta| meta.get("progressToken"))
+            .cloned();
+        self.track_upstream_request(&request_id, group, json!(upstream_id));
+        let _tag = self.tag_upstream_call(&client, group, &upstream_id);
+
+        let result = match progress_token {
+            Some(progress_token) => {
+                // The upstream id doubles as the upstream progress token, which keeps
+                // tokens unique across sessions sharing the same upstream server
+                let upstream_token = json!(upstream_id);
+                let notifications = client.subscribe_upstream_notifications();
+                let call =
+                    client.call_tool(group, name, args, upstream_id, Some(upstream_token.clone()));
+                let forward = |mut notification: JsonRpcRequest| {
+                    if let Some(params) = notification.params.as_mut() {
+                        params["progressToken"] = progress_token.clone();
+                    }
+                    self.send_to_client(json!(notification));
+                };
+                self.relay_progress(notifications, group, &upstream_token, forward, call)
+                    .await
+            }
+            None => client.call_tool(group, name, args, upstream_id, None).await,
+        };
+        let result = hint_unknown_tool(&client, group, name, result);
+
+        match result {
+            Ok(result) => JsonRpcResponse {
+                jsonrpc: "2.0".to_string(),
+                id: request_id,
+                result: Some(if self.supports_structured_output() {
+                    result
+                } else {
+                    downgrade_tool_result(result)
                 }),
+                error: None,
+            },
+            Err(e) => JsonRpcResponse {
+                jsonrpc: "2.0".to_string(),
+                id: request_id,
+                result: Some(json!({
+                    "content": [{
+                        "type": "text",
+                        "text": format!("Tool execution failed: {}", e)
+                    }],
+                    "isError": true
+                })),
+                error: None,
             },
         }
     }
 
+    /// The text `get_dynamic_tools` returns for a group, shaped by the group's
+    /// `tool_schemas` and `schema_compaction` settings
+    fn group_tools_listing(&self, client: &ModularMcpClient, group: &str) -> Result<String> {
+        let tools = client.list_tools(group)?;
+        let config = client.group_config(group);
+        let compact = config.is_some_and(|config| config.tool_schemas() == ToolSchemas::Compact);
+        let compaction = config
+            .map(|config| config.schema_compaction())
+            .unwrap_or_default();
+
+        let tools_json: Vec<_> = tools
+            .iter()
+            .map(|tool| {
+                if compact {
+                    json!({
+                        "name": tool.name,
+                        "description": tool.description.as_deref().map(short_description)
+                    })
+                } else {
+                    self.tool_entry(tool, compaction)
+                }
+            })
+            .collect();
+
+        Ok(serialize_listing(&json!(tools_json), compaction))
+    }
+
+    /// Tools listed in `tools/list`, as their namespaced name, group name and tool: the
+    /// `pinned_tools` of every connected group in configured order, then the remaining tools
+    /// of flat groups and of the groups loaded in this session. Pinned names the group does
+    /// not offer are left out.
+    fn direct_tools(&self, client: &ModularMcpClient) -> Vec<(String, String, ToolInfo)> {
+        let mut groups = client.list_groups();
+        groups.sort_by(|a, b| a.name.cmp(&b.name));
+        let loaded = if client.dynamic_loading() {
+            self.loaded_groups
+                .lock()
+                .map(|loaded| loaded.clone())
+                .unwrap_or_default()
+        } else {
+            HashSet::new()
+        };
+
+        let mut direct = Vec::new();
+        for group in groups {
+            let Some(config) = client.group_config(&group.name) else {
+                continue;
+            };
+            let pinned = config.pinned_tools();
+            let is_loaded = loaded.contains(&group.name)
+                || client.group_tool_mode(&group.name) == ToolMode::Flat;
+            if pinned.is_empty() && !is_loaded {
+                continue;
+            }
+            let tools = client.list_tools(&group.name).unwrap_or_default();
+            for name in pinned {
+                if let Some(tool) = tools.iter().find(|tool| &tool.name == name) {
+                    direct.push((group.name.clone(), tool.clone()));
+                }
+            }
+            if is_loaded {
+                for tool in tools.iter().filter(|tool| !pinned.contains(&tool.name)) {
+                    direct.push((group.name.clone(), tool.clone()));
+                }
+            }
+        }
+        namespace_tools(direct)
+    }
+
+    /// Mark `group` as loaded in this session, telling the client its tool list changed
+    fn load_group(&self, group: &str) {
+        let added = self
+            .loaded_groups
+            .lock()
+            .map(|mut loaded| loaded.insert(group.to_string()))
+            .unwrap_or(false);
+        if added {
+            self.notify_tools_list_changed();
+        }
+    }
+
+    /// Unload `group`, or every loaded group, returning the groups that were loaded
+    fn unload_groups(&self, group: Option<&str>) -> Vec<String> {
+        let mut unloaded: Vec<String> = match self.loaded_groups.lock() {
+            Ok(mut loaded) => match group {
+                Some(group) => loaded.take(group).into_iter().collect(),
+                None => loaded.drain().collect(),
+            },
+            Err(_) => Vec::new(),
+        };
+        unloaded.sort();
+        if !unloaded.is_empty() {
+            self.notify_tools_list_changed();
+        }
+        unloaded
+    }
+
+    fn notify_tools_list_changed(&self) {
+        self.send_to_client(json!({
+            "jsonrpc": "2.0",
+            "method": "notifications/tools/list_changed"
+        }));
+    }
+
+    /// A tool as listed by `get_dynamic_tools`, with its full schemas
+    fn tool_entry(&self, tool: &ToolInfo, compaction: SchemaCompaction) -> serde_json::Value {
+        let mut tool_json = json!({
+            "name": tool.name,
+            "description": tool.description.as_deref().map(|d| minify_description(d, compaction)),
+            "inputSchema": minify_schema(&tool.input_schema, compaction)
+        });
+        if self.supports_structured_output() {
+            if let Some(title) = &tool.title {
+                tool_json["title"] = json!(title);
+            }
+            if let Some(output_schema) = &tool.output_schema {
+                tool_json["outputSchema"] = minify_schema(output_schema, compaction);
+            }
+        }
+        tool_json
+    }
+
+    /// Run the `{group, name, args}` calls of a `call_dynamic_tools` batch concurrently,
+    /// at most the group's `max_concurrent_batch_calls` at a time per group. Returns one
+    /// entry per call, in call order, holding either the tool result or the error.
+    ///
+    /// With a `progress_token`, every call gets its own upstream progress token, and the
+    /// client is told how many calls have finished as they do.
+    async fn call_tools_batch(
+        &self,
+        request_id: &serde_json::Value,
+        calls: &[serde_json::Value],
+        progress_token: Option<&serde_json::Value>,
+    ) -> Vec<serde_json::Value> {
+        let client = self.client.read().await;
+        let structured = self.supports_structured_output();
+
+        let mut group_slots: HashMap<&str, Arc<Semaphore>> = HashMap::new();
+        for call in calls {
+            if let Some(group) = call.get("group").and_then(|g| g.as_str()) {
+                group_slots.entry(group).or_insert_with(|| {
+                    let slots = client
+                        .group_config(group)
+                        .map_or(1, |config| config.max_concurrent_batch_calls());
+                    Arc::new(Semaphore::new(slots))
+                });
+            }
+        }
+
+        let client = &client;
+        let group_slots = &group_slots;
+        // How far along each call is, from 0 to 1; the batch's progress is their sum
+        let done = &std::sync::Mutex::new((vec![0.0_f64; calls.len()], 0.0));
+        let report = |index: usize, fraction: f64, message: Option<&serde_json::Value>| {
+            let Some(progress_token) = progress_token else {
+                return;
+            };
+            let Ok(mut done) = done.lock() else {
+                return;
+            };
+            let (fractions, reported) = &mut *done;
+            fractions[index] = fractions[index].max(fraction.min(1.0));
+            let progress: f64 = fractions.iter().sum();
+            // Progress must increase with every notification
+            if progress <= *reported {
+                return;
+            }
+            *reported = progress;
+
+            let mut params = json!({
+                "progressToken": progress_token,
+                "progress": progress,
+                "total": calls.len()
+            });
+            if let Some(message) = message {
+                params["message"] = message.clone();
+            }
+            self.send_to_client(json!({
+                "jsonrpc": "2.0",
+                "method": "notifications/progress",
+                "params": params
+            }));
+        };
+        let report = &report;
+        futures::future::join_all(calls.iter().enumerate().map(|(index, call)| async move {
+            let entry = async {
+                let group = call.get("group").and_then(|g| g.as_str());
+                let name = call.get("name").and_then(|n| n.as_str());
+                let (Some(group), Some(name)) = (group, name) else {
+                    return json!({
+                        "group": group,
+                        "name": name,
+                        "error": "Missing required parameters: group and name"
+                    });
+                };
+                let args = call.get("args").cloned().unwrap_or(json!({}));
+                if let Some(problem) = invalid_tool_call(client, group, name, &args) {
+                    return json!({"group": group, "name": name, "error": problem});
+                }
+
+                // The semaphore lives as long as the batch and is never closed
+                let _slot = group_slots[group].acquire().await;
+                let upstream_id = uuid::Uuid::new_v4().to_string();
+                self.track_upstream_request(request_id, group, json!(upstream_id));
+                let _tag = self.tag_upstream_call(client, group, &upstream_id);
+
+                // As for single calls, the upstream id doubles as the upstream progress token.
+                // The call's own progress counts towards the batch as its share done.
+                let result = match progress_token {
+                    Some(_) => {
+                        let upstream_token = json!(upstream_id);
+                        let notifications = client.subscribe_upstream_notifications();
+                        let call = client.call_tool(
+                            group,
+                            name,
+                            args,
+                            upstream_id,
+                            Some(upstream_token.clone()),
+                        );
+                        let forward = |notification: JsonRpcRequest| {
+                            let params = notification.params.unwrap_or_default();
+                            let progress = params.get("progress").and_then(|p| p.as_f64());
+                            let total = params.get("total").and_then(|t| t.as_f64());
+                            if let (Some(progress), Some(total)) = (progress, total) {
+                                if total > 0.0 {
+                                    report(index, progress / total, params.get("message"));
+                                }
+                            }
+                        };
+                        self.relay_progress(notifications, group, &upstream_token, forward, call)
+                            .await
+                    }
+                    None => client.call_tool(group, name, args, upstream_id, None).await,
+                };
+                match hint_unknown_tool(client, group, name, result) {
+                    Ok(result) => json!({
+                        "group": group,
+                        "name": name,
+                        "result": if structured { result } else { downgrade_tool_result(result) }
+                    }),
+                    Err(e) => json!({
+                        "group": group,
+                        "name": name,
+                        "error": format!("Tool execution failed: {}", e)
+                    }),
+                }
+            }
+            .await;
+            report(index, 1.0, None);
+            entry
+        }))
+        .await
+    }
+
     async fn handle_resources_list(&self, request: JsonRpcRequest) -> JsonRpcResponse {
         let client = self.client.read().await;
 
@@ -323,27 +1606,8 @@ Example usage:
                 },
             },
             None => {
-                let groups = client.list_groups();
-                let mut all_resources = Vec::new();
-
-                for group in groups {
-                    if let Ok(result) = client.proxy_resources_list(&group.name, None).await {
-                        if let Some(resources_array) =
-                            result.get("resources").and_then(|v| v.as_array())
-                        {
-                            all_resources.extend(resources_array.iter().cloned());
-                        }
-                    }
-                }
-
-                JsonRpcResponse {
-                    jsonrpc: "2.0".to_string(),
-                    id: request.id,
-                    result: Some(json!({
-                        "resources": all_resources
-                    })),
-                    error: None,
-                }
+                self.aggregated_list(&client, AggregatedList::Resources, request.id, cursor)
+                    .await
             }
         }
     }
@@ -381,30 +1645,8 @@ Example usage:
             }
         };
 
-        // Find which group has this resource
-        let groups = client.list_groups();
-        let mut found_group: Option<String> = None;
-
-        for group in groups {
-            if let Ok(result) = client.proxy_resources_list(&group.name, None).await {
-                if let Some(resources_array) = result.get("resources").and_then(|v| v.as_array()) {
-                    for resource in resources_array {
-                        if let Some(resource_uri) = resource.get("uri").and_then(|u| u.as_str()) {
-                            if resource_uri == uri {
-                                found_group = Some(group.name.clone());
-                                break;
-                            }
-                        }
-                    }
-                }
-                if found_group.is_some() {
-                    break;
-                }
-            }
-        }
-
-        let group_name = match found_group {
-            Some(g) => g,
+        let (group_name, upstream_uri) = match self.resolve_resource(&client, &uri).await {
+            Some(found) => found,
             None => {
                 return JsonRpcResponse {
                     jsonrpc: "2.0".to_string(),
@@ -419,14 +1661,29 @@ Example usage:
             }
         };
 
-        match client.proxy_resources_read(&group_name, uri).await {
-            Ok(result) => JsonRpcResponse {
-                jsonrpc: "2.0".to_string(),
-                id: request.id,
-                result: Some(result),
-                error: None,
-            },
-            Err(e) => JsonRpcResponse {
+        let namespaced = upstream_uri != uri;
+        match client.proxy_resources_read(&group_name, upstream_uri).await {
+            Ok(mut result) => {
+                if namespaced {
+                    // Keep the contents addressable the way the client addressed them
+                    if let Some(contents) =
+                        result.get_mut("contents").and_then(|c| c.as_array_mut())
+                    {
+                        for content in contents {
+                            if let Some(content_uri) = content.get("uri").and_then(|u| u.as_str()) {
+                                content["uri"] = json!(namespace_uri(&group_name, content_uri));
+                            }
+                        }
+                    }
+                }
+                JsonRpcResponse {
+                    jsonrpc: "2.0".to_string(),
+                    id: request.id,
+                    result: Some(result),
+                    error: None,
+                }
+            }
+            Err(e) => JsonRpcResponse {
                 jsonrpc: "2.0".to_string(),
                 id: request.id,
                 result: None,
@@ -441,25 +1698,178 @@ Example usage:
 
     async fn handle_resources_templates_list(&self, request: JsonRpcRequest) -> JsonRpcResponse {
         let client = self.client.read().await;
-        let groups = client.list_groups();
-        let mut all_templates = Vec::new();
+        let cursor = request
+            .params
+            .as_ref()
+            .and_then(|p| p.get("cursor"))
+            .and_then(|c| c.as_str())
+            .map(String::from);
 
-        for group in groups {
-            if let Ok(result) = client.proxy_resources_templates_list(&group.name).await {
-                if let Some(templates_array) =
-                    result.get("resourceTemplates").and_then(|v| v.as_array())
-                {
-                    all_templates.extend(templates_array.iter().cloned());
+        self.aggregated_list(
+            &client,
+            AggregatedList::ResourceTemplates,
+            request.id,
+            cursor,
+        )
+        .await
+    }
+
+    /// Group and upstream URI of a resource, from its namespace prefix or by asking the groups
+    async fn resolve_resource(
+        &self,
+        client: &ModularMcpClient,
+        uri: &str,
+    ) -> Option<(String, String)> {
+        if client.namespacing() {
+            let groups = connected_group_names(client);
+            if let Some((group, upstream)) = split_namespaced_uri(uri, &groups) {
+                return Some((group, upstream.to_string()));
+            }
+        }
+        let group = client.find_resource_group(uri).await?;
+        Some((group, uri.to_string()))
+    }
+
+    /// Group and upstream URI template, from its namespace prefix or by asking the groups.
+    /// Either way the template must be in the group's filtered listing.
+    async fn resolve_resource_template(
+        &self,
+        client: &ModularMcpClient,
+        uri_template: &str,
+    ) -> Option<(String, String)> {
+        if client.namespacing() {
+            let groups = connected_group_names(client);
+            if let Some((group, upstream)) = split_namespaced_uri(uri_template, &groups) {
+                return client
+                    .lists_resource_template(&group, upstream)
+                    .await
+                    .then(|| (group, upstream.to_string()));
+            }
+        }
+        let group = client.find_resource_template_group(uri_template).await?;
+        Some((group, uri_template.to_string()))
+    }
+
+    /// Group and upstream name of a prompt, from its namespace prefix or by asking the groups.
+    /// Either way the prompt must be in the group's filtered listing.
+    async fn resolve_prompt(
+        &self,
+        client: &ModularMcpClient,
+        name: &str,
+    ) -> Option<(String, String)> {
+        if client.namespacing() {
+            let groups = connected_group_names(client);
+            if let Some((group, upstream)) = split_namespaced_prompt(name, &groups) {
+                return client
+                    .lists_prompt(&group, upstream)
+                    .await
+                    .then(|| (group, upstream.to_string()));
+            }
+        }
+        let group = client.find_prompt_group(name).await?;
+        Some((group, name.to_string()))
+    }
+
+    /// Combine a list across all connected groups, in group name order.
+    ///
+    /// Pages are fetched group by group until one reports more; the response then carries
+    /// an opaque cursor naming that group and its upstream cursor, so the next request
+    /// resumes there. Groups that fail to list are skipped.
+    async fn aggregated_list(
+        &self,
+        client: &ModularMcpClient,
+        list: AggregatedList,
+        id: serde_json::Value,
+        cursor: Option<String>,
+    ) -> JsonRpcResponse {
+        let mut position = match cursor.as_deref().map(AggregateCursor::decode) {
+            None => None,
+            Some(Some(position)) => Some(position),
+            Some(None) => {
+                return JsonRpcResponse {
+                    jsonrpc: "2.0".to_string(),
+                    id,
+                    result: None,
+                    error: Some(JsonRpcError {
+                        code: -32602,
+                        message: "Invalid cursor".to_string(),
+                        data: None,
+                    }),
+                };
+            }
+        };
+
+        let mut group_names: Vec<String> =
+            client.list_groups().into_iter().map(|g| g.name).collect();
+        group_names.sort();
+
+        let namespacing = client.namespacing();
+        let mut owners: HashMap<String, String> = HashMap::new();
+        let mut items = Vec::new();
+        let mut next_cursor = None;
+        for group_name in group_names {
+            // Groups are visited in name order, so a cursor for a group that went away
+            // resumes with the next one
+            let upstream_cursor = match &mut position {
+                Some(position) if group_name < position.group => continue,
+                Some(position) if group_name == position.group => position.cursor.take(),
+                _ => None,
+            };
+            position = None;
+
+            let Ok(page) = list.fetch(client, &group_name, upstream_cursor).await else {
+                continue;
+            };
+            if let Some(page_items) = page.get(list.key()).and_then(|v| v.as_array()) {
+                for item in page_items {
+                    let mut item = item.clone();
+                    if let Some(id) = item.get(list.id_field()).and_then(|v| v.as_str()) {
+                        let id = id.to_string();
+                        if let Some(other) = owners.insert(id.clone(), group_name.clone()) {
+                            if other != group_name {
+                                tracing::warn!(
+                                    "{} {} is exposed by both {} and {}{}",
+                                    list.id_field(),
+                                    id,
+                                    other,
+                                    group_name,
+                                    if namespacing {
+                                        ""
+                                    } else {
+                                        "; enable namespacing to tell them apart"
+                                    }
+                                );
+                            }
+                        }
+                        if namespacing {
+                            item[list.id_field()] = json!(list.namespace(&group_name, &id));
+                        }
+                    }
+                    items.push(item);
                 }
             }
+
+            if let Some(upstream_next) = page.get("nextCursor").and_then(|c| c.as_str()) {
+                next_cursor = Some(
+                    AggregateCursor {
+                        group: group_name,
+                        cursor: Some(upstream_next.to_string()),
+                    }
+                    .encode(),
+                );
+                break;
+            }
+        }
+
+        let mut result = json!({ list.key(): items });
+        if let Some(next_cursor) = next_cursor {
+            result["nextCursor"] = json!(next_cursor);
         }
 
         JsonRpcResponse {
             jsonrpc: "2.0".to_string(),
-            id: request.id,
-            result: Some(json!({
-                "resourceTemplates": all_templates
-            })),
+            id,
+            result: Some(result),
             error: None,
         }
     }
@@ -486,8 +1896,24 @@ Example usage:
             }
         };
 
+        // Hold the set while subscribing upstream so a concurrent unsubscribe waits its turn
         let mut subs = self.subscriptions.write().await;
-        subs.insert(uri.clone());
+        if subs.insert(uri.clone()) {
+            let client = self.client.read().await;
+            if let Err(e) = client.subscribe_resource(upstream_uri(&client, &uri)).await {
+                subs.remove(&uri);
+                return JsonRpcResponse {
+                    jsonrpc: "2.0".to_string(),
+                    id: request.id,
+                    result: None,
+                    error: Some(JsonRpcError {
+                        code: -32603,
+                        message: format!("Failed to subscribe to resource: {}", e),
+                        data: None,
+                    }),
+                };
+            }
+        }
         tracing::debug!("Client subscribed to resource changes for uri: {}", uri);
 
         JsonRpcResponse {
@@ -521,7 +1947,15 @@ Example usage:
         };
 
         let mut subs = self.subscriptions.write().await;
-        subs.remove(&uri);
+        if subs.remove(&uri) {
+            let client = self.client.read().await;
+            if let Err(e) = client
+                .unsubscribe_resource(upstream_uri(&client, &uri))
+                .await
+            {
+                tracing::warn!("Failed to unsubscribe upstream from {}: {}", uri, e);
+            }
+        }
         tracing::debug!("Client unsubscribed from resource changes for uri: {}", uri);
 
         JsonRpcResponse {
@@ -569,27 +2003,8 @@ Example usage:
                 },
             },
             None => {
-                let groups = client.list_groups();
-                let mut all_prompts = Vec::new();
-
-                for group in groups {
-                    if let Ok(result) = client.proxy_prompts_list(&group.name, None).await {
-                        if let Some(prompts_array) =
-                            result.get("prompts").and_then(|v| v.as_array())
-                        {
-                            all_prompts.extend(prompts_array.iter().cloned());
-                        }
-                    }
-                }
-
-                JsonRpcResponse {
-                    jsonrpc: "2.0".to_string(),
-                    id: request.id,
-                    result: Some(json!({
-                        "prompts": all_prompts
-                    })),
-                    error: None,
-                }
+                self.aggregated_list(&client, AggregatedList::Prompts, request.id, cursor)
+                    .await
             }
         }
     }
@@ -635,30 +2050,8 @@ Example usage:
             }
         };
 
-        // Find which group has this prompt
-        let groups = client.list_groups();
-        let mut found_group: Option<String> = None;
-
-        for group in groups {
-            if let Ok(result) = client.proxy_prompts_list(&group.name, None).await {
-                if let Some(prompts_array) = result.get("prompts").and_then(|v| v.as_array()) {
-                    for prompt in prompts_array {
-                        if let Some(prompt_name_str) = prompt.get("name").and_then(|n| n.as_str()) {
-                            if prompt_name_str == prompt_name {
-                                found_group = Some(group.name.clone());
-                                break;
-                            }
-                        }
-                    }
-                }
-                if found_group.is_some() {
-                    break;
-                }
-            }
-        }
-
-        let group_name = match found_group {
-            Some(g) => g,
+        let (group_name, upstream_name) = match self.resolve_prompt(&client, &prompt_name).await {
+            Some(found) => found,
             None => {
                 return JsonRpcResponse {
                     jsonrpc: "2.0".to_string(),
@@ -674,7 +2067,7 @@ Example usage:
         };
 
         match client
-            .proxy_prompts_get(&group_name, prompt_name, arguments)
+            .proxy_prompts_get(&group_name, upstream_name, arguments)
             .await
         {
             Ok(result) => JsonRpcResponse {
@@ -696,12 +2089,96 @@ Example usage:
         }
     }
 
+    /// Forward `completion/complete` to the group that owns the referenced prompt or
+    /// resource template
+    async fn handle_completion_complete(&self, request: JsonRpcRequest) -> JsonRpcResponse {
+        let error = |code: i32, message: String| JsonRpcResponse {
+            jsonrpc: "2.0".to_string(),
+            id: request.id.clone(),
+            result: None,
+            error: Some(JsonRpcError {
+                code,
+                message,
+                data: None,
+            }),
+        };
+
+        let Some(mut params) = request.params.clone() else {
+            return error(-32602, "Missing params object".to_string());
+        };
+        let Some(reference) = params.get("ref") else {
+            return error(-32602, "Missing required parameter: ref".to_string());
+        };
+
+        let client = self.client.read().await;
+        let resolved = match reference.get("type").and_then(|t| t.as_str()) {
+            Some("ref/prompt") => {
+                let Some(name) = reference.get("name").and_then(|n| n.as_str()) else {
+                    return error(-32602, "Missing required parameter: ref.name".to_string());
+                };
+                self.resolve_prompt(&client, name)
+                    .await
+                    .map(|(group, upstream)| (group, "name", upstream))
+                    .ok_or_else(|| format!("Prompt not found: {}", name))
+            }
+            Some("ref/resource") => {
+                let Some(uri) = reference.get("uri").and_then(|u| u.as_str()) else {
+                    return error(-32602, "Missing required parameter: ref.uri".to_string());
+                };
+                self.resolve_resource_template(&client, uri)
+                    .await
+                    .map(|(group, upstream)| (group, "uri", upstream))
+                    .ok_or_else(|| format!("Resource template not found: {}", uri))
+            }
+            other => Err(format!(
+                "Unsupported reference type: {}",
+                other.unwrap_or("none")
+            )),
+        };
+
+        let group = match resolved {
+            Ok((group, field, upstream)) => {
+                params["ref"][field] = json!(upstream);
+                group
+            }
+            Err(message) => return error(-32602, message),
+        };
+
+        match client.proxy_completion_complete(&group, params).await {
+            Ok(result) => JsonRpcResponse {
+                jsonrpc: "2.0".to_string(),
+                id: request.id,
+                result: Some(result),
+                error: None,
+            },
+            Err(e) => error(-32603, format!("Failed to complete argument: {}", e)),
+        }
+    }
+
     #[allow(dead_code)]
     async fn get_active_subscriptions(&self) -> HashSet<String> {
         let subs = self.subscriptions.read().await;
         subs.clone()
     }
 
+    /// Drop this session's resource subscriptions when the client goes away
+    pub async fn release_subscriptions(&self) {
+        let uris: Vec<String> = self.subscriptions.write().await.drain().collect();
+        if uris.is_empty() {
+            return;
+        }
+
+        let client = self.client.read().await;
+        for uri in uris {
+            if let Err(e) = client
+                .unsubscribe_resource(upstream_uri(&client, &uri))
+                .await
+            {
+                tracing::warn!("Failed to unsubscribe upstream from {}: {}", uri, e);
+            }
+        }
+    }
+
     #[allow(dead_code)]
     fn validate_prompt_arguments(
         &self,
@@ -725,126 +2202,530 @@ Example usage:
     }
 
     pub async fn run_stdio(&self) -> Result<()> {
-        use crate::proxy::types::JsonRpcMessage;
-        use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
-
-        let stdin = tokio::io::stdin();
-        let mut stdout = tokio::io::stdout();
-        let mut reader = BufReader::new(stdin);
-        let mut line = String::new();
-
         tracing::info!("MCP server listening on stdio");
+        self.serve_stream(tokio::io::stdin(), tokio::io::stdout())
+            .await
+    }
+
+    /// Serve newline-delimited JSON-RPC over a reader/writer pair.
+    ///
+    /// Every message is handled in its own task, so a slow tool call does not hold up
+    /// other requests. A single writer emits responses (and server-initiated messages)
+    /// in completion order. Returns once the reader is closed and all handlers finished.
+    ///
+    /// Requests are only spawned once they hold one of the session's slots. While every
+    /// slot is busy, up to the concurrent request limit of further requests wait in line
+    /// and the reader pauses after that. Notifications need no slot and keep flowing
+    /// until then, so cancellations reach busy requests.
+    async fn serve_stream<R, W>(&self, reader: R, mut writer: W) -> Result<()>
+    where
+        R: AsyncRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        let (outbound_tx, mut outbound_rx) = mpsc::unbounded_channel();
+        let server = self.clone().with_outbound(outbound_tx.clone());
+        let notifier = server.spawn_notifier();
+        let mut lines = BufReader::new(reader).lines();
+        let mut tasks = JoinSet::new();
+        let mut waiting: VecDeque<JsonRpcMessage> = VecDeque::new();
+        let mut reader_closed = false;
+
+        let spawn = |tasks: &mut JoinSet<()>,
+                     permit: Option<OwnedSemaphorePermit>,
+                     message: JsonRpcMessage| {
+            let server = server.clone();
+            tasks.spawn(async move {
+                let _permit = permit;
+                if let Some(response) = server.handle_admitted_message(message).await {
+                    server.send_to_client(response);
+                }
+            });
+        };
 
         loop {
-            line.clear();
-            let bytes_read = reader.read_line(&mut line).await?;
+            tokio::select! {
+                line = lines.next_line(), if !reader_closed && waiting.len() < self.max_concurrent_requests => match line? {
+                    Some(line) => {
+                        let trimmed = line.trim();
+                        if trimmed.is_empty() {
+                            continue;
+                        }
+
+                        // Try to parse as JsonRpcMessage (handles both single request and batch array)
+                        match serde_json::from_str::<JsonRpcMessage>(trimmed) {
+                            Ok(message) if !needs_slot(&message) => spawn(&mut tasks, None, message),
+                            Ok(message) => {
+                                // Requests already waiting go first
+                                let permit = if waiting.is_empty() {
+                                    server.in_flight.clone().try_acquire_owned().ok()
+                                } else {
+                                    None
+                                };
+                                match permit {
+                                    Some(permit) => spawn(&mut tasks, Some(permit), message),
+                                    None => waiting.push_back(message),
+                                }
+                            }
+                            Err(e) => {
+                                tracing::error!("Failed to parse request: {}. Raw input: {}", e, trimmed);
+                                if let Ok(response) = serde_json::to_value(parse_error_response(&e)) {
+                                    let _ = outbound_tx.send(response);
+                                }
+                            }
+                        }
+                    }
+                    None => reader_closed = true,
+                },
+                Ok(permit) = server.in_flight.clone().acquire_owned(), if !waiting.is_empty() => {
+                    if let Some(message) = waiting.pop_front() {
+                        spawn(&mut tasks, Some(permit), message);
+                    }
+                }
+                Some(message) = outbound_rx.recv() => {
+                    write_message(&mut writer, &message).await?;
+                }
+                Some(result) = tasks.join_next() => {
+                    if let Err(e) = result {
+                        tracing::error!("Request handler failed: {}", e);
+                    }
+                }
+                else => break,
+            }
 
-            if bytes_read == 0 {
+            if reader_closed && waiting.is_empty() && tasks.is_empty() {
+                // Flush whatever the finished handlers queued before shutting down
+                while let Ok(message) = outbound_rx.try_recv() {
+                    write_message(&mut writer, &message).await?;
+                }
                 break;
             }
+        }
 
-            let trimmed = line.trim();
-            if trimmed.is_empty() {
-                continue;
+        if let Some(notifier) = notifier {
+            notifier.abort();
+        }
+        server.end_session().await;
+
+        Ok(())
+    }
+}
+
+/// Whether handling `message` takes one of the session's request slots: requests and
+/// batches with at least one request do, notifications, responses and invalid messages do not
+fn needs_slot(message: &JsonRpcMessage) -> bool {
+    match message {
+        JsonRpcMessage::Request(request) => !request.is_notification(),
+        JsonRpcMessage::Batch(requests) => {
+            requests.iter().any(|request| !request.is_notification())
+        }
+        JsonRpcMessage::Response(_) | JsonRpcMessage::Invalid(_) => false,
+    }
+}
+
+fn connected_group_names(client: &ModularMcpClient) -> Vec<String> {
+    client.list_groups().into_iter().map(|g| g.name).collect()
+}
+
+/// The URI upstream servers know a resource by, without any namespace prefix. The group
+/// need not be connected, so subscriptions can be released after it went away.
+fn upstream_uri<'a>(client: &ModularMcpClient, uri: &'a str) -> &'a str {
+    if client.namespacing() {
+        if let Some((_, upstream)) = uri
+            .strip_prefix(NAMESPACED_URI_PREFIX)
+            .and_then(|rest| rest.split_once('/'))
+        {
+            return upstream;
+        }
+    }
+    uri
+}
+
+/// Lists that are combined across groups when the client names no group
+#[derive(Clone, Copy)]
+enum AggregatedList {
+    Resources,
+    Prompts,
+    ResourceTemplates,
+}
+
+impl AggregatedList {
+    /// Item field identifying an item across groups
+    fn id_field(self) -> &'static str {
+        match self {
+            AggregatedList::Resources => "uri",
+            AggregatedList::Prompts => "name",
+            AggregatedList::ResourceTemplates => "uriTemplate",
+        }
+    }
+
+    fn namespace(self, group: &str, id: &str) -> String {
+        match self {
+            AggregatedList::Prompts => namespace_prompt(group, id),
+            AggregatedList::Resources | AggregatedList::ResourceTemplates => {
+                namespace_uri(group, id)
             }
+        }
+    }
 
-            // Try to parse as JsonRpcMessage (handles both single request and batch array)
-            match serde_json::from_str::<JsonRpcMessage>(trimmed) {
-                Ok(JsonRpcMessage::Batch(requests)) => {
-                    tracing::debug!("Received batch request with {} requests", requests.len());
+    /// Result field holding the items
+    fn key(self) -> &'static str {
+        match self {
+            AggregatedList::Resources => "resources",
+            AggregatedList::Prompts => "prompts",
+            AggregatedList::ResourceTemplates => "resourceTemplates",
+        }
+    }
 
-                    if requests.is_empty() {
-                        // Empty batch is invalid per JSON-RPC spec
-                        let error_response = JsonRpcResponse {
-                            jsonrpc: "2.0".to_string(),
-                            id: serde_json::Value::Null,
-                            result: None,
-                            error: Some(JsonRpcError {
-                                code: -32600,
-                                message: "Invalid Request: batch array cannot be empty".to_string(),
-                                data: None,
-                            }),
-                        };
-                        let response_json = serde_json::to_string(&error_response)?;
-                        stdout.write_all(response_json.as_bytes()).await?;
-                        stdout.write_all(b"\n").await?;
-                        stdout.flush().await?;
-                        continue;
-                    }
+    async fn fetch(
+        self,
+        client: &ModularMcpClient,
+        group_name: &str,
+        cursor: Option<String>,
+    ) -> Result<serde_json::Value> {
+        match self {
+            AggregatedList::Resources => client.proxy_resources_list(group_name, cursor).await,
+            AggregatedList::Prompts => client.proxy_prompts_list(group_name, cursor).await,
+            AggregatedList::ResourceTemplates => {
+                client
+                    .proxy_resources_templates_list(group_name, cursor)
+                    .await
+            }
+        }
+    }
+}
 
-                    // Process all requests in the batch
-                    let mut responses = Vec::new();
-                    let mut has_notifications_only = true;
-
-                    for request in requests {
-                        let is_notification = matches!(request.id, serde_json::Value::Null);
-
-                        if !is_notification {
-                            has_notifications_only = false;
-                            tracing::debug!("Processing batch request: {}", request.method);
-                            let response = self.handle_request(request).await;
-                            responses.push(response);
-                        } else {
-                            tracing::debug!(
-                                "Received notification in batch: {} (no response needed)",
-                                request.method
-                            );
-                        }
-                    }
+fn namespace_uri(group: &str, uri: &str) -> String {
+    format!("{}{}/{}", NAMESPACED_URI_PREFIX, group, uri)
+}
 
-                    // Only send response if batch contained at least one non-notification
-                    if !has_notifications_only {
-                        let response_json = serde_json::to_string(&responses)?;
-                        stdout.write_all(response_json.as_bytes()).await?;
-                        stdout.write_all(b"\n").await?;
-                        stdout.flush().await?;
-                    }
-                }
-                Ok(JsonRpcMessage::Request(request)) => {
-                    let is_notification = matches!(request.id, serde_json::Value::Null);
+fn namespace_prompt(group: &str, name: &str) -> String {
+    format!("{}{}{}", group, NAMESPACED_PROMPT_SEPARATOR, name)
+}
 
-                    if is_notification {
-                        tracing::debug!(
-                            "Received notification: {} (no response needed)",
-                            request.method
-                        );
-                        continue;
-                    }
+/// Split `dmcp://<group>/<uri>` into a connected group and the upstream URI
+fn split_namespaced_uri<'a>(uri: &'a str, groups: &[String]) -> Option<(String, &'a str)> {
+    let (group, upstream) = uri.strip_prefix(NAMESPACED_URI_PREFIX)?.split_once('/')?;
+    groups
+        .iter()
+        .find(|g| *g == group)
+        .map(|g| (g.clone(), upstream))
+}
 
-                    tracing::debug!("Received request: {}", request.method);
-                    let response = self.handle_request(request).await;
-                    let response_json = serde_json::to_string(&response)?;
-                    stdout.write_all(response_json.as_bytes()).await?;
-                    stdout.write_all(b"\n").await?;
-                    stdout.flush().await?;
-                }
-                Err(e) => {
-                    tracing::error!("Failed to parse request: {}. Raw input: {}", e, trimmed);
-                    let error_response = JsonRpcResponse {
-                        jsonrpc: "2.0".to_string(),
-                        id: serde_json::Value::Null,
-                        result: None,
-                        error: Some(JsonRpcError {
-                            code: -32700,
-                            message: format!("Parse error: {}", e),
-                            data: None,
-                        }),
-                    };
-                    let response_json = serde_json::to_string(&error_response)?;
-                    stdout.write_all(response_json.as_bytes()).await?;
-                    stdout.write_all(b"\n").await?;
-                    stdout.flush().await?;
-                }
+/// Split `<group>__<prompt>` into a connected group and the upstream prompt name. Group names
+/// may contain the separator themselves, so the longest matching group wins.
+fn split_namespaced_prompt<'a>(name: &'a str, groups: &[String]) -> Option<(String, &'a str)> {
+    groups
+        .iter()
+        .filter_map(|group| {
+            let upstream = name
+                .strip_prefix(group.as_str())?
+                .strip_prefix(NAMESPACED_PROMPT_SEPARATOR)?;
+            Some((group.clone(), upstream))
+        })
+        .max_by_key(|(group, _)| group.len())
+}
+
+/// Position in an aggregated list: the group to resume with and its upstream cursor
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct AggregateCursor {
+    group: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cursor: Option<String>,
+}
+
+impl AggregateCursor {
+    /// Hex-encoded JSON, so clients treat it as opaque
+    fn encode(&self) -> String {
+        serde_json::to_vec(self)
+            .unwrap_or_default()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+
+    fn decode(cursor: &str) -> Option<Self> {
+        if cursor.len() % 2 != 0 || !cursor.is_ascii() {
+            return None;
+        }
+        let bytes = (0..cursor.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&cursor[i..i + 2], 16).ok())
+            .collect::<Option<Vec<u8>>>()?;
+        serde_json::from_slice(&bytes).ok()
+    }
+}
+
+/// Tool listings are pretty-printed unless the group asks for compaction
+fn serialize_listing(listing: &serde_json::Value, compaction: SchemaCompaction) -> String {
+    let text = if compaction == SchemaCompaction::Off {
+        serde_json::to_string_pretty(listing)
+    } else {
+        serde_json::to_string(listing)
+    };
+    text.unwrap_or_else(|_| "[]".to_string())
+}
+
+/// The tool `group` listed that a call to `name` is for. Aliases from `tool_overrides` are
+/// called by a name the group does not list.
+fn listed_tool(client: &ModularMcpClient, group: &str, name: &str) -> Option<ToolInfo> {
+    let listed_name = client
+        .group_config(group)
+        .and_then(|config| config.listed_tool_name(name))
+        .unwrap_or(name);
+    client
+        .list_tools(group)
+        .ok()?
+        .into_iter()
+        .find(|tool| tool.name == listed_name)
+}
+
+/// Why a call to `name` in `group` would fail, judged by the tools the group advertised:
+/// arguments that do not match its input schema, with each violation and the schema.
+/// `None` when the call can go upstream, also for tools the group did not list, which it
+/// may have added since, and when the group is unavailable, which the call itself reports.
+fn invalid_tool_call(
+    client: &ModularMcpClient,
+    group: &str,
+    name: &str,
+    args: &serde_json::Value,
+) -> Option<String> {
+    let tool = listed_tool(client, group, name)?;
+
+    let errors = argument_errors(&tool.input_schema, args);
+    if errors.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "Invalid arguments for tool {} in group {}:\n- {}\n\nInput schema:\n{}",
+        name,
+        group,
+        errors.join("\n- "),
+        serde_json::to_string_pretty(&tool.input_schema).unwrap_or_default()
+    ))
+}
+
+/// Point out similarly named tools when a call to a tool `group` did not list failed,
+/// either with an error or with an `isError` result
+fn hint_unknown_tool(
+    client: &ModularMcpClient,
+    group: &str,
+    name: &str,
+    outcome: Result<serde_json::Value>,
+) -> Result<serde_json::Value> {
+    let failed = match &outcome {
+        Ok(result) => result.get("isError").and_then(|e| e.as_bool()) == Some(true),
+        Err(_) => true,
+    };
+    if !failed || listed_tool(client, group, name).is_some() {
+        return outcome;
+    }
+    let Ok(tools) = client.list_tools(group) else {
+        return outcome;
+    };
+
+    let suggestions = similar_names(name, tools.iter().map(|tool| tool.name.as_str()));
+    let hint = if suggestions.is_empty() {
+        format!(
+            "Tool {} not found in group {}. Use get_dynamic_tools to list its tools.",
+            name, group
+        )
+    } else {
+        format!(
+            "Tool {} not found in group {}. Did you mean: {}?",
+            name,
+            group,
+            suggestions.join(", ")
+        )
+    };
+    match outcome {
+        Ok(mut result) => {
+            if let Some(content) = result.get_mut("content").and_then(|c| c.as_array_mut()) {
+                content.push(json!({"type": "text", "text": hint}));
             }
+            Ok(result)
         }
+        Err(e) => Err(anyhow::anyhow!("{}\n\n{}", e, hint)),
+    }
+}
 
-        Ok(())
+/// Name a pinned, loaded or flat tool is listed under, `<group>__<tool>`.
+///
+/// Clients commonly accept only [`MAX_TOOL_NAME_LENGTH`] letters, digits, `_` and `-`.
+/// Other characters become `_`, and names that had to change are cut to leave room for a
+/// hash of the full name, so they stay unique and the same across restarts.
+fn namespaced_tool_name(group: &str, tool: &str) -> String {
+    let name = format!("{}{}{}", group, NAMESPACED_TOOL_SEPARATOR, tool);
+    let sanitized: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if sanitized == name && name.len() <= MAX_TOOL_NAME_LENGTH {
+        return name;
+    }
+
+    hashed_tool_name(&sanitized, &name)
+}
+
+/// `sanitized` cut to leave room for a hash of `key` within [`MAX_TOOL_NAME_LENGTH`]
+fn hashed_tool_name(sanitized: &str, key: &str) -> String {
+    let suffix = format!("_{:08x}", name_hash(key));
+    let kept = sanitized.len().min(MAX_TOOL_NAME_LENGTH - suffix.len());
+    // Only ASCII is left, so any byte index is a character boundary
+    format!("{}{}", &sanitized[..kept], suffix)
+}
+
+/// Give every (group, tool) pair its [`namespaced_tool_name`]. Pairs that would share one,
+/// such as `a__b` + `c` and `a` + `b__c`, each get a hash of the pair itself instead.
+fn namespace_tools(tools: Vec<(String, ToolInfo)>) -> Vec<(String, String, ToolInfo)> {
+    let names: Vec<String> = tools
+        .iter()
+        .map(|(group, tool)| namespaced_tool_name(group, &tool.name))
+        .collect();
+    let mut uses: HashMap<&str, usize> = HashMap::new();
+    for name in &names {
+        *uses.entry(name).or_default() += 1;
+    }
+    let shared: HashSet<String> = uses
+        .into_iter()
+        .filter(|(_, count)| *count > 1)
+        .map(|(name, _)| name.to_string())
+        .collect();
+
+    tools
+        .into_iter()
+        .zip(names)
+        .map(|((group, tool), name)| {
+            let name = if shared.contains(&name) {
+                tracing::warn!(
+                    "Tool {} of {} shares the name {} with another tool; adding a hash",
+                    tool.name,
+                    group,
+                    name
+                );
+                hashed_tool_name(&name, &format!("{}\0{}", group, tool.name))
+            } else {
+                name
+            };
+            (name, group, tool)
+        })
+        .collect()
+}
+
+/// 32-bit FNV-1a hash, stable across platforms and Rust versions
+fn name_hash(text: &str) -> u32 {
+    text.bytes().fold(0x811c_9dc5, |hash, byte| {
+        (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193)
+    })
+}
+
+/// A token estimate as shown next to a group, such as `~850 tokens` or `~12.4k tokens`
+fn format_token_estimate(tokens: usize) -> String {
+    if tokens < 1000 {
+        format!("~{} tokens", tokens)
+    } else {
+        format!("~{:.1}k tokens", tokens as f64 / 1000.0)
+    }
+}
+
+/// First line of a tool description, cut at [`SEARCH_DESCRIPTION_LENGTH`] characters
+fn short_description(description: &str) -> String {
+    let first_line = description.trim().lines().next().unwrap_or_default();
+    match first_line.char_indices().nth(SEARCH_DESCRIPTION_LENGTH) {
+        Some((end, _)) => format!("{}…", first_line[..end].trim_end()),
+        None => first_line.to_string(),
+    }
+}
+
+/// Reshape a tool result for clients older than 2025-06-18: `structuredContent` is dropped
+/// (servers also serialize it as text content) and resource links become text
+fn downgrade_tool_result(mut result: serde_json::Value) -> serde_json::Value {
+    if let Some(obj) = result.as_object_mut() {
+        obj.remove("structuredContent");
+    }
+
+    if let Some(content) = result.get_mut("content").and_then(|c| c.as_array_mut()) {
+        for item in content.iter_mut() {
+            if item.get("type").and_then(|t| t.as_str()) != Some("resource_link") {
+                continue;
+            }
+            let uri = item.get("uri").and_then(|u| u.as_str()).unwrap_or_default();
+            let text = match item.get("name").and_then(|n| n.as_str()) {
+                Some(name) => format!("{}: {}", name, uri),
+                None => uri.to_string(),
+            };
+            *item = json!({ "type": "text", "text": text });
+        }
+    }
+
+    result
+}
+
+/// Translate an upstream notification for this session, or `None` if it is meant for another
+async fn relayed_notification(
+    upstream: UpstreamNotification,
+    client: &tokio::sync::RwLock<ModularMcpClient>,
+    subscriptions: &tokio::sync::RwLock<HashSet<String>>,
+) -> Option<serde_json::Value> {
+    let UpstreamNotification {
+        group,
+        mut notification,
+    } = upstream;
+
+    match notification.method.as_str() {
+        "notifications/resources/updated" => {
+            let params = notification.params.as_mut()?;
+            let mut uri = params.get("uri")?.as_str()?.to_string();
+            if client.read().await.namespacing() {
+                uri = namespace_uri(&group, &uri);
+                params["uri"] = json!(uri);
+            }
+            if !subscriptions.read().await.contains(&uri) {
+                return None;
+            }
+            tracing::debug!("Relaying update of {} from {}", uri, group);
+        }
+        _ => return None,
+    }
+
+    Some(json!(notification))
+}
+
+async fn write_message<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    message: &serde_json::Value,
+) -> Result<()> {
+    let message_json = serde_json::to_string(message)?;
+    writer.write_all(message_json.as_bytes()).await?;
+    writer.write_all(b"\n").await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+/// JSON-RPC error response for input that could not be parsed as a message
+pub fn parse_error_response(error: &serde_json::Error) -> JsonRpcResponse {
+    JsonRpcResponse {
+        jsonrpc: "2.0".to_string(),
+        id: serde_json::Value::Null,
+        result: None,
+        error: Some(JsonRpcError {
+            code: -32700,
+            message: format!("Parse error: {}", error),
+            data: None,
+        }),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::schema::{NameFilter, ToolOverride};
+    use crate::proxy::testing::{server_with, FakeServer};
     use crate::proxy::ModularMcpClient;
 
     fn create_test_server() -> ModularMcpServer {
@@ -879,6 +2760,99 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_negotiate_protocol_version() {
+        assert_eq!(negotiate_protocol_version(None), "2024-11-05");
+        assert_eq!(negotiate_protocol_version(Some("2024-11-05")), "2024-11-05");
+        assert_eq!(negotiate_protocol_version(Some("2025-06-18")), "2025-06-18");
+        assert_eq!(negotiate_protocol_version(Some("2025-11-25")), "2025-11-25");
+        assert_eq!(negotiate_protocol_version(Some("2099-01-01")), "2025-11-25");
+    }
+
+    #[tokio::test]
+    async fn test_initialize_echoes_supported_protocol_version() {
+        let server = create_test_server();
+        let request = JsonRpcRequest::new(1, "initialize")
+            .with_params(json!({"protocolVersion": "2025-06-18", "capabilities": {}}));
+
+        let result = server.handle_request(request).await.result.unwrap();
+        assert_eq!(result["protocolVersion"], "2025-06-18");
+        assert_eq!(server.protocol_version(), "2025-06-18");
+        assert!(server.supports_structured_output());
+    }
+
+    #[test]
+    fn test_downgrade_tool_result() {
+        let result = json!({
+            "content": [
+                {"type": "text", "text": "{\"temperature\": 21}"},
+                {"type": "resource_link", "uri": "file:///report.md", "name": "report"}
+            ],
+            "structuredContent": {"temperature": 21}
+        });
+
+        let downgraded = downgrade_tool_result(result);
+        assert!(downgraded.get("structuredContent").is_none());
+        assert_eq!(downgraded["content"][0]["text"], "{\"temperature\": 21}");
+        assert_eq!(
+            downgraded["content"][1],
+            json!({"type": "text", "text": "report: file:///report.md"})
+        );
+    }
+
+    #[tokio::test]
+    async fn test_structured_output_shaped_for_negotiated_version() {
+        let config = FakeServer::new()
+            .respond(
+                "tools/list",
+                json!({"tools": [{
+                    "name": "weather",
+                    "title": "Weather",
+                    "inputSchema": {"type": "object"},
+                    "outputSchema": {"type": "object", "properties": {"temperature": {"type": "number"}}}
+                }]}),
+            )
+            .respond(
+                "tools/call",
+                json!({
+                    "content": [{"type": "text", "text": "{\"temperature\": 21}"}],
+                    "structuredContent": {"temperature": 21}
+                }),
+            )
+            .config();
+        let (_, client) = server_with(&[("weather", config)]).await;
+
+        for (version, structured) in [("2024-11-05", false), ("2025-06-18", true)] {
+            let server = ModularMcpServer::new(
+                client.clone(),
+                "test-server".to_string(),
+                "1.0.0".to_string(),
+            );
+            let initialize = JsonRpcRequest::new(1, "initialize")
+                .with_params(json!({"protocolVersion": version, "capabilities": {}}));
+            server.handle_request(initialize).await;
+
+            let list = JsonRpcRequest::new(2, "tools/call").with_params(json!({
+                "name": "get_dynamic_tools",
+                "arguments": {"group": "weather"}
+            }));
+            let result = server.handle_request(list).await.result.unwrap();
+            let text = result["content"][0]["text"].as_str().unwrap();
+            let tools: serde_json::Value = serde_json::from_str(text).unwrap();
+            assert_eq!(tools[0].get("outputSchema").is_some(), structured);
+            assert_eq!(tools[0].get("title").is_some(), structured);
+
+            let call = JsonRpcRequest::new(3, "tools/call").with_params(json!({
+                "name": "call_dynamic_tool",
+                "arguments": {"group": "weather", "name": "weather"}
+            }));
+            let result = server.handle_request(call).await.result.unwrap();
+            assert_eq!(result.get("structuredContent").is_some(), structured);
+        }
+
+        client.write().await.disconnect_all().await.unwrap();
+    }
+
     #[tokio::test]
     async fn test_handle_list_tools_empty() {
         let server = create_test_server();
@@ -888,17 +2862,826 @@ mod tests {
         assert!(response.error.is_none());
         assert!(response.result.is_some());
 
-        let result = response.result.unwrap();
-        let tools = result.get("tools").unwrap().as_array().unwrap();
-        assert_eq!(tools.len(), 2);
-        assert_eq!(
-            tools[0].get("name").unwrap().as_str().unwrap(),
-            "get_dynamic_tools"
-        );
-        assert_eq!(
-            tools[1].get("name").unwrap().as_str().unwrap(),
-            "call_dynamic_tool"
-        );
+        let result = response.result.unwrap();
+        let tools = result.get("tools").unwrap().as_array().unwrap();
+        assert_eq!(tools.len(), 4);
+        assert_eq!(
+            tools[0].get("name").unwrap().as_str().unwrap(),
+            "get_dynamic_tools"
+        );
+        assert_eq!(
+            tools[1].get("name").unwrap().as_str().unwrap(),
+            "call_dynamic_tool"
+        );
+        assert_eq!(
+            tools[2].get("name").unwrap().as_str().unwrap(),
+            "search_dynamic_tools"
+        );
+        assert_eq!(
+            tools[3].get("name").unwrap().as_str().unwrap(),
+            "call_dynamic_tools"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_search_dynamic_tools_across_groups() {
+        let browser = FakeServer::new()
+            .respond(
+                "tools/list",
+                json!({"tools": [
+                    {"name": "browser_navigate", "description": "Navigate to a URL", "inputSchema": {"type": "object", "$schema": "http://json-schema.org/draft-07/schema#"}},
+                    {"name": "browser_take_screenshot", "description": "Take a screenshot of the page", "inputSchema": {"type": "object"}}
+                ]}),
+            )
+            .config();
+        let github = FakeServer::new()
+            .respond(
+                "tools/list",
+                json!({"tools": [
+                    {"name": "create_issue", "description": "Create a GitHub issue\nLong details follow", "inputSchema": {"type": "object"}}
+                ]}),
+            )
+            .config();
+        let (server, client) = server_with(&[("playwright", browser), ("github", github)]).await;
+
+        let search = |arguments: serde_json::Value| {
+            JsonRpcRequest::new(1, "tools/call")
+                .with_params(json!({"name": "search_dynamic_tools", "arguments": arguments}))
+        };
+        let results = |response: JsonRpcResponse| -> serde_json::Value {
+            let text = response.result.unwrap()["content"][0]["text"]
+                .as_str()
+                .unwrap()
+                .to_string();
+            serde_json::from_str(&text).unwrap()
+        };
+
+        let found = results(
+            server
+                .handle_request(search(
+                    json!({"query": "navigate to url", "include_schemas": 1}),
+                ))
+                .await,
+        );
+        assert_eq!(found[0]["group"], "playwright");
+        assert_eq!(found[0]["name"], "browser_navigate");
+        assert_eq!(found[0]["inputSchema"], json!({"type": "object"}));
+        assert!(found
+            .as_array()
+            .unwrap()
+            .iter()
+            .skip(1)
+            .all(|tool| tool.get("inputSchema").is_none()));
+
+        let found = results(
+            server
+                .handle_request(search(json!({"query": "issue", "limit": 1})))
+                .await,
+        );
+        assert_eq!(
+            found,
+            json!([{"group": "github", "name": "create_issue", "description": "Create a GitHub issue"}])
+        );
+
+        let response = server.handle_request(search(json!({}))).await;
+        assert_eq!(response.error.unwrap().code, -32602);
+
+        client.write().await.disconnect_all().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_call_dynamic_tool_rejects_invalid_arguments_locally() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("requests.log");
+        let config = FakeServer::new()
+            .respond(
+                "tools/list",
+                json!({"tools": [
+                    {"name": "browser_navigate", "inputSchema": {"type": "object", "properties": {"url": {"type": "string"}}, "required": ["url"]}},
+                    {"name": "browser_click", "inputSchema": {"type": "object"}}
+                ]}),
+            )
+            .log_to(&log)
+            .config();
+        let (server, client) = server_with(&[("browser", config)]).await;
+        let call = |name: &str, args: serde_json::Value| {
+            JsonRpcRequest::new(1, "tools/call").with_params(json!({
+                "name": "call_dynamic_tool",
+                "arguments": {"group": "browser", "name": name, "args": args}
+            }))
+        };
+
+        let result = server
+            .handle_request(call("browser_navigate", json!({"url": 42})))
+            .await
+            .result
+            .unwrap();
+        assert_eq!(result["isError"], true);
+        let text = result["content"][0]["text"].as_str().unwrap();
+        assert!(text.contains("args/url: 42 is not of type \"string\""));
+        assert!(text.contains("Input schema:"));
+        assert!(text.contains("\"required\""));
+        let requests = std::fs::read_to_string(&log).unwrap();
+        assert!(!requests.contains("tools/call"));
+
+        client.write().await.disconnect_all().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_call_dynamic_tool_forwards_unlisted_tools() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("requests.log");
+        let config = FakeServer::new()
+            .with_tools(&["browser_navigate"])
+            .respond(
+                "tools/call",
+                json!({"content": [{"type": "text", "text": "Unknown tool"}], "isError": true}),
+            )
+            .log_to(&log)
+            .config();
+        let (server, client) = server_with(&[("browser", config)]).await;
+
+        let request = JsonRpcRequest::new(1, "tools/call").with_params(json!({
+            "name": "call_dynamic_tool",
+            "arguments": {"group": "browser", "name": "browser_navigat", "args": {}}
+        }));
+        let result = server.handle_request(request).await.result.unwrap();
+        assert_eq!(result["isError"], true);
+        assert_eq!(result["content"][0]["text"], "Unknown tool");
+        assert_eq!(
+            result["content"][1]["text"],
+            "Tool browser_navigat not found in group browser. Did you mean: browser_navigate?"
+        );
+        let requests = std::fs::read_to_string(&log).unwrap();
+        assert!(requests.contains("\"name\":\"browser_navigat\""));
+
+        client.write().await.disconnect_all().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_call_dynamic_tool_accepts_override_aliases() {
+        let config = FakeServer::new()
+            .with_tools(&["search_code"])
+            .respond(
+                "tools/call",
+                json!({"content": [{"type": "text", "text": "found"}]}),
+            )
+            .tool_override(
+                "search_code",
+                ToolOverride {
+                    name: Some("github_search".to_string()),
+                    aliases: vec!["code_search".to_string()],
+                    ..Default::default()
+                },
+            )
+            .config();
+        let (server, client) = server_with(&[("github", config)]).await;
+        let call = |name: &str| {
+            JsonRpcRequest::new(1, "tools/call").with_params(json!({
+                "name": "call_dynamic_tool",
+                "arguments": {"group": "github", "name": name, "args": {}}
+            }))
+        };
+
+        for name in ["github_search", "code_search"] {
+            let result = server.handle_request(call(name)).await.result.unwrap();
+            assert_eq!(result["content"][0]["text"], "found");
+        }
+        let result = server
+            .handle_request(call("search_code"))
+            .await
+            .result
+            .unwrap();
+        assert_eq!(result["isError"], true);
+
+        client.write().await.disconnect_all().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_call_dynamic_tool_repairs_malformed_arguments() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("requests.log");
+        let config = FakeServer::new()
+            .respond(
+                "tools/list",
+                json!({"tools": [{"name": "list", "inputSchema": {
+                    "type": "object",
+                    "properties": {"path": {"type": "string"}, "limit": {"type": "integer"}},
+                    "required": ["path"]
+                }}]}),
+            )
+            .respond(
+                "tools/call",
+                json!({"content": [{"type": "text", "text": "ok"}]}),
+            )
+            .log_to(&log)
+            .config();
+        let (server, client) = server_with(&[("fs", config)]).await;
+        client.write().await.set_repair_arguments(true);
+
+        let result = server
+            .handle_request(JsonRpcRequest::new(1, "tools/call").with_params(json!({
+                "name": "call_dynamic_tool",
+                "arguments": {"group": "fs", "name": "list", "args": "{\"limit\": \"5\"}", "path": "/tmp"}
+            })))
+            .await
+            .result
+            .unwrap();
+        assert_eq!(result["content"][0]["text"], "ok");
+        assert_eq!(
+            result["_meta"][ARGUMENT_REPAIRS_META_KEY],
+            json!([
+                "args: parsed from a JSON string",
+                "args/path: moved in from next to group and name",
+                "args/limit: converted \"5\" to integer"
+            ])
+        );
+
+        let requests = std::fs::read_to_string(&log).unwrap();
+        let call = requests
+            .lines()
+            .filter_map(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+            .find(|message| message["method"] == "tools/call")
+            .unwrap();
+        assert_eq!(
+            call["params"]["arguments"],
+            json!({"path": "/tmp", "limit": 5})
+        );
+
+        // Without repair the same call is rejected by validation
+        client.write().await.set_repair_arguments(false);
+        let result = server
+            .handle_request(JsonRpcRequest::new(2, "tools/call").with_params(json!({
+                "name": "call_dynamic_tool",
+                "arguments": {"group": "fs", "name": "list", "args": "{\"limit\": \"5\"}", "path": "/tmp"}
+            })))
+            .await
+            .result
+            .unwrap();
+        assert_eq!(result["isError"], true);
+        assert!(result.get("_meta").is_none());
+
+        client.write().await.disconnect_all().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_call_dynamic_tool_repairs_arguments_of_aliases() {
+        let config = FakeServer::new()
+            .respond(
+                "tools/list",
+                json!({"tools": [{"name": "list", "inputSchema": {
+                    "type": "object",
+                    "properties": {"limit": {"type": "integer"}}
+                }}]}),
+            )
+            .respond(
+                "tools/call",
+                json!({"content": [{"type": "text", "text": "ok"}]}),
+            )
+            .tool_override(
+                "list",
+                ToolOverride {
+                    aliases: vec!["ls".to_string()],
+                    ..Default::default()
+                },
+            )
+            .config();
+        let (server, client) = server_with(&[("fs", config)]).await;
+        client.write().await.set_repair_arguments(true);
+
+        let result = server
+            .handle_request(JsonRpcRequest::new(1, "tools/call").with_params(json!({
+                "name": "call_dynamic_tool",
+                "arguments": {"group": "fs", "name": "ls", "args": {"limit": "5"}}
+            })))
+            .await
+            .result
+            .unwrap();
+        assert_eq!(result["content"][0]["text"], "ok");
+        assert_eq!(
+            result["_meta"][ARGUMENT_REPAIRS_META_KEY],
+            json!(["args/limit: converted \"5\" to integer"])
+        );
+
+        client.write().await.disconnect_all().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_dynamic_loading_lists_opened_groups() {
+        let config = FakeServer::new()
+            .respond(
+                "tools/list",
+                json!({"tools": [
+                    {"name": "search", "description": "Search the web", "inputSchema": {"type": "object"}},
+                    {"name": "fetch", "description": "Fetch a page", "inputSchema": {"type": "object"}}
+                ]}),
+            )
+            .respond(
+                "tools/call",
+                json!({"content": [{"type": "text", "text": "page"}]}),
+            )
+            .config();
+        let (server, client) = server_with(&[("web", config)]).await;
+        client.write().await.set_dynamic_loading(true);
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let server = server.with_outbound(tx);
+
+        let names = |list: serde_json::Value| -> Vec<String> {
+            list["tools"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|tool| tool["name"].as_str().unwrap().to_string())
+                .collect()
+        };
+        let list_tools = || server.handle_request(JsonRpcRequest::new(1, "tools/list"));
+        let call = |name: &str, arguments: serde_json::Value| {
+            JsonRpcRequest::new(2, "tools/call")
+                .with_params(json!({"name": name, "arguments": arguments}))
+        };
+
+        let listed = names(list_tools().await.result.unwrap());
+        assert_eq!(listed.len(), 5);
+        assert_eq!(listed[4], "unload_dynamic_tools");
+
+        server
+            .handle_request(call("get_dynamic_tools", json!({"group": "web"})))
+            .await;
+        assert_eq!(
+            rx.try_recv().unwrap()["method"],
+            "notifications/tools/list_changed"
+        );
+        let listed = names(list_tools().await.result.unwrap());
+        assert_eq!(listed[5..], ["web__search", "web__fetch"]);
+
+        // Opening the group again changes nothing
+        server
+            .handle_request(call("get_dynamic_tools", json!({"group": "web"})))
+            .await;
+        assert!(rx.try_recv().is_err());
+
+        let response = server.handle_request(call("web__fetch", json!({}))).await;
+        assert_eq!(response.result.unwrap()["content"][0]["text"], "page");
+
+        let response = server
+            .handle_request(call("unload_dynamic_tools", json!({})))
+            .await;
+        assert_eq!(
+            response.result.unwrap()["content"][0]["text"],
+            "Unloaded the tools of web"
+        );
+        assert_eq!(
+            rx.try_recv().unwrap()["method"],
+            "notifications/tools/list_changed"
+        );
+        assert_eq!(names(list_tools().await.result.unwrap()).len(), 5);
+        let response = server.handle_request(call("web__fetch", json!({}))).await;
+        assert_eq!(response.error.unwrap().code, -32601);
+
+        client.write().await.disconnect_all().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_pinned_tools_are_listed_and_callable() {
+        let config = FakeServer::new()
+            .respond(
+                "tools/list",
+                json!({"tools": [
+                    {"name": "search", "description": "Search the web", "inputSchema": {"type": "object", "properties": {"query": {"type": "string"}}}},
+                    {"name": "fetch", "description": "Fetch a page", "inputSchema": {"type": "object"}}
+                ]}),
+            )
+            .respond(
+                "tools/call",
+                json!({"content": [{"type": "text", "text": "results"}]}),
+            )
+            .pinned_tools(&["search", "missing"])
+            .config();
+        let (server, client) = server_with(&[("web", config)]).await;
+
+        let list = server
+            .handle_request(JsonRpcRequest::new(1, "tools/list"))
+            .await
+            .result
+            .unwrap();
+        let tools = list["tools"].as_array().unwrap();
+        assert_eq!(tools.len(), 5);
+        assert_eq!(tools[4]["name"], "web__search");
+        assert_eq!(tools[4]["description"], "Search the web");
+        assert_eq!(
+            tools[4]["inputSchema"]["properties"]["query"]["type"],
+            "string"
+        );
+
+        let response = server
+            .handle_request(
+                JsonRpcRequest::new(2, "tools/call")
+                    .with_params(json!({"name": "web__search", "arguments": {"query": "rust"}})),
+            )
+            .await;
+        assert_eq!(response.result.unwrap()["content"][0]["text"], "results");
+
+        // Tools that are not pinned stay behind call_dynamic_tool
+        let response = server
+            .handle_request(
+                JsonRpcRequest::new(3, "tools/call")
+                    .with_params(json!({"name": "web__fetch", "arguments": {}})),
+            )
+            .await;
+        assert_eq!(response.error.unwrap().code, -32601);
+
+        client.write().await.disconnect_all().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_compact_group_discloses_schemas_on_demand() {
+        let tools = json!({"tools": [
+            {"name": "navigate", "description": "Go to a URL\nMore details", "inputSchema": {"type": "object", "properties": {"url": {"type": "string"}}}},
+            {"name": "click", "description": "Click an element", "inputSchema": {"type": "object"}}
+        ]});
+        let compact = FakeServer::new()
+            .respond("tools/list", tools.clone())
+            .tool_schemas(ToolSchemas::Compact)
+            .config();
+        let full = FakeServer::new().respond("tools/list", tools).config();
+        let (server, client) = server_with(&[("browser", compact), ("full", full)]).await;
+
+        let list = server
+            .handle_request(JsonRpcRequest::new(1, "tools/list"))
+            .await
+            .result
+            .unwrap();
+        assert_eq!(list["tools"][1]["name"], "get_dynamic_tool_schema");
+        assert!(list["tools"][1]["description"]
+            .as_str()
+            .unwrap()
+            .contains("browser"));
+
+        let call = |name: &str, arguments: serde_json::Value| {
+            JsonRpcRequest::new(2, "tools/call")
+                .with_params(json!({"name": name, "arguments": arguments}))
+        };
+        let text = |response: JsonRpcResponse| -> serde_json::Value {
+            let result = response.result.unwrap();
+            serde_json::from_str(result["content"][0]["text"].as_str().unwrap()).unwrap()
+        };
+
+        let listed = text(
+            server
+                .handle_request(call("get_dynamic_tools", json!({"group": "browser"})))
+                .await,
+        );
+        assert_eq!(
+            listed,
+            json!([
+                {"name": "navigate", "description": "Go to a URL"},
+                {"name": "click", "description": "Click an element"}
+            ])
+        );
+
+        let listed = text(
+            server
+                .handle_request(call("get_dynamic_tools", json!({"group": "full"})))
+                .await,
+        );
+        assert!(listed[0].get("inputSchema").is_some());
+
+        let schemas = text(
+            server
+                .handle_request(call(
+                    "get_dynamic_tool_schema",
+                    json!({"group": "browser", "names": ["navigate", "missing"]}),
+                ))
+                .await,
+        );
+        assert_eq!(schemas[0]["name"], "navigate");
+        assert_eq!(schemas[0]["description"], "Go to a URL\nMore details");
+        assert_eq!(
+            schemas[0]["inputSchema"]["properties"]["url"]["type"],
+            "string"
+        );
+        assert_eq!(schemas[1]["name"], "missing");
+        assert!(schemas[1]["error"].as_str().unwrap().contains("not found"));
+
+        let response = server
+            .handle_request(call("get_dynamic_tool_schema", json!({"group": "browser"})))
+            .await;
+        assert_eq!(response.error.unwrap().code, -32602);
+
+        client.write().await.disconnect_all().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_schema_compaction_and_token_estimates() {
+        let tools = json!({"tools": [{
+            "name": "navigate",
+            "description": "Go to a URL",
+            "inputSchema": {
+                "type": "object",
+                "title": "NavigateArgs",
+                "additionalProperties": false,
+                "properties": {"url": {"type": "string", "examples": ["https://example.com"]}}
+            }
+        }]});
+        let minified = FakeServer::new()
+            .respond("tools/list", tools.clone())
+            .schema_compaction(SchemaCompaction::Basic)
+            .config();
+        let verbatim = FakeServer::new().respond("tools/list", tools).config();
+        let (server, client) = server_with(&[("minified", minified), ("verbatim", verbatim)]).await;
+
+        let listing = |group: &str| {
+            let server = server.clone();
+            let request = JsonRpcRequest::new(1, "tools/call")
+                .with_params(json!({"name": "get_dynamic_tools", "arguments": {"group": group}}));
+            async move {
+                let result = server.handle_request(request).await.result.unwrap();
+                result["content"][0]["text"].as_str().unwrap().to_string()
+            }
+        };
+
+        let minified = listing("minified").await;
+        assert!(!minified.contains('\n'));
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&minified).unwrap(),
+            json!([{
+                "name": "navigate",
+                "description": "Go to a URL",
+                "inputSchema": {"type": "object", "properties": {"url": {"type": "string"}}}
+            }])
+        );
+        let verbatim = listing("verbatim").await;
+        assert!(verbatim.contains("NavigateArgs"));
+        assert!(verbatim.len() > minified.len());
+
+        let list = server
+            .handle_request(JsonRpcRequest::new(2, "tools/list"))
+            .await
+            .result
+            .unwrap();
+        let description = list["tools"][0]["description"].as_str().unwrap();
+        assert!(description.contains(&format!(
+            "- minified: Fake test server (~{} tokens)",
+            estimate_tokens(&minified)
+        )));
+        assert!(description.contains(&format!(
+            "- verbatim: Fake test server (~{} tokens)",
+            estimate_tokens(&verbatim)
+        )));
+
+        client.write().await.disconnect_all().await.unwrap();
+    }
+
+    #[test]
+    fn test_format_token_estimate() {
+        assert_eq!(format_token_estimate(850), "~850 tokens");
+        assert_eq!(format_token_estimate(12_400), "~12.4k tokens");
+    }
+
+    #[test]
+    fn test_namespaced_tool_name_fits_client_limits() {
+        assert_eq!(namespaced_tool_name("web", "search"), "web__search");
+        assert_eq!(
+            namespaced_tool_name(
+                "github",
+                "list_pull_request_review_comments_with_reactions_and_threads"
+            ),
+            "github__list_pull_request_review_comments_with_reaction_5b8b521e"
+        );
+
+        let long = namespaced_tool_name("github", &"list_pull_request_review_comments_".repeat(3));
+        assert_eq!(long.len(), MAX_TOOL_NAME_LENGTH);
+        assert!(long.starts_with("github__list_pull_request"));
+        assert_eq!(
+            long,
+            namespaced_tool_name("github", &"list_pull_request_review_comments_".repeat(3))
+        );
+        let other =
+            namespaced_tool_name("github", &"list_pull_request_review_comments__".repeat(3));
+        assert_ne!(long, other);
+
+        let dotted = namespaced_tool_name("files", "read.file");
+        assert!(dotted.starts_with("files__read_file_"));
+        assert_ne!(dotted, namespaced_tool_name("files", "read_file"));
+        assert!(dotted
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-'));
+    }
+
+    async fn flat_test_server(
+        global: ToolMode,
+        web_mode: Option<ToolMode>,
+    ) -> (ModularMcpServer, Arc<tokio::sync::RwLock<ModularMcpClient>>) {
+        let mut web = FakeServer::new()
+            .respond(
+                "tools/list",
+                json!({"tools": [
+                    {"name": "search", "description": "Search the web", "inputSchema": {"type": "object"}},
+                    {"name": "fetch", "description": "Fetch a page", "inputSchema": {"type": "object"}}
+                ]}),
+            )
+            .respond(
+                "tools/call",
+                json!({"content": [{"type": "text", "text": "page"}]}),
+            );
+        if let Some(web_mode) = web_mode {
+            web = web.tool_mode(web_mode);
+        }
+        let docs = FakeServer::new()
+            .respond(
+                "tools/list",
+                json!({"tools": [{"name": "lookup", "inputSchema": {"type": "object"}}]}),
+            )
+            .config();
+        let (server, client) = server_with(&[("web", web.config()), ("docs", docs)]).await;
+        client.write().await.set_tool_mode(global);
+        (server, client)
+    }
+
+    fn listed_names(response: JsonRpcResponse) -> Vec<String> {
+        response.result.unwrap()["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|tool| tool["name"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_flat_mode_aggregates_every_tool() {
+        let (server, client) = flat_test_server(ToolMode::Flat, None).await;
+
+        let listed = listed_names(
+            server
+                .handle_request(JsonRpcRequest::new(1, "tools/list"))
+                .await,
+        );
+        assert_eq!(listed, ["docs__lookup", "web__search", "web__fetch"]);
+
+        let response = server
+            .handle_request(
+                JsonRpcRequest::new(2, "tools/call")
+                    .with_params(json!({"name": "web__fetch", "arguments": {}})),
+            )
+            .await;
+        assert_eq!(response.result.unwrap()["content"][0]["text"], "page");
+
+        client.write().await.disconnect_all().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_flat_names_shared_by_two_tools_get_hashes() {
+        let group = |tool: &str, text: &str| {
+            FakeServer::new()
+                .with_tools(&[tool])
+                .respond(
+                    "tools/call",
+                    json!({"content": [{"type": "text", "text": text}]}),
+                )
+                .tool_mode(ToolMode::Flat)
+                .config()
+        };
+        let (server, client) = server_with(&[
+            ("a__b", group("c", "first")),
+            ("a", group("b__c", "second")),
+        ])
+        .await;
+
+        let listed = listed_names(
+            server
+                .handle_request(JsonRpcRequest::new(1, "tools/list"))
+                .await,
+        );
+        assert_eq!(listed.len(), 2);
+        assert_ne!(listed[0], listed[1]);
+        assert!(listed.iter().all(|name| name.starts_with("a__b__c_")));
+
+        // Both stay callable, each in its own group
+        let mut texts = Vec::new();
+        for name in &listed {
+            let response = server
+                .handle_request(
+                    JsonRpcRequest::new(2, "tools/call")
+                        .with_params(json!({"name": name, "arguments": {}})),
+                )
+                .await;
+            texts.push(response.result.unwrap()["content"][0]["text"].clone());
+        }
+        texts.sort_by_key(|text| text.to_string());
+        assert_eq!(texts, [json!("first"), json!("second")]);
+
+        client.write().await.disconnect_all().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_flat_mode_per_group() {
+        let (server, client) = flat_test_server(ToolMode::Dynamic, Some(ToolMode::Flat)).await;
+
+        let list = server
+            .handle_request(JsonRpcRequest::new(1, "tools/list"))
+            .await
+            .result
+            .unwrap();
+        assert_eq!(list["tools"][0]["name"], "get_dynamic_tools");
+        assert_eq!(
+            list["tools"][0]["inputSchema"]["properties"]["group"]["enum"],
+            json!(["docs"])
+        );
+        let tools = list["tools"].as_array().unwrap();
+        assert_eq!(tools[tools.len() - 2]["name"], "web__search");
+        assert_eq!(tools[tools.len() - 1]["name"], "web__fetch");
+
+        // A server can also opt out of a flat top level
+        let (server, client2) = flat_test_server(ToolMode::Flat, Some(ToolMode::Dynamic)).await;
+        let listed = listed_names(
+            server
+                .handle_request(JsonRpcRequest::new(1, "tools/list"))
+                .await,
+        );
+        assert!(listed.contains(&"get_dynamic_tools".to_string()));
+        assert!(listed.contains(&"docs__lookup".to_string()));
+        assert!(!listed.contains(&"web__search".to_string()));
+
+        client.write().await.disconnect_all().await.unwrap();
+        client2.write().await.disconnect_all().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_call_dynamic_tools_runs_groups_concurrently_in_order() {
+        let slow_tool = || {
+            FakeServer::new()
+                .with_tools(&["work"])
+                .respond_after(
+                    "tools/call",
+                    std::time::Duration::from_millis(800),
+                    json!({"content": [{"type": "text", "text": "done"}]}),
+                )
+                .config()
+        };
+        let (server, client) =
+            server_with(&[("first", slow_tool()), ("second", slow_tool())]).await;
+
+        let request = JsonRpcRequest::new(1, "tools/call").with_params(json!({
+            "name": "call_dynamic_tools",
+            "arguments": {"calls": [
+                {"group": "second", "name": "work", "args": {}},
+                {"group": "missing", "name": "work"},
+                {"group": "first", "name": "work"},
+                {"name": "work"}
+            ]}
+        }));
+        let started = std::time::Instant::now();
+        let response = server.handle_request(request).await;
+        assert!(started.elapsed() < std::time::Duration::from_millis(1500));
+
+        let text = response.result.unwrap()["content"][0]["text"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        let results: serde_json::Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(results.as_array().unwrap().len(), 4);
+        assert_eq!(results[0]["group"], "second");
+        assert_eq!(results[0]["result"]["content"][0]["text"], "done");
+        assert_eq!(results[1]["group"], "missing");
+        assert!(results[1]["error"]
+            .as_str()
+            .unwrap()
+            .contains("Tool execution failed"));
+        assert_eq!(results[2]["group"], "first");
+        assert_eq!(results[2]["result"]["content"][0]["text"], "done");
+        assert!(results[3]["error"]
+            .as_str()
+            .unwrap()
+            .contains("Missing required parameters"));
+
+        client.write().await.disconnect_all().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_call_dynamic_tools_rejects_invalid_batches() {
+        let server = create_test_server();
+        let call = |arguments: serde_json::Value| {
+            JsonRpcRequest::new(1, "tools/call")
+                .with_params(json!({"name": "call_dynamic_tools", "arguments": arguments}))
+        };
+
+        let response = server.handle_request(call(json!({}))).await;
+        assert_eq!(response.error.unwrap().code, -32602);
+
+        let calls = vec![json!({"group": "g", "name": "t"}); MAX_BATCH_CALLS + 1];
+        let response = server.handle_request(call(json!({ "calls": calls }))).await;
+        let error = response.error.unwrap();
+        assert_eq!(error.code, -32602);
+        assert!(error.message.contains("Too many calls"));
+    }
+
+    #[test]
+    fn test_short_description() {
+        assert_eq!(short_description("  One line.\nMore.  "), "One line.");
+        let long = "word ".repeat(60);
+        let short = short_description(&long);
+        assert!(short.ends_with('…'));
+        assert!(short.chars().count() <= SEARCH_DESCRIPTION_LENGTH + 1);
     }
 
     #[tokio::test]
@@ -1174,6 +3957,310 @@ mod tests {
         assert!(error.code <= -32600);
     }
 
+    async fn paginated_server() -> (ModularMcpServer, Arc<tokio::sync::RwLock<ModularMcpClient>>) {
+        let alpha = FakeServer::new()
+            .respond_to_cursor(
+                "resources/list",
+                "page-2",
+                json!({"resources": [{"uri": "alpha://2", "name": "a2"}]}),
+            )
+            .respond(
+                "resources/list",
+                json!({"resources": [{"uri": "alpha://1", "name": "a1"}], "nextCursor": "page-2"}),
+            )
+            .respond_to_cursor(
+                "resources/templates/list",
+                "page-2",
+                json!({"resourceTemplates": [{"uriTemplate": "alpha://{b}", "name": "b"}]}),
+            )
+            .respond(
+                "resources/templates/list",
+                json!({"resourceTemplates": [{"uriTemplate": "alpha://{a}", "name": "a"}], "nextCursor": "page-2"}),
+            )
+            .config();
+        let beta = FakeServer::new()
+            .respond(
+                "resources/list",
+                json!({"resources": [{"uri": "beta://1", "name": "b1"}]}),
+            )
+            .respond(
+                "resources/templates/list",
+                json!({"resourceTemplates": [{"uriTemplate": "beta://{c}", "name": "c"}]}),
+            )
+            .config();
+
+        server_with(&[("beta", beta), ("alpha", alpha)]).await
+    }
+
+    /// Walk every page of an aggregated list, returning each page's item names
+    async fn walk_pages(server: &ModularMcpServer, method: &str, key: &str) -> Vec<Vec<String>> {
+        let mut pages = Vec::new();
+        let mut cursor: Option<serde_json::Value> = None;
+        loop {
+            let params = match &cursor {
+                Some(cursor) => json!({ "cursor": cursor }),
+                None => json!({}),
+            };
+            let request = JsonRpcRequest::new(pages.len() + 1, method).with_params(params);
+            let result = server.handle_request(request).await.result.unwrap();
+            pages.push(
+                result[key]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|item| item["name"].as_str().unwrap().to_string())
+                    .collect(),
+            );
+            match result.get("nextCursor") {
+                Some(next) => cursor = Some(next.clone()),
+                None => return pages,
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_aggregated_resources_list_walks_every_page() {
+        let (server, client) = paginated_server().await;
+
+        let pages = walk_pages(&server, "resources/list", "resources").await;
+        assert_eq!(pages, vec![vec!["a1"], vec!["a2", "b1"]]);
+
+        client.write().await.disconnect_all().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_aggregated_templates_list_walks_every_page() {
+        let (server, client) = paginated_server().await;
+
+        let pages = walk_pages(&server, "resources/templates/list", "resourceTemplates").await;
+        assert_eq!(pages, vec![vec!["a"], vec!["b", "c"]]);
+
+        client.write().await.disconnect_all().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_aggregated_list_resumes_after_vanished_group() {
+        let (server, client) = paginated_server().await;
+
+        // "alpha-old" sorts between the two groups, so listing resumes with "beta"
+        let cursor = AggregateCursor {
+            group: "alpha-old".to_string(),
+            cursor: Some("stale".to_string()),
+        }
+        .encode();
+        let request =
+            JsonRpcRequest::new(1, "resources/list").with_params(json!({ "cursor": cursor }));
+        let result = server.handle_request(request).await.result.unwrap();
+        assert_eq!(
+            result["resources"],
+            json!([{"uri": "beta://1", "name": "b1"}])
+        );
+        assert!(result.get("nextCursor").is_none());
+
+        client.write().await.disconnect_all().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_aggregated_list_rejects_invalid_cursor() {
+        let server = create_test_server();
+        let request =
+            JsonRpcRequest::new(1, "prompts/list").with_params(json!({"cursor": "not-a-cursor"}));
+
+        let response = server.handle_request(request).await;
+        assert_eq!(response.error.unwrap().code, -32602);
+    }
+
+    async fn colliding_groups(
+        namespacing: bool,
+    ) -> (ModularMcpServer, Arc<tokio::sync::RwLock<ModularMcpClient>>) {
+        let group = |group: &str| {
+            FakeServer::new()
+                .respond(
+                    "resources/list",
+                    json!({"resources": [{"uri": "file:///README.md", "name": "README"}]}),
+                )
+                .respond(
+                    "resources/read",
+                    json!({"contents": [{"uri": "file:///README.md", "text": group}]}),
+                )
+                .respond("prompts/list", json!({"prompts": [{"name": "review"}]}))
+                .respond("prompts/get", json!({"description": group, "messages": []}))
+                .config()
+        };
+        let (server, client) =
+            server_with(&[("docs", group("docs")), ("notes", group("notes"))]).await;
+        client.write().await.set_namespacing(namespacing);
+        (server, client)
+    }
+
+    #[tokio::test]
+    async fn test_namespacing_tells_colliding_groups_apart() {
+        let (server, client) = colliding_groups(true).await;
+
+        let list = server
+            .handle_request(JsonRpcRequest::new(1, "resources/list"))
+            .await
+            .result
+            .unwrap();
+        let uris: Vec<_> = list["resources"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| r["uri"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            uris,
+            vec![
+                "dmcp://docs/file:///README.md",
+                "dmcp://notes/file:///README.md"
+            ]
+        );
+
+        let read = JsonRpcRequest::new(2, "resources/read")
+            .with_params(json!({"uri": "dmcp://notes/file:///README.md"}));
+        let result = server.handle_request(read).await.result.unwrap();
+        assert_eq!(result["contents"][0]["text"], "notes");
+        assert_eq!(
+            result["contents"][0]["uri"],
+            "dmcp://notes/file:///README.md"
+        );
+
+        let prompts = server
+            .handle_request(JsonRpcRequest::new(3, "prompts/list"))
+            .await
+            .result
+            .unwrap();
+        assert_eq!(
+            prompts["prompts"],
+            json!([{"name": "docs__review"}, {"name": "notes__review"}])
+        );
+
+        let get =
+            JsonRpcRequest::new(4, "prompts/get").with_params(json!({"name": "notes__review"}));
+        let result = server.handle_request(get).await.result.unwrap();
+        assert_eq!(result["description"], "notes");
+
+        client.write().await.disconnect_all().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_identifiers_unchanged_without_namespacing() {
+        let (server, client) = colliding_groups(false).await;
+
+        let prompts = server
+            .handle_request(JsonRpcRequest::new(1, "prompts/list"))
+            .await
+            .result
+            .unwrap();
+        assert_eq!(
+            prompts["prompts"],
+            json!([{"name": "review"}, {"name": "review"}])
+        );
+
+        // Namespaced names are not recognized unless namespacing is enabled
+        let get =
+            JsonRpcRequest::new(2, "prompts/get").with_params(json!({"name": "notes__review"}));
+        assert!(server.handle_request(get).await.error.is_some());
+
+        client.write().await.disconnect_all().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_namespaced_references_respect_filters() {
+        let config = FakeServer::new()
+            .respond(
+                "prompts/list",
+                json!({"prompts": [{"name": "review"}, {"name": "internal_triage"}]}),
+            )
+            .respond(
+                "resources/templates/list",
+                json!({"resourceTemplates": [
+                    {"uriTemplate": "file:///docs/{name}", "name": "docs"},
+                    {"uriTemplate": "file:///{+path}", "name": "anything"}
+                ]}),
+            )
+            .respond("prompts/get", json!({"messages": []}))
+            .prompt_filter(NameFilter {
+                allow: Vec::new(),
+                deny: vec!["internal_*".to_string()],
+            })
+            .resource_template_filter(NameFilter {
+                allow: vec!["file:///docs/*".to_string()],
+                deny: Vec::new(),
+            })
+            .config();
+        let (server, client) = server_with(&[("files", config)]).await;
+        client.write().await.set_namespacing(true);
+        let complete = |reference: serde_json::Value| {
+            JsonRpcRequest::new(1, "completion/complete").with_params(json!({
+                "ref": reference,
+                "argument": {"name": "name", "value": "a"}
+            }))
+        };
+
+        let get =
+            |name: &str| JsonRpcRequest::new(2, "prompts/get").with_params(json!({ "name": name }));
+        assert!(server
+            .handle_request(get("files__review"))
+            .await
+            .error
+            .is_none());
+        let response = server.handle_request(get("files__internal_triage")).await;
+        assert_eq!(response.error.unwrap().code, -32602);
+
+        for reference in [
+            json!({"type": "ref/prompt", "name": "files__internal_triage"}),
+            json!({"type": "ref/resource", "uri": "dmcp://files/file:///{+path}"}),
+        ] {
+            let response = server.handle_request(complete(reference)).await;
+            assert_eq!(response.error.unwrap().code, -32602);
+        }
+        let response = server
+            .handle_request(complete(
+                json!({"type": "ref/resource", "uri": "dmcp://files/file:///docs/{name}"}),
+            ))
+            .await;
+        assert!(response.error.is_none());
+
+        client.write().await.disconnect_all().await.unwrap();
+    }
+
+    #[test]
+    fn test_split_namespaced_identifiers() {
+        let groups = vec!["a".to_string(), "a__b".to_string()];
+
+        assert_eq!(
+            split_namespaced_prompt("a__b__c", &groups),
+            Some(("a__b".to_string(), "c"))
+        );
+        assert_eq!(
+            split_namespaced_prompt("a__c", &groups),
+            Some(("a".to_string(), "c"))
+        );
+        assert_eq!(split_namespaced_prompt("other__c", &groups), None);
+
+        assert_eq!(
+            split_namespaced_uri("dmcp://a/https://example.com/x", &groups),
+            Some(("a".to_string(), "https://example.com/x"))
+        );
+        assert_eq!(
+            split_namespaced_uri("dmcp://missing/file:///x", &groups),
+            None
+        );
+        assert_eq!(split_namespaced_uri("file:///x", &groups), None);
+    }
+
+    #[test]
+    fn test_aggregate_cursor_round_trip() {
+        let cursor = AggregateCursor {
+            group: "files".to_string(),
+            cursor: Some("upstream-page-3".to_string()),
+        };
+        assert_eq!(AggregateCursor::decode(&cursor.encode()), Some(cursor));
+        assert_eq!(AggregateCursor::decode("zz"), None);
+        assert_eq!(AggregateCursor::decode("abc"), None);
+    }
+
     #[tokio::test]
     async fn test_resources_read_protocol_compliance() {
         let server = create_test_server();
@@ -1212,6 +4299,38 @@ mod tests {
             resources_cap.get("subscribe").is_some(),
             "Resources should declare subscribe capability"
         );
+        assert_eq!(capabilities["tools"]["listChanged"], true);
+    }
+
+    #[tokio::test]
+    async fn test_group_changes_notify_tools_list_changed() {
+        let client = Arc::new(tokio::sync::RwLock::new(ModularMcpClient::new()));
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let server = ModularMcpServer::new(
+            client.clone(),
+            "test-server".to_string(),
+            "1.0.0".to_string(),
+        )
+        .with_outbound(tx);
+        let notifier = server.spawn_notifier().unwrap();
+
+        // Let the notifier subscribe before the group set changes
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        client
+            .write()
+            .await
+            .connect("fake".to_string(), FakeServer::new().config())
+            .await
+            .unwrap();
+
+        let notification = tokio::time::timeout(std::time::Duration::from_secs(5), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(notification["method"], "notifications/tools/list_changed");
+
+        notifier.abort();
+        client.write().await.disconnect_all().await.unwrap();
     }
 
     #[tokio::test]
@@ -1326,6 +4445,125 @@ mod tests {
         );
     }
 
+    fn completion_group(capabilities: serde_json::Value) -> crate::config::McpServerConfig {
+        FakeServer::new()
+            .respond(
+                "initialize",
+                json!({"protocolVersion": "2025-06-18", "capabilities": capabilities}),
+            )
+            .respond("prompts/list", json!({"prompts": [{"name": "greet"}]}))
+            .respond(
+                "resources/templates/list",
+                json!({"resourceTemplates": [{"uriTemplate": "file:///{path}", "name": "files"}]}),
+            )
+            .respond(
+                "completion/complete",
+                json!({"completion": {"values": ["Alice"], "hasMore": false}}),
+            )
+            .config()
+    }
+
+    #[tokio::test]
+    async fn test_completion_routed_to_owning_group() {
+        let (server, client) =
+            server_with(&[("names", completion_group(json!({"completions": {}})))]).await;
+
+        let initialize = server
+            .handle_request(JsonRpcRequest::new(1, "initialize"))
+            .await;
+        assert!(initialize.result.unwrap()["capabilities"]["completions"].is_object());
+
+        for reference in [
+            json!({"type": "ref/prompt", "name": "greet"}),
+            json!({"type": "ref/resource", "uri": "file:///{path}"}),
+        ] {
+            let request = JsonRpcRequest::new(2, "completion/complete").with_params(json!({
+                "ref": reference,
+                "argument": {"name": "name", "value": "A"}
+            }));
+            let response = server.handle_request(request).await;
+            assert!(response.error.is_none(), "{:?}", response.error);
+            assert_eq!(
+                response.result.unwrap()["completion"]["values"],
+                json!(["Alice"])
+            );
+        }
+
+        client.write().await.disconnect_all().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_completion_prefers_group_with_capability() {
+        let plain = FakeServer::new()
+            .respond("prompts/list", json!({"prompts": [{"name": "greet"}]}))
+            .respond(
+                "resources/templates/list",
+                json!({"resourceTemplates": [{"uriTemplate": "file:///{path}", "name": "files"}]}),
+            )
+            .config();
+        let (server, client) = server_with(&[
+            ("names", completion_group(json!({"completions": {}}))),
+            ("a-plain", plain),
+        ])
+        .await;
+
+        for reference in [
+            json!({"type": "ref/prompt", "name": "greet"}),
+            json!({"type": "ref/resource", "uri": "file:///{path}"}),
+        ] {
+            let request = JsonRpcRequest::new(2, "completion/complete").with_params(json!({
+                "ref": reference,
+                "argument": {"name": "name", "value": "A"}
+            }));
+            let response = server.handle_request(request).await;
+            assert_eq!(
+                response.result.unwrap()["completion"]["values"],
+                json!(["Alice"])
+            );
+        }
+
+        client.write().await.disconnect_all().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_completion_empty_for_group_without_capability() {
+        let (server, client) = server_with(&[("names", completion_group(json!({})))]).await;
+
+        let initialize = server
+            .handle_request(JsonRpcRequest::new(1, "initialize"))
+            .await;
+        assert!(initialize.result.unwrap()["capabilities"]
+            .get("completions")
+            .is_none());
+
+        let request = JsonRpcRequest::new(2, "completion/complete").with_params(json!({
+            "ref": {"type": "ref/prompt", "name": "greet"},
+            "argument": {"name": "name", "value": "A"}
+        }));
+        let response = server.handle_request(request).await;
+        assert_eq!(response.result.unwrap()["completion"]["values"], json!([]));
+
+        client.write().await.disconnect_all().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_completion_invalid_reference() {
+        let server = create_test_server();
+
+        let missing_ref = JsonRpcRequest::new(1, "completion/complete")
+            .with_params(json!({"argument": {"name": "name", "value": ""}}));
+        let response = server.handle_request(missing_ref).await;
+        assert_eq!(response.error.unwrap().code, -32602);
+
+        let unknown_prompt = JsonRpcRequest::new(2, "completion/complete").with_params(json!({
+            "ref": {"type": "ref/prompt", "name": "nope"},
+            "argument": {"name": "name", "value": ""}
+        }));
+        let error = server.handle_request(unknown_prompt).await.error.unwrap();
+        assert_eq!(error.code, -32602);
+        assert!(error.message.contains("Prompt not found"));
+    }
+
     #[tokio::test]
     async fn test_batch_request_parsing() {
         use crate::proxy::types::JsonRpcMessage;
@@ -1344,7 +4582,7 @@ mod tests {
                 assert_eq!(requests[0].method, "initialize");
                 assert_eq!(requests[1].method, "tools/list");
             }
-            JsonRpcMessage::Request(_) => panic!("Expected batch, got single request"),
+            _ => panic!("Expected batch, got single request"),
         }
     }
 
@@ -1361,7 +4599,7 @@ mod tests {
                 assert_eq!(request.method, "initialize");
                 assert_eq!(request.id, serde_json::json!(1));
             }
-            JsonRpcMessage::Batch(_) => panic!("Expected single request, got batch"),
+            _ => panic!("Expected single request, got batch"),
         }
     }
 
@@ -1388,7 +4626,7 @@ mod tests {
                 // Third is normal request
                 assert_eq!(requests[2].id, serde_json::json!(2));
             }
-            JsonRpcMessage::Request(_) => panic!("Expected batch, got single request"),
+            _ => panic!("Expected batch, got single request"),
         }
     }
 
@@ -1420,7 +4658,7 @@ mod tests {
             Ok(JsonRpcMessage::Batch(requests)) => {
                 assert_eq!(requests.len(), 0);
             }
-            Ok(JsonRpcMessage::Request(_)) => {
+            Ok(_) => {
                 panic!("Expected batch, got single request");
             }
             Err(_) => {
@@ -1429,6 +4667,405 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_handle_message_notification_has_no_response() {
+        let server = create_test_server();
+        let message: JsonRpcMessage =
+            serde_json::from_str(r#"{"jsonrpc": "2.0", "method": "notifications/initialized"}"#)
+                .unwrap();
+
+        assert!(server.handle_message(message).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_handle_message_batch_skips_notifications() {
+        let server = create_test_server();
+        let message: JsonRpcMessage = serde_json::from_str(
+            r#"[
+                {"jsonrpc": "2.0", "id": 1, "method": "initialize"},
+                {"jsonrpc": "2.0", "method": "notifications/initialized"},
+                {"jsonrpc": "2.0", "id": 2, "method": "tools/list"}
+            ]"#,
+        )
+        .unwrap();
+
+        let response = server.handle_message(message).await.unwrap();
+        let responses = response.as_array().unwrap();
+        assert_eq!(responses.len(), 2);
+        assert_eq!(responses[0]["id"], 1);
+        assert_eq!(responses[1]["id"], 2);
+    }
+
+    #[tokio::test]
+    async fn test_handle_message_empty_batch_is_error() {
+        let server = create_test_server();
+        let response = server
+            .handle_message(JsonRpcMessage::Batch(Vec::new()))
+            .await
+            .unwrap();
+
+        assert_eq!(response["error"]["code"], -32600);
+    }
+
+    async fn serve_lines(server: &ModularMcpServer, input: &str) -> Vec<serde_json::Value> {
+        let mut output = Vec::new();
+        server
+            .serve_stream(input.as_bytes(), &mut output)
+            .await
+            .unwrap();
+
+        String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_serve_stream_answers_every_request() {
+        let server = create_test_server();
+        let input = concat!(
+            r#"{"jsonrpc": "2.0", "id": 1, "method": "initialize"}"#,
+            "\n",
+            r#"{"jsonrpc": "2.0", "method": "notifications/initialized"}"#,
+            "\n",
+            r#"{"jsonrpc": "2.0", "id": 2, "method": "tools/list"}"#,
+            "\n",
+            r#"{"jsonrpc": "2.0", "id": 3, "method": "prompts/list"}"#,
+            "\n",
+        );
+
+        let responses = serve_lines(&server, input).await;
+        let mut ids: Vec<_> = responses
+            .iter()
+            .map(|r| r["id"].as_i64().unwrap())
+            .collect();
+        ids.sort();
+
+        assert_eq!(ids, vec![1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_serve_stream_reports_parse_errors() {
+        let server = create_test_server();
+        let responses = serve_lines(&server, "not json\n").await;
+
+        assert_eq!(responses.len(), 1);
+        assert_eq!(responses[0]["error"]["code"], -32700);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_request_limit_waits_for_free_slot() {
+        let server = create_test_server().with_max_concurrent_requests(1);
+        let permit = server.in_flight.clone().acquire_owned().await.unwrap();
+
+        let pending = tokio::spawn({
+            let server = server.clone();
+            async move {
+                server
+                    .handle_message(JsonRpcMessage::Request(JsonRpcRequest::new(
+                        1,
+                        "tools/list",
+                    )))
+                    .await
+            }
+        });
+
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert!(!pending.is_finished());
+
+        drop(permit);
+        let response = pending.await.unwrap().unwrap();
+        assert_eq!(response["id"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_serve_stream_holds_requests_until_a_slot_frees() {
+        let server = create_test_server().with_max_concurrent_requests(1);
+        let permit = server.in_flight.clone().acquire_owned().await.unwrap();
+        let input = concat!(
+            r#"{"jsonrpc": "2.0", "id": 1, "method": "tools/list"}"#,
+            "\n",
+            r#"{"jsonrpc": "2.0", "id": 2, "method": "prompts/list"}"#,
+            "\n",
+        );
+
+        let serving = tokio::spawn({
+            let server = server.clone();
+            async move { serve_lines(&server, input).await }
+        });
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert!(!serving.is_finished());
+
+        drop(permit);
+        let responses = serving.await.unwrap();
+        let ids: Vec<_> = responses.iter().map(|r| r["id"].clone()).collect();
+        assert_eq!(ids, vec![json!(1), json!(2)]);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_tool_call_is_aborted_and_forwarded_upstream() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("requests.log");
+        let config = FakeServer::new()
+            .with_tools(&["wait"])
+            .never_respond("tools/call")
+            .log_to(&log)
+            .config();
+        let (server, client) = server_with(&[("slow", config)]).await;
+
+        let call = tokio::spawn({
+            let server = server.clone();
+            async move {
+                let request = JsonRpcRequest::new(7, "tools/call").with_params(json!({
+                    "name": "call_dynamic_tool",
+                    "arguments": {"group": "slow", "name": "wait"}
+                }));
+                server
+                    .handle_message(JsonRpcMessage::Request(request))
+                    .await
+            }
+        });
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+        let cancel = JsonRpcRequest::notification("notifications/cancelled")
+            .with_params(json!({"requestId": 7, "reason": "user aborted"}));
+        assert!(server
+            .handle_message(JsonRpcMessage::Request(cancel))
+            .await
+            .is_none());
+
+        let response = tokio::time::timeout(std::time::Duration::from_secs(5), call)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(response.is_none(), "Cancelled requests are not answered");
+
+        // The fake server logs the notification asynchronously
+        let mut messages = Vec::new();
+        for _ in 0..50 {
+            messages = std::fs::read_to_string(&log)
+                .unwrap()
+                .lines()
+                .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+                .collect();
+            if messages
+                .iter()
+                .any(|m| m["method"] == "notifications/cancelled")
+            {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        let upstream_call = messages
+            .iter()
+            .find(|m| m["method"] == "tools/call")
+            .unwrap();
+        let cancellation = messages
+            .iter()
+            .find(|m| m["method"] == "notifications/cancelled")
+            .unwrap();
+        assert_eq!(cancellation["params"]["requestId"], upstream_call["id"]);
+        assert_eq!(cancellation["params"]["reason"], "user aborted");
+
+        client.write().await.disconnect_all().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_cancellation_of_unknown_request_is_ignored() {
+        let server = create_test_server();
+        let cancel = JsonRpcRequest::notification("notifications/cancelled")
+            .with_params(json!({"requestId": 42}));
+
+        assert!(server
+            .handle_message(JsonRpcMessage::Request(cancel))
+            .await
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_tool_call_progress_relayed_under_client_token() {
+        let config = FakeServer::new()
+            .with_tools(&["work"])
+            .report_progress("tools/call", 2, std::time::Duration::from_millis(50))
+            .config();
+        let (server, client) = server_with(&[("busy", config)]).await;
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let server = server.with_outbound(tx);
+
+        let request = JsonRpcRequest::new(3, "tools/call").with_params(json!({
+            "name": "call_dynamic_tool",
+            "arguments": {"group": "busy", "name": "work"},
+            "_meta": {"progressToken": "client-token"}
+        }));
+        let response = server
+            .handle_message(JsonRpcMessage::Request(request))
+            .await
+            .unwrap();
+        assert_eq!(response["id"], 3);
+        assert!(response.get("error").is_none());
+
+        // Progress is flushed before the response is returned
+        let mut progress = Vec::new();
+        while let Ok(notification) = rx.try_recv() {
+            progress.push(notification);
+        }
+        assert_eq!(progress.len(), 2);
+        for (step, notification) in progress.iter().enumerate() {
+            assert_eq!(notification["method"], "notifications/progress");
+            assert_eq!(notification["params"]["progressToken"], "client-token");
+            assert_eq!(notification["params"]["progress"], step + 1);
+        }
+
+        client.write().await.disconnect_all().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_batch_calls_get_own_progress_tokens() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("messages.log");
+        let config = FakeServer::new()
+            .with_tools(&["work"])
+            .report_progress("tools/call", 2, std::time::Duration::from_millis(10))
+            .log_to(&log)
+            .config();
+        let (server, client) = server_with(&[("busy", config)]).await;
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let server = server.with_outbound(tx);
+
+        let request = JsonRpcRequest::new(3, "tools/call").with_params(json!({
+            "name": "call_dynamic_tools",
+            "arguments": {"calls": [
+                {"group": "busy", "name": "work"},
+                {"group": "busy", "name": "work"}
+            ]},
+            "_meta": {"progressToken": "client-token"}
+        }));
+        assert!(server.handle_request(request).await.error.is_none());
+
+        // Each call reports half its work done, then all of it
+        let mut progress = Vec::new();
+        while let Ok(notification) = rx.try_recv() {
+            assert_eq!(notification["params"]["progressToken"], "client-token");
+            assert_eq!(notification["params"]["total"], 2);
+            progress.push(notification["params"]["progress"].as_f64().unwrap());
+        }
+        assert_eq!(progress, [0.5, 1.0, 1.5, 2.0]);
+
+        let calls: Vec<_> = std::fs::read_to_string(&log)
+            .unwrap()
+            .lines()
+            .filter_map(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+            .filter(|m| m["method"] == "tools/call")
+            .collect();
+        assert_eq!(calls.len(), 2);
+        let tokens: Vec<_> = calls
+            .iter()
+            .map(|call| call["params"]["_meta"]["progressToken"].clone())
+            .collect();
+        assert_eq!(tokens[0], calls[0]["id"]);
+        assert_ne!(tokens[0], tokens[1]);
+
+        client.write().await.disconnect_all().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_upstream_sampling_request_relayed_to_client_and_answered() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("messages.log");
+        let (server, client) = server_with(&[]).await;
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let server = server.with_outbound(tx);
+
+        let initialize = JsonRpcRequest::new(1, "initialize")
+            .with_params(json!({"capabilities": {"sampling": {}}}));
+        assert!(server.handle_request(initialize).await.error.is_none());
+
+        let config = FakeServer::new()
+            .with_tools(&["draft"])
+            .respond_and_notify(
+                "tools/call",
+                json!({}),
+                json!({
+                    "jsonrpc": "2.0",
+                    "id": 5,
+                    "method": "sampling/createMessage",
+                    "params": {"messages": [], "maxTokens": 10}
+                }),
+            )
+            .log_to(&log)
+            .config();
+        client
+            .write()
+            .await
+            .connect("writer".to_string(), config)
+            .await
+            .unwrap();
+
+        let call = JsonRpcRequest::new(2, "tools/call").with_params(json!({
+            "name": "call_dynamic_tool",
+            "arguments": {"group": "writer", "name": "draft"}
+        }));
+        assert!(server.handle_request(call).await.error.is_none());
+
+        let relayed = tokio::time::timeout(std::time::Duration::from_secs(5), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(relayed["method"], "sampling/createMessage");
+        assert_ne!(relayed["id"], 5, "Relayed requests get their own id");
+
+        let answer = serde_json::from_value(json!({
+            "jsonrpc": "2.0",
+            "id": relayed["id"],
+            "result": {"role": "assistant", "content": {"type": "text", "text": "hi"}}
+        }))
+        .unwrap();
+        assert!(server.handle_message(answer).await.is_none());
+
+        let answered = crate::proxy::testing::wait_for_logged(&log, |m| {
+            m["id"] == 5 && m.get("method").is_none()
+        })
+        .await
+        .unwrap();
+        assert_eq!(answered["result"]["content"]["text"], "hi");
+
+        server.end_session().await;
+        client.write().await.disconnect_all().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_response_parses_as_message() {
+        let message: JsonRpcMessage =
+            serde_json::from_str(r#"{"jsonrpc": "2.0", "id": "a-1", "result": {}}"#).unwrap();
+        assert!(matches!(message, JsonRpcMessage::Response(_)));
+
+        // Unknown responses are dropped without an answer
+        assert!(create_test_server().handle_message(message).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_malformed_messages_are_invalid_requests() {
+        let server = create_test_server();
+        for (raw, id) in [
+            (r#"{"jsonrpc": "2.0", "id": 1, "method": 5}"#, json!(1)),
+            (
+                r#"{"jsonrpc": "2.0", "id": "a", "result": {}, "error": {"code": 1, "message": "x"}}"#,
+                json!("a"),
+            ),
+            (r#"{"jsonrpc": "2.0", "id": 2}"#, json!(2)),
+            (r#"{"id": {"nested": true}}"#, json!(null)),
+            ("42", json!(null)),
+        ] {
+            let message: JsonRpcMessage = serde_json::from_str(raw).unwrap();
+            assert!(matches!(message, JsonRpcMessage::Invalid(_)), "{}", raw);
+
+            let response = server.handle_message(message).await.unwrap();
+            assert_eq!(response["error"]["code"], -32600, "{}", raw);
+            assert_eq!(response["id"], id, "{}", raw);
+        }
+    }
+
     #[tokio::test]
     async fn test_resources_subscribe_with_uri() {
         let server = create_test_server();
@@ -1523,6 +5160,47 @@ mod tests {
         assert!(!subs.contains("file:///test.txt"));
     }
 
+    #[tokio::test]
+    async fn test_resource_updates_relayed_to_subscribed_session() {
+        let uri = "file:///watched.txt";
+        let config = FakeServer::new()
+            .respond(
+                "resources/list",
+                json!({"resources": [{"uri": uri, "name": "watched"}]}),
+            )
+            .respond_and_notify(
+                "resources/subscribe",
+                json!({}),
+                json!({
+                    "jsonrpc": "2.0",
+                    "method": "notifications/resources/updated",
+                    "params": {"uri": uri}
+                }),
+            )
+            .config();
+        let (server, client) = server_with(&[("files", config)]).await;
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let server = server.with_outbound(tx);
+        let notifier = server.spawn_notifier().unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+
+        let request =
+            JsonRpcRequest::new(1, "resources/subscribe").with_params(json!({ "uri": uri }));
+        let response = server.handle_request(request).await;
+        assert!(response.error.is_none());
+
+        let notification = tokio::time::timeout(std::time::Duration::from_secs(5), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(notification["method"], "notifications/resources/updated");
+        assert_eq!(notification["params"]["uri"], uri);
+
+        notifier.abort();
+        server.release_subscriptions().await;
+        client.write().await.disconnect_all().await.unwrap();
+    }
+
     #[tokio::test]
     async fn test_multiple_subscriptions() {
         let server = create_test_server();